@@ -1,5 +1,5 @@
 #!/bin/bash
 set -e
 
-RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release
-cp target/wasm32-unknown-unknown/release/game-of-life.wasm ./res/
\ No newline at end of file
+RUSTFLAGS='-C link-arg=-s -C target-cpu=mvp' cargo build --target wasm32-unknown-unknown --release
+cp target/wasm32-unknown-unknown/release/game_of_life.wasm ./res/game-of-life.wasm
@@ -0,0 +1,2 @@
+//! Sandbox tests for the game of life contract live in `tests/`. Run `../build.sh` first so
+//! `../res/game-of-life.wasm` is up to date, then `cargo test` from this directory.
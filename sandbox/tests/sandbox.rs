@@ -0,0 +1,133 @@
+//! Deploys the contract wasm to a sandbox node and measures what calls really cost, wasm
+//! execution included. The mocked runner in `src/simulation.rs` only sees host calls, so gas
+//! ceilings for the stepping engines are checked here.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use near_workspaces::{Account, Contract};
+use serde_json::{json, Value};
+
+const WASM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../res/game-of-life.wasm");
+
+/// Most gas one `step` of a default 16x16 board may burn, wasm included.
+const STEP_GAS_CEILING: u64 = 30_000_000_000_000;
+
+/// Cells of a 16x16 board, one bit per cell in row-major order.
+fn field(cells: &[(usize, usize)]) -> String {
+    let mut bytes = vec![0u8; 32];
+    for &(x, y) in cells {
+        let bit = y * 16 + x;
+        bytes[bit / 8] |= 1 << (bit % 8);
+    }
+    STANDARD.encode(bytes)
+}
+
+async fn deploy() -> anyhow::Result<(Contract, Account)> {
+    let worker = near_workspaces::sandbox().await?;
+    let contract = worker.dev_deploy(&std::fs::read(WASM)?).await?;
+    contract.call("new").transact().await?.into_result()?;
+    let user = worker.dev_create_account().await?;
+    Ok((contract, user))
+}
+
+async fn create_board(contract: &Contract, user: &Account, cells: &[(usize, usize)]) -> anyhow::Result<u64> {
+    let outcome = user
+        .call(contract.id(), "create_board")
+        .args_json(json!({ "field": field(cells) }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(outcome.json()?)
+}
+
+/// Steps board `index` once and returns the gas the whole transaction burnt.
+async fn step(contract: &Contract, user: &Account, index: u64) -> anyhow::Result<u64> {
+    let outcome = user
+        .call(contract.id(), "step")
+        .args_json(json!({ "index": index }))
+        .max_gas()
+        .transact()
+        .await?;
+    let gas = outcome.total_gas_burnt.as_gas();
+    outcome.into_result()?;
+    Ok(gas)
+}
+
+async fn get_board(contract: &Contract, index: u64) -> anyhow::Result<Value> {
+    Ok(contract.view("get_board").args_json(json!({ "index": index })).await?.json()?)
+}
+
+#[tokio::test]
+async fn test_glider_steps_under_gas_ceiling() -> anyhow::Result<()> {
+    let (contract, user) = deploy().await?;
+    let index = create_board(&contract, &user, &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]).await?;
+    for generation in 1..=20 {
+        let gas = step(&contract, &user, index).await?;
+        assert!(gas <= STEP_GAS_CEILING, "step {} burnt {} gas", generation, gas);
+    }
+    let board = get_board(&contract, index).await?;
+    assert_eq!(board["generation"], 20);
+    assert!(board["last_step"]["gas"].as_u64().unwrap() > 0);
+
+    let spaceship: Value = contract.view("get_spaceship").args_json(json!({ "index": index })).await?.json()?;
+    assert_eq!(spaceship["known_as"], "glider");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dense_board_steps_under_gas_ceiling() -> anyhow::Result<()> {
+    let (contract, user) = deploy().await?;
+    let cells: Vec<(usize, usize)> = (0..16)
+        .flat_map(|y| (0..16).map(move |x| (x, y)))
+        .filter(|&(x, y)| (x * 7 + y * 3) % 5 < 2)
+        .collect();
+    let index = create_board(&contract, &user, &cells).await?;
+    for generation in 1..=5 {
+        let gas = step(&contract, &user, index).await?;
+        assert!(gas <= STEP_GAS_CEILING, "step {} burnt {} gas", generation, gas);
+    }
+    assert_eq!(get_board(&contract, index).await?["generation"], 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_step_many_reports_missing_boards() -> anyhow::Result<()> {
+    let (contract, user) = deploy().await?;
+    let blinker = create_board(&contract, &user, &[(4, 4), (5, 4), (6, 4)]).await?;
+    let result: Value = user
+        .call(contract.id(), "step_many")
+        .args_json(json!({ "indices": [blinker, 7], "cursor": null }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?
+        .json()?;
+    assert_eq!(result["processed"], json!([blinker]));
+    assert_eq!(result["failed"], json!([7]));
+    assert_eq!(get_board(&contract, blinker).await?["generation"], 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engines_report_gas() -> anyhow::Result<()> {
+    let (contract, user) = deploy().await?;
+    let cells: Vec<(usize, usize)> = (0..16).flat_map(|y| (0..8).map(move |x| (x * 2 + y % 2, y))).collect();
+    let mut boards = vec![];
+    for engine in ["Naive", "Sparse", "BitParallel", "HashLife"] {
+        let index = create_board(&contract, &user, &cells).await?;
+        user.call(contract.id(), "set_engine_hint")
+            .args_json(json!({ "index": index, "hint": { "Always": engine } }))
+            .transact()
+            .await?
+            .into_result()?;
+        let gas = step(&contract, &user, index).await?;
+        let board = get_board(&contract, index).await?;
+        assert_eq!(board["last_step"]["engine"], engine);
+        let engine_gas = board["last_step"]["gas"].as_u64().unwrap();
+        assert!(engine_gas > 0 && engine_gas < gas, "{} reported {} gas of {}", engine, engine_gas, gas);
+        boards.push(board["board"].clone());
+    }
+    assert!(boards.windows(2).all(|pair| pair[0] == pair[1]));
+    Ok(())
+}
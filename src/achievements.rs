@@ -0,0 +1,98 @@
+use crate::patterns::PatternKind;
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AchievementKind {
+    /// Found a spaceship that is not in the known pattern library.
+    SpaceshipDiscovered { period: u64, dx: i64, dy: i64 },
+    /// Won a tournament.
+    TournamentChampion { tournament: TournamentId },
+}
+
+impl AchievementKind {
+    /// Leaderboard points earned in the season the achievement is awarded in.
+    pub fn points(&self) -> u64 {
+        match self {
+            AchievementKind::SpaceshipDiscovered { .. } => 10,
+            AchievementKind::TournamentChampion { .. } => 25,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Achievement {
+    pub kind: AchievementKind,
+    pub board: BoardIndex,
+    pub block_height: BlockHeight,
+    /// Season running when the achievement was awarded.
+    pub season: Option<SeasonId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_achievements(&self, account_id: AccountId) -> Vec<Achievement> {
+        self.achievements.get(&account_id).unwrap_or_default()
+    }
+
+    /// Awards the owner of the board at `index` a discovery achievement if the board is a
+    /// spaceship missing from the known pattern library. Owner only, and only the first board
+    /// of a spaceship in any phase or orientation counts. Known objects flying together, e.g.
+    /// two gliders side by side, are not a discovery.
+    pub fn claim_spaceship_discovery(&mut self, index: BoardIndex) -> Achievement {
+        let board = self.internal_get_board(index);
+        assert_owner(&board);
+        let spaceship = self.get_spaceship(index).expect("The board is not a spaceship");
+        assert!(spaceship.known_as.is_none(), "The spaceship is already known");
+        let (_, _, _, phases) = board
+            .board
+            .find_spaceship(analysis::MAX_SPACESHIP_PERIOD)
+            .expect("The board is not a spaceship");
+        assert!(!is_composite(&phases), "The spaceship is made of known objects");
+        let shape = patterns::canonical_shape(&phases);
+        let key = env::sha256(&shape.try_to_vec().expect("Board serializes"));
+        if let Some(discoverer) = self.discoveries.get(&key) {
+            env::panic(format!("The spaceship was already discovered by {}", discoverer).as_bytes());
+        }
+        self.discoveries.insert(&key, &board.owner_id);
+        let achievement = Achievement {
+            kind: AchievementKind::SpaceshipDiscovered {
+                period: spaceship.period,
+                dx: spaceship.dx,
+                dy: spaceship.dy,
+            },
+            board: index,
+            block_height: env::block_index(),
+            season: None,
+        };
+        self.internal_award(&board.owner_id, achievement)
+    }
+}
+
+impl Contract {
+    /// Stores the achievement under the current season, credits its points there and mints
+    /// the matching trophy.
+    pub(crate) fn internal_award(&mut self, account_id: &AccountId, mut achievement: Achievement) -> Achievement {
+        achievement.season = self.get_current_season().map(|season| season.id);
+        if let Some(season) = achievement.season {
+            self.internal_add_points(season, account_id, achievement.kind.points());
+        }
+        let mut achievements = self.achievements.get(account_id).unwrap_or_default();
+        achievements.push(achievement.clone());
+        self.achievements.insert(account_id, &achievements);
+        self.internal_mint_trophy(account_id, &achievement);
+        achievement
+    }
+}
+
+/// Whether some phase falls apart into several clusters that are known objects, or carry a
+/// known spaceship along, rather than being one new spaceship.
+fn is_composite(phases: &[Board]) -> bool {
+    phases.iter().any(|phase| {
+        let census = phase.census();
+        let known: u32 = census.objects.iter().map(|object| object.count).sum();
+        let carries_spaceship = census.objects.iter().any(|object| object.kind == PatternKind::Spaceship);
+        known + census.unknown > 1 && (census.unknown == 0 || carries_spaceship)
+    })
+}
@@ -0,0 +1,120 @@
+//! Action points: a non-monetary budget for casual players. Every account regenerates points
+//! each block up to a cap, and spends them on interactive moves on boards it doesn't own:
+//! stepping them and painting cells into their layers.
+
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ActionPointConfig {
+    /// Points an account holds at most, and starts with.
+    pub max_points: u32,
+    pub points_per_block: u32,
+    pub step_cost: u32,
+    /// Cost of every painted cell.
+    pub cell_cost: u32,
+}
+
+impl ActionPointConfig {
+    /// Points of an account that had `points` at block `since`.
+    fn regenerated(&self, points: u32, since: BlockHeight) -> u32 {
+        let blocks = env::block_index().saturating_sub(since);
+        let gained = blocks.saturating_mul(self.points_per_block as u64);
+        (points as u64).saturating_add(gained).min(self.max_points as u64) as u32
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets or (with None) turns off action points. Contract owner only.
+    pub fn set_action_point_config(&mut self, config: Option<ActionPointConfig>) {
+        self.assert_contract_owner();
+        if let Some(config) = config {
+            assert!(config.max_points > 0, "Action points must be positive");
+        }
+        self.action_point_config = config;
+    }
+
+    pub fn get_action_point_config(&self) -> Option<ActionPointConfig> {
+        self.action_point_config
+    }
+
+    /// Points `account_id` can spend right now, None while action points are off.
+    pub fn get_action_points(&self, account_id: AccountId) -> Option<u32> {
+        let config = self.action_point_config?;
+        Some(match self.action_points.get(&account_id) {
+            Some((points, since)) => config.regenerated(points, since),
+            None => config.max_points,
+        })
+    }
+}
+
+impl Contract {
+    /// Charges the predecessor for `steps` steps and `cells` painted cells on `board`, unless
+    /// they own it or action points are off.
+    pub(crate) fn internal_spend_action_points(&mut self, board: &BoardWithBlock, steps: u64, cells: u64) {
+        self.internal_spend_action_points_for(&env::predecessor_account_id(), board, steps, cells);
+    }
+
+    /// Charges `account_id`, acting directly or through a router.
+    pub(crate) fn internal_spend_action_points_for(
+        &mut self,
+        account_id: &AccountId,
+        board: &BoardWithBlock,
+        steps: u64,
+        cells: u64,
+    ) {
+        assert!(
+            self.internal_try_spend_action_points(account_id, board, steps, cells),
+            "Not enough action points, wait for them to regenerate"
+        );
+    }
+
+    /// Like `internal_spend_action_points_for`, but returns false instead of failing when
+    /// `account_id` can't afford it, for batches that go on with their other items.
+    pub(crate) fn internal_try_spend_action_points(
+        &mut self,
+        account_id: &AccountId,
+        board: &BoardWithBlock,
+        steps: u64,
+        cells: u64,
+    ) -> bool {
+        let cost = self.internal_action_point_cost(account_id, board, steps, cells);
+        if cost == 0 {
+            return true;
+        }
+        let available = self.get_action_points(account_id.clone()).expect("Action points are on");
+        if cost > available as u64 {
+            return false;
+        }
+        self.action_points.insert(account_id, &(available - cost as u32, env::block_index()));
+        true
+    }
+
+    /// Points `account_id` pays for `steps` steps and `cells` painted cells on `board`.
+    pub(crate) fn internal_action_point_cost(
+        &self,
+        account_id: &AccountId,
+        board: &BoardWithBlock,
+        steps: u64,
+        cells: u64,
+    ) -> u64 {
+        match self.action_point_config {
+            Some(config) if *account_id != board.owner_id => {
+                steps * config.step_cost as u64 + cells * config.cell_cost as u64
+            }
+            _ => 0,
+        }
+    }
+
+    /// Gives `points` spent on a move that didn't happen back to `account_id`, up to the cap.
+    pub(crate) fn internal_refund_action_points(&mut self, account_id: &AccountId, points: u64) {
+        let config = match self.action_point_config {
+            Some(config) if points > 0 => config,
+            _ => return,
+        };
+        let available = self.get_action_points(account_id.clone()).expect("Action points are on");
+        let points = (available as u64 + points).min(config.max_points as u64) as u32;
+        self.action_points.insert(account_id, &(points, env::block_index()));
+    }
+}
@@ -0,0 +1,537 @@
+use crate::patterns::PatternKind;
+use crate::*;
+
+/// Number of most recent changes averaged for the change rate.
+const CHANGE_RATE_WINDOW: u64 = 8;
+
+/// Ratios are in basis points (1/100 of a percent), entropy in thousandths of a bit.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Complexity {
+    pub density_bps: u32,
+    /// Shannon entropy of the 2x2 tile patterns, from 0 to 4000.
+    pub block_entropy_millibits: u32,
+    /// Average share of cells flipped per recent change.
+    pub change_rate_bps: u32,
+    /// Number of changes the change rate was computed from.
+    pub sampled_changes: u32,
+}
+
+/// Entropy of the distribution of 2x2 tiles; a trailing odd row/column is padded with dead cells.
+pub fn block_entropy(board: &Board) -> f64 {
+    let mut counts = [0u32; 16];
+    let cell = |x: usize, y: usize| x < board.width() && y < board.height() && board.is_bit_set(x, y);
+    for y in (0..board.height()).step_by(2) {
+        for x in (0..board.width()).step_by(2) {
+            let tile = cell(x, y) as usize
+                | (cell(x + 1, y) as usize) << 1
+                | (cell(x, y + 1) as usize) << 2
+                | (cell(x + 1, y + 1) as usize) << 3;
+            counts[tile] += 1;
+        }
+    }
+    let total: u32 = counts.iter().sum();
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Number of most recent generations whose centroids are kept.
+pub const DRIFT_WINDOW: usize = 8;
+/// Centroid displacement over the window, in thousandths of a cell, that counts as moving.
+const MOVING_THRESHOLD_MILLI: i64 = 500;
+
+/// Center of mass of live cells in thousandths of a cell.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Centroid {
+    pub generation: u64,
+    pub x_milli: u64,
+    pub y_milli: u64,
+}
+
+/// Compass direction with north at the top row.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Nearest of the 8 directions to (`dx`, `dy`), None for no displacement.
+    pub fn from_displacement(dx: i64, dy: i64) -> Option<Direction> {
+        let (horizontal, vertical) = (dx.abs(), dy.abs());
+        // Components under half of the other one are ignored.
+        let east_west = horizontal * 2 >= vertical && horizontal > 0;
+        let north_south = vertical * 2 >= horizontal && vertical > 0;
+        let direction = match (east_west.then_some(dx > 0), north_south.then_some(dy < 0)) {
+            (None, None) => return None,
+            (None, Some(true)) => Direction::North,
+            (None, Some(false)) => Direction::South,
+            (Some(true), None) => Direction::East,
+            (Some(false), None) => Direction::West,
+            (Some(true), Some(true)) => Direction::NorthEast,
+            (Some(true), Some(false)) => Direction::SouthEast,
+            (Some(false), Some(true)) => Direction::NorthWest,
+            (Some(false), Some(false)) => Direction::SouthWest,
+        };
+        Some(direction)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Drift {
+    /// Generations between the oldest and newest tracked centroid.
+    pub generations: u64,
+    pub dx_milli: i64,
+    pub dy_milli: i64,
+    /// The live cells as a whole are travelling, e.g. the board holds a glider.
+    pub moving: bool,
+    pub direction: Option<Direction>,
+}
+
+/// Longest period searched for translating patterns.
+pub const MAX_SPACESHIP_PERIOD: u64 = 16;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SpaceshipReport {
+    pub period: u64,
+    /// Displacement per period.
+    pub dx: i64,
+    pub dy: i64,
+    /// E.g. "c/4 diagonal" or "c/2 orthogonal".
+    pub speed: String,
+    /// Name in the known pattern library, None for an unknown ship.
+    pub known_as: Option<String>,
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Conventional speed notation for a pattern moving (`dx`, `dy`) cells every `period` generations.
+pub fn format_speed(period: u64, dx: i64, dy: i64) -> String {
+    let (horizontal, vertical) = (dx.unsigned_abs(), dy.unsigned_abs());
+    let distance = horizontal.max(vertical);
+    let divisor = gcd(distance, period);
+    let (distance, period) = (distance / divisor, period / divisor);
+    let speed = match (distance, period) {
+        (1, 1) => "c".to_string(),
+        (1, _) => format!("c/{}", period),
+        _ => format!("{}c/{}", distance, period),
+    };
+    let heading = if horizontal == 0 || vertical == 0 {
+        "orthogonal"
+    } else if horizontal == vertical {
+        "diagonal"
+    } else {
+        "oblique"
+    };
+    format!("{} {}", speed, heading)
+}
+
+/// Default and highest configurable period looked for by the oscillator detector.
+pub const DEFAULT_MAX_PERIOD: u64 = 16;
+pub const MAX_PERIOD_LIMIT: u64 = 64;
+
+impl BoardWithBlock {
+    /// Remembers the hash of the freshly stepped field and looks for the smallest period
+    /// after which the field repeats.
+    pub(crate) fn detect_period(&mut self) {
+        let hash = self.board.short_hash();
+        self.period = self
+            .recent_hashes
+            .iter()
+            .rev()
+            .position(|&h| h == hash)
+            .map(|position| position as u64 + 1);
+        self.recent_hashes.push(hash);
+        let excess = self.recent_hashes.len().saturating_sub(self.max_period as usize);
+        self.recent_hashes.drain(..excess);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ObjectCount {
+    pub name: String,
+    pub kind: PatternKind,
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Census {
+    /// Known objects found, in library order.
+    pub objects: Vec<ObjectCount>,
+    /// Clusters that match no known pattern.
+    pub unknown: u32,
+}
+
+/// The 8 symmetries of a square.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirror across the vertical axis.
+    FlipHorizontal,
+    /// Mirror across the horizontal axis.
+    FlipVertical,
+    /// Mirror across the top-left to bottom-right diagonal.
+    FlipDiagonal,
+    /// Mirror across the top-right to bottom-left diagonal.
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::FlipDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    /// Whether the transform swaps width and height.
+    pub fn transposes(&self) -> bool {
+        matches!(
+            self,
+            Symmetry::Rotate90 | Symmetry::Rotate270 | Symmetry::FlipDiagonal | Symmetry::FlipAntiDiagonal
+        )
+    }
+
+    /// Image of cell (`x`, `y`) of a `width` x `height` board (clockwise rotations).
+    pub fn apply(&self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let (right, bottom) = (width - 1 - x, height - 1 - y);
+        match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (bottom, x),
+            Symmetry::Rotate180 => (right, bottom),
+            Symmetry::Rotate270 => (y, right),
+            Symmetry::FlipHorizontal => (right, y),
+            Symmetry::FlipVertical => (x, bottom),
+            Symmetry::FlipDiagonal => (y, x),
+            Symmetry::FlipAntiDiagonal => (bottom, right),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BoundingBox {
+    pub min_x: u32,
+    pub min_y: u32,
+    pub max_x: u32,
+    pub max_y: u32,
+    pub population: u32,
+}
+
+impl Board {
+    pub fn centroid(&self, generation: u64) -> Option<Centroid> {
+        let cells = self.live_cells();
+        if cells.is_empty() {
+            return None;
+        }
+        let count = cells.len() as u64;
+        let (sum_x, sum_y) = cells
+            .iter()
+            .fold((0u64, 0u64), |(sx, sy), &(x, y)| (sx + x as u64, sy + y as u64));
+        Some(Centroid {
+            generation,
+            x_milli: sum_x * 1000 / count,
+            y_milli: sum_y * 1000 / count,
+        })
+    }
+
+    /// Smallest rectangle (inclusive) containing every live cell, None for an empty board.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut bounds: Option<BoundingBox> = None;
+        for (x, y) in self.live_cells() {
+            let (x, y) = (x as u32, y as u32);
+            let b = bounds.get_or_insert(BoundingBox { min_x: x, min_y: y, max_x: x, max_y: y, population: 0 });
+            b.min_x = b.min_x.min(x);
+            b.min_y = b.min_y.min(y);
+            b.max_x = b.max_x.max(x);
+            b.max_y = b.max_y.max(y);
+            b.population += 1;
+        }
+        bounds
+    }
+
+    /// Groups of live cells connected horizontally, vertically or diagonally, each trimmed
+    /// to its own cells. Objects whose phases fall apart into several groups (e.g. the second
+    /// phase of a toad) are reported as several clusters.
+    pub fn clusters(&self) -> Vec<Board> {
+        let (width, height) = (self.width(), self.height());
+        let mut seen = vec![false; width * height];
+        let mut clusters = vec![];
+        for (x, y) in self.live_cells() {
+            if seen[y * width + x] {
+                continue;
+            }
+            seen[y * width + x] = true;
+            let mut cells = vec![(x, y)];
+            let mut next = 0;
+            while next < cells.len() {
+                let (cx, cy) = cells[next];
+                next += 1;
+                for ny in cy.saturating_sub(1)..=(cy + 1).min(height - 1) {
+                    for nx in cx.saturating_sub(1)..=(cx + 1).min(width - 1) {
+                        if !seen[ny * width + nx] && self.is_bit_set(nx, ny) {
+                            seen[ny * width + nx] = true;
+                            cells.push((nx, ny));
+                        }
+                    }
+                }
+            }
+            let mut cluster = Board::with_dimensions(width, height);
+            for (cx, cy) in cells {
+                cluster.set_bit(cx, cy, true);
+            }
+            clusters.push(cluster.trimmed().expect("Cluster has live cells").0);
+        }
+        clusters
+    }
+
+    /// Connected clusters counted per known still life, oscillator phase or spaceship phase.
+    pub fn census(&self) -> Census {
+        let library: Vec<(&patterns::Pattern, Vec<Board>)> = patterns::KNOWN_PATTERNS
+            .iter()
+            .map(|pattern| (pattern, pattern.phases()))
+            .collect();
+        let mut counts = vec![0u32; library.len()];
+        let mut unknown = 0;
+        for cluster in self.clusters() {
+            let known = library
+                .iter()
+                .position(|(_, phases)| phases.iter().any(|phase| patterns::same_shape(phase, &cluster)));
+            match known {
+                Some(position) => counts[position] += 1,
+                None => unknown += 1,
+            }
+        }
+        let objects = library
+            .iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            .map(|((pattern, _), count)| ObjectCount {
+                name: pattern.name.to_string(),
+                kind: pattern.kind,
+                count,
+            })
+            .collect();
+        Census { objects, unknown }
+    }
+
+    /// The live cells cropped to their bounding box, with the box. None for an empty board.
+    pub fn trimmed(&self) -> Option<(Board, BoundingBox)> {
+        let b = self.bounding_box()?;
+        let shape = self.crop(
+            b.min_x as usize,
+            b.min_y as usize,
+            (b.max_x - b.min_x + 1) as usize,
+            (b.max_y - b.min_y + 1) as usize,
+        );
+        Some((shape, b))
+    }
+
+    /// Period and displacement if the whole board reappears translated within `max_period`
+    /// generations, with every phase of it trimmed to its live cells.
+    pub fn find_spaceship(&self, max_period: u64) -> Option<(u64, i64, i64, Vec<Board>)> {
+        let (shape, start) = self.trimmed()?;
+        let mut phases = vec![shape.clone()];
+        let mut state = self.clone();
+        for period in 1..=max_period {
+            state = state.next_generation();
+            let (next_shape, bounds) = state.trimmed()?;
+            if next_shape == shape {
+                let dx = bounds.min_x as i64 - start.min_x as i64;
+                let dy = bounds.min_y as i64 - start.min_y as i64;
+                if dx == 0 && dy == 0 {
+                    return None;
+                }
+                return Some((period, dx, dy, phases));
+            }
+            phases.push(next_shape);
+        }
+        None
+    }
+
+    pub fn transformed(&self, symmetry: Symmetry) -> Board {
+        let (width, height) = if symmetry.transposes() {
+            (self.height(), self.width())
+        } else {
+            (self.width(), self.height())
+        };
+        let mut board = Board::with_dimensions(width, height);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.is_bit_set(x, y) {
+                    let (nx, ny) = symmetry.apply(x, y, self.width(), self.height());
+                    board.set_bit(nx, ny, true);
+                }
+            }
+        }
+        board
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SymmetryReport {
+    pub symmetry: Symmetry,
+    pub exact: bool,
+    /// Share of live cells whose image is also alive.
+    pub score_bps: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Simple "interestingness" metrics of the board at `index`.
+    pub fn get_complexity(&self, index: BoardIndex) -> Complexity {
+        let board = self.internal_get_board(index);
+        let cells = (board.board.width() * board.board.height()) as u64;
+        let density_bps = (board.board.population() as u64 * 10_000 / cells) as u32;
+
+        let first = board.change_seq.saturating_sub(CHANGE_RATE_WINDOW) + 1;
+        let mut flipped = 0u64;
+        let mut sampled = 0u64;
+        for seq in first..=board.change_seq {
+            if let Some(change) = self.changes.get(&(index, seq)).filter(|change| !change.resized) {
+                flipped += (change.delta.born.len() + change.delta.died.len()) as u64 * 10_000
+                    / (change.width as u64 * change.height as u64);
+                sampled += 1;
+            }
+        }
+
+        Complexity {
+            density_bps,
+            block_entropy_millibits: (block_entropy(&board.board) * 1000.0).round() as u32,
+            change_rate_bps: flipped.checked_div(sampled).unwrap_or(0) as u32,
+            sampled_changes: sampled as u32,
+        }
+    }
+
+    /// Movement of the live-cell centroid over the last generations of the board at `index`.
+    /// None until at least two generations have been tracked since the last edit.
+    pub fn get_drift(&self, index: BoardIndex) -> Option<Drift> {
+        let board = self.internal_get_board(index);
+        let (first, last) = (board.recent_centroids.first()?, board.recent_centroids.last()?);
+        if first.generation == last.generation {
+            return None;
+        }
+        let dx_milli = last.x_milli as i64 - first.x_milli as i64;
+        let dy_milli = last.y_milli as i64 - first.y_milli as i64;
+        Some(Drift {
+            generations: last.generation - first.generation,
+            dx_milli,
+            dy_milli,
+            moving: dx_milli.abs().max(dy_milli.abs()) >= MOVING_THRESHOLD_MILLI,
+            direction: Direction::from_displacement(dx_milli, dy_milli),
+        })
+    }
+
+    /// Period, displacement and speed if the board at `index` is a single translating
+    /// pattern (e.g. a glider), along with its name if it is in the known pattern library.
+    pub fn get_spaceship(&self, index: BoardIndex) -> Option<SpaceshipReport> {
+        let board = self.internal_get_board(index).board;
+        let (period, dx, dy, phases) = board.find_spaceship(MAX_SPACESHIP_PERIOD)?;
+        let known_as = phases
+            .iter()
+            .find_map(|phase| patterns::identify(phase, patterns::PatternKind::Spaceship))
+            .map(|pattern| pattern.name.to_string());
+        Some(SpaceshipReport {
+            period,
+            dx,
+            dy,
+            speed: format_speed(period, dx, dy),
+            known_as,
+        })
+    }
+
+    /// Splits the board at `index` into connected clusters and counts them per known still
+    /// life, oscillator phase or spaceship phase.
+    pub fn get_census(&self, index: BoardIndex) -> Census {
+        self.internal_get_board(index).board.census()
+    }
+
+    /// Sets the longest period the oscillator detector looks for on the board at `index`.
+    /// Owner only.
+    pub fn set_max_period(&mut self, index: BoardIndex, max_period: u64) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        assert!(
+            (1..=MAX_PERIOD_LIMIT).contains(&max_period),
+            "Max period must be between 1 and {}",
+            MAX_PERIOD_LIMIT
+        );
+        board.max_period = max_period;
+        let excess = board.recent_hashes.len().saturating_sub(max_period as usize);
+        board.recent_hashes.drain(..excess);
+        if board.period.is_some_and(|period| period > max_period) {
+            board.period = None;
+        }
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Extent and population of the live cells of the board at `index`, None if it is empty.
+    pub fn get_bounding_box(&self, index: BoardIndex) -> Option<BoundingBox> {
+        self.internal_get_board(index).board.bounding_box()
+    }
+
+    /// How closely the board at `index` matches each of the 8 square symmetries. Symmetries
+    /// swapping width and height never match a non-square board.
+    pub fn detect_symmetries(&self, index: BoardIndex) -> Vec<SymmetryReport> {
+        let board = self.internal_get_board(index).board;
+        let (width, height) = (board.width(), board.height());
+        let population = board.population();
+        Symmetry::ALL
+            .iter()
+            .map(|&symmetry| {
+                if symmetry.transposes() && width != height {
+                    return SymmetryReport { symmetry, exact: false, score_bps: 0 };
+                }
+                let mut matching = 0u32;
+                for y in 0..height {
+                    for x in 0..width {
+                        let (nx, ny) = symmetry.apply(x, y, width, height);
+                        if board.is_bit_set(x, y) && board.is_bit_set(nx, ny) {
+                            matching += 1;
+                        }
+                    }
+                }
+                SymmetryReport {
+                    symmetry,
+                    exact: matching == population,
+                    score_bps: (matching as u64 * 10_000).checked_div(population as u64).unwrap_or(10_000) as u32,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,656 @@
+//! A maintained registry of the contract's methods, so generic frontends and wallets can build
+//! forms for them. Argument types are named as in the contract's source; `U128` and `U64`
+//! travel as strings and `Base64VecU8` as base64. A test keeps the registry in step with the
+//! `#[near_bindgen]` methods.
+
+use crate::*;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MethodKind {
+    /// Read-only, callable without a transaction.
+    View,
+    Call,
+    /// Initializes the contract once, on deployment.
+    Init,
+}
+
+/// Who may call a method.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Permission {
+    Anyone,
+    /// The owner of the board the method is about.
+    BoardOwner,
+    /// The account that deployed the contract.
+    ContractOwner,
+    /// The contract owner and moderators.
+    Moderator,
+    /// The organizer of the tournament, or an entrant of a stalled one to cancel it.
+    Organizer,
+    /// The player of the battle whose turn it is, or player A to cancel.
+    Player,
+    /// The contract itself, as a callback of its own promises.
+    Contract,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ArgInfo {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub type_name: &'static str,
+}
+
+const fn arg(name: &'static str, type_name: &'static str) -> ArgInfo {
+    ArgInfo { name, type_name }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MethodInfo {
+    pub name: &'static str,
+    pub kind: MethodKind,
+    /// Accepts an attached deposit.
+    pub payable: bool,
+    pub permission: Permission,
+    /// JSON arguments, in order. `set_shard_code` takes the raw shard wasm as input instead.
+    pub args: &'static [ArgInfo],
+}
+
+impl MethodInfo {
+    const fn view(name: &'static str, args: &'static [ArgInfo]) -> Self {
+        Self {
+            name,
+            kind: MethodKind::View,
+            payable: false,
+            permission: Permission::Anyone,
+            args,
+        }
+    }
+
+    const fn call(name: &'static str, permission: Permission, args: &'static [ArgInfo]) -> Self {
+        Self {
+            name,
+            kind: MethodKind::Call,
+            payable: false,
+            permission,
+            args,
+        }
+    }
+
+    const fn init(name: &'static str, args: &'static [ArgInfo]) -> Self {
+        Self {
+            name,
+            kind: MethodKind::Init,
+            payable: false,
+            permission: Permission::Anyone,
+            args,
+        }
+    }
+
+    const fn payable(self) -> Self {
+        Self { payable: true, ..self }
+    }
+}
+
+/// Every method of the contract, grouped by module.
+pub const API: &[MethodInfo] = &[
+    MethodInfo::view("get_api", &[]),
+    MethodInfo::view("get_achievements", &[arg("account_id", "AccountId")]),
+    MethodInfo::call(
+        "claim_spaceship_discovery",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex")],
+    ),
+    MethodInfo::call(
+        "set_action_point_config",
+        Permission::ContractOwner,
+        &[arg("config", "Option<ActionPointConfig>")],
+    ),
+    MethodInfo::view("get_action_point_config", &[]),
+    MethodInfo::view("get_action_points", &[arg("account_id", "AccountId")]),
+    MethodInfo::view("get_complexity", &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_drift", &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_spaceship", &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_census", &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "set_max_period",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("max_period", "u64")],
+    ),
+    MethodInfo::view("get_bounding_box", &[arg("index", "BoardIndex")]),
+    MethodInfo::view("detect_symmetries", &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "step_many",
+        Permission::Anyone,
+        &[arg("indices", "Vec<BoardIndex>"), arg("cursor", "Option<u64>")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "create_battle",
+        Permission::Anyone,
+        &[
+            arg("width", "u32"),
+            arg("height", "u32"),
+            arg("cell_price", "U128"),
+            arg("cells_per_player", "u32"),
+            arg("generations", "u64"),
+        ],
+    )
+    .payable(),
+    MethodInfo::view("get_battle", &[arg("id", "BattleId")]),
+    MethodInfo::call("join_battle", Permission::Anyone, &[arg("id", "BattleId")]).payable(),
+    MethodInfo::call("cancel_battle", Permission::Player, &[arg("id", "BattleId")]),
+    MethodInfo::call(
+        "place_battle_cell",
+        Permission::Player,
+        &[arg("id", "BattleId"), arg("x", "u32"), arg("y", "u32")],
+    )
+    .payable(),
+    MethodInfo::call("settle_battle", Permission::Anyone, &[arg("id", "BattleId")]),
+    MethodInfo::call("claim_battle_timeout", Permission::Anyone, &[arg("id", "BattleId")]),
+    MethodInfo::call(
+        "set_step_callback",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("callback", "Option<StepCallback>")],
+    ),
+    MethodInfo::view("get_changes_since", &[arg("index", "BoardIndex"), arg("seq", "u64")]),
+    MethodInfo::call(
+        "post_comment",
+        Permission::Anyone,
+        &[arg("index", "BoardIndex"), arg("text", "String")],
+    )
+    .payable(),
+    MethodInfo::view(
+        "get_comments",
+        &[
+            arg("index", "BoardIndex"),
+            arg("from_index", "u64"),
+            arg("limit", "u64"),
+        ],
+    ),
+    MethodInfo::call(
+        "set_engine_hint",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("hint", "EngineHint")],
+    ),
+    MethodInfo::call(
+        "set_diff_events",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("enabled", "bool")],
+    ),
+    MethodInfo::call(
+        "set_event_tag",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("tag", "Option<String>")],
+    ),
+    MethodInfo::view("get_fee_config", &[]),
+    MethodInfo::view("get_pending_fee_config", &[]),
+    MethodInfo::call(
+        "set_fee_config",
+        Permission::ContractOwner,
+        &[arg("config", "FeeConfig")],
+    ),
+    MethodInfo::call("cancel_fee_config", Permission::ContractOwner, &[]),
+    MethodInfo::call("follow_board", Permission::Anyone, &[arg("index", "BoardIndex")]).payable(),
+    MethodInfo::call("unfollow_board", Permission::Anyone, &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_followed_boards", &[arg("account_id", "AccountId")]),
+    MethodInfo::call(
+        "claim_handle",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("handle", "String")],
+    )
+    .payable(),
+    MethodInfo::view("get_board_by_handle", &[arg("handle", "String")]),
+    MethodInfo::view(
+        "get_board_headers",
+        &[arg("from_index", "BoardIndex"), arg("limit", "u64")],
+    ),
+    MethodInfo::call(
+        "set_heatmap_tracking",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("enabled", "bool")],
+    )
+    .payable(),
+    MethodInfo::view("get_heatmap", &[arg("index", "BoardIndex")]),
+    MethodInfo::view(
+        "get_generation",
+        &[arg("index", "BoardIndex"), arg("generation", "u64")],
+    ),
+    MethodInfo::view("get_population_history", &[arg("index", "BoardIndex")]),
+    MethodInfo::view(
+        "estimate_storage_cost",
+        &[
+            arg("width", "u32"),
+            arg("height", "u32"),
+            arg("history_policy", "HistoryPolicy"),
+        ],
+    ),
+    MethodInfo::call(
+        "set_history_policy",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("policy", "HistoryPolicy")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "import_board",
+        Permission::Anyone,
+        &[arg("source_contract", "AccountId"), arg("source_index", "BoardIndex")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "on_board_imported",
+        Permission::Contract,
+        &[
+            arg("source_contract", "AccountId"),
+            arg("source_index", "BoardIndex"),
+            arg("owner_id", "AccountId"),
+            arg("fee", "U128"),
+        ],
+    ),
+    MethodInfo::view("check_invariants", &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "set_layers_enabled",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("enabled", "bool")],
+    ),
+    MethodInfo::call(
+        "paint_layer",
+        Permission::Anyone,
+        &[
+            arg("index", "BoardIndex"),
+            arg("cells", "Vec<(u32, u32)>"),
+            arg("alive", "bool"),
+        ],
+    )
+    .payable(),
+    MethodInfo::view(
+        "get_layer",
+        &[arg("index", "BoardIndex"), arg("account_id", "AccountId")],
+    ),
+    MethodInfo::init("new", &[]),
+    MethodInfo::call("create_board", Permission::Anyone, &[arg("field", "Base64VecU8")]).payable(),
+    MethodInfo::call("split_board", Permission::Anyone, &[arg("index", "BoardIndex")]).payable(),
+    MethodInfo::call(
+        "resize_board",
+        Permission::BoardOwner,
+        &[
+            arg("index", "BoardIndex"),
+            arg("new_w", "u32"),
+            arg("new_h", "u32"),
+            arg("anchor", "Anchor"),
+        ],
+    )
+    .payable(),
+    MethodInfo::call(
+        "shift_board",
+        Permission::BoardOwner,
+        &[
+            arg("index", "BoardIndex"),
+            arg("dx", "i32"),
+            arg("dy", "i32"),
+            arg("wrap", "bool"),
+        ],
+    ),
+    MethodInfo::call(
+        "write_text",
+        Permission::BoardOwner,
+        &[
+            arg("index", "BoardIndex"),
+            arg("text", "String"),
+            arg("x", "u32"),
+            arg("y", "u32"),
+        ],
+    ),
+    MethodInfo::call("clear_board", Permission::BoardOwner, &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "reset_to_genesis",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex")],
+    ),
+    MethodInfo::call("undo_step", Permission::BoardOwner, &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "set_auto_step",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("enabled", "bool")],
+    ),
+    MethodInfo::call("poke", Permission::Anyone, &[arg("index", "BoardIndex")]),
+    MethodInfo::view(
+        "preview_timeline",
+        &[arg("index", "BoardIndex"), arg("every_n", "u64"), arg("count", "u64")],
+    ),
+    MethodInfo::view("get_board", &[arg("index", "BoardIndex")]),
+    MethodInfo::call("step", Permission::Anyone, &[arg("index", "BoardIndex")]).payable(),
+    MethodInfo::call(
+        "set_board_limits",
+        Permission::BoardOwner,
+        &[
+            arg("index", "BoardIndex"),
+            arg("max_population", "Option<u32>"),
+            arg("max_generation", "Option<u64>"),
+        ],
+    ),
+    MethodInfo::view("get_board_metadata", &[arg("index", "BoardIndex")]),
+    MethodInfo {
+        permission: Permission::Contract,
+        ..MethodInfo::init("migrate", &[arg("owner_id", "AccountId")])
+    },
+    MethodInfo::call(
+        "add_moderator",
+        Permission::ContractOwner,
+        &[arg("account_id", "AccountId")],
+    ),
+    MethodInfo::call(
+        "remove_moderator",
+        Permission::ContractOwner,
+        &[arg("account_id", "AccountId")],
+    ),
+    MethodInfo::view("get_moderators", &[]),
+    MethodInfo::call(
+        "flag_board",
+        Permission::Anyone,
+        &[arg("index", "BoardIndex"), arg("reason", "String")],
+    )
+    .payable(),
+    MethodInfo::view("get_flags", &[arg("index", "BoardIndex")]),
+    MethodInfo::call("hide_board", Permission::Moderator, &[arg("index", "BoardIndex")]),
+    MethodInfo::call("unhide_board", Permission::Moderator, &[arg("index", "BoardIndex")]),
+    MethodInfo::call("freeze_board", Permission::Moderator, &[arg("index", "BoardIndex")]),
+    MethodInfo::call("unfreeze_board", Permission::Moderator, &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_boards", &[arg("from_index", "BoardIndex"), arg("limit", "u64")]),
+    MethodInfo::call("finalize_board", Permission::BoardOwner, &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_museum", &[arg("from_index", "u64"), arg("limit", "u64")]),
+    MethodInfo::view("get_museum_size", &[]),
+    MethodInfo::call(
+        "set_rate_limit",
+        Permission::ContractOwner,
+        &[arg("rate_limit", "Option<RateLimit>")],
+    ),
+    MethodInfo::view("get_rate_limit", &[]),
+    MethodInfo::view("render_board", &[arg("index", "BoardIndex"), arg("mode", "RenderMode")]),
+    MethodInfo::view("get_board_matrix", &[arg("index", "BoardIndex")]),
+    MethodInfo::view(
+        "export_image",
+        &[
+            arg("index", "BoardIndex"),
+            arg("format", "ImageFormat"),
+            arg("scale", "u32"),
+        ],
+    ),
+    MethodInfo::view(
+        "get_match_replay",
+        &[
+            arg("match_id", "MatchId"),
+            arg("from_index", "u32"),
+            arg("limit", "u32"),
+        ],
+    ),
+    MethodInfo::view("get_match_replay_len", &[arg("match_id", "MatchId")]),
+    MethodInfo::call(
+        "set_rule_oracle",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("oracle", "Option<AccountId>")],
+    ),
+    MethodInfo::call("step_with_oracle", Permission::Anyone, &[arg("index", "BoardIndex")]).payable(),
+    MethodInfo::call(
+        "on_oracle_rows",
+        Permission::Contract,
+        &[
+            arg("index", "BoardIndex"),
+            arg("change_seq", "u64"),
+            arg("held", "HeldStep"),
+        ],
+    ),
+    MethodInfo::view("get_scenarios", &[]),
+    MethodInfo::call(
+        "start_scenario",
+        Permission::Anyone,
+        &[arg("name", "String"), arg("cells", "Vec<(u32, u32)>")],
+    )
+    .payable(),
+    MethodInfo::view("get_completed_scenarios", &[arg("account_id", "AccountId")]),
+    MethodInfo::call(
+        "schedule_step",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("at_block", "BlockHeight")],
+    )
+    .payable(),
+    MethodInfo::view(
+        "get_scheduled_steps",
+        &[arg("from_block", "BlockHeight"), arg("limit", "u64")],
+    ),
+    MethodInfo::call("execute_due_steps", Permission::Anyone, &[arg("limit", "u64")]),
+    MethodInfo::call(
+        "add_season",
+        Permission::ContractOwner,
+        &[
+            arg("name", "String"),
+            arg("start_block", "BlockHeight"),
+            arg("end_block", "BlockHeight"),
+        ],
+    ),
+    MethodInfo::view("get_seasons", &[]),
+    MethodInfo::view("get_current_season", &[]),
+    MethodInfo::view("get_leaderboard", &[arg("season", "SeasonId")]),
+    MethodInfo::view(
+        "get_season_achievements",
+        &[arg("account_id", "AccountId"), arg("season", "SeasonId")],
+    ),
+    MethodInfo::call("set_shard_code", Permission::ContractOwner, &[]),
+    MethodInfo::call(
+        "deploy_shard",
+        Permission::ContractOwner,
+        &[arg("name", "String"), arg("capacity", "u64")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "on_shard_deployed",
+        Permission::Contract,
+        &[arg("shard_id", "ShardId"), arg("deposit", "U128")],
+    ),
+    MethodInfo::view("get_shards", &[]),
+    MethodInfo::view("get_total_boards", &[]),
+    MethodInfo::view("get_board_locations", &[arg("from_index", "u64"), arg("limit", "u64")]),
+    MethodInfo::call(
+        "create_board_routed",
+        Permission::Anyone,
+        &[arg("field", "Base64VecU8")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "on_shard_board_created",
+        Permission::Contract,
+        &[
+            arg("shard_id", "ShardId"),
+            arg("owner_id", "AccountId"),
+            arg("fee", "U128"),
+            arg("storage_deposit", "U128"),
+        ],
+    ),
+    MethodInfo::call(
+        "step_routed",
+        Permission::Anyone,
+        &[arg("shard_id", "ShardId"), arg("index", "BoardIndex")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "on_routed_step",
+        Permission::Contract,
+        &[arg("account_id", "AccountId"), arg("deposit", "U128")],
+    ),
+    MethodInfo::call(
+        "get_board_routed",
+        Permission::Anyone,
+        &[arg("shard_id", "ShardId"), arg("index", "BoardIndex")],
+    ),
+    MethodInfo::call(
+        "create_board_for",
+        Permission::ContractOwner,
+        &[arg("owner_id", "AccountId"), arg("field", "Base64VecU8")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "step_for",
+        Permission::ContractOwner,
+        &[arg("account_id", "AccountId"), arg("index", "BoardIndex")],
+    )
+    .payable(),
+    MethodInfo::view("get_share_payload", &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "create_board_from_payload",
+        Permission::Anyone,
+        &[arg("payload", "String")],
+    )
+    .payable(),
+    MethodInfo::view(
+        "find_similar_boards",
+        &[arg("index", "BoardIndex"), arg("limit", "u64")],
+    ),
+    MethodInfo::call(
+        "export_all_boards",
+        Permission::ContractOwner,
+        &[arg("from_index", "BoardIndex"), arg("limit", "u64")],
+    ),
+    MethodInfo::call(
+        "import_snapshot",
+        Permission::ContractOwner,
+        &[arg("snapshots", "Vec<Base64VecU8>")],
+    ),
+    MethodInfo::call(
+        "register_template",
+        Permission::ContractOwner,
+        &[
+            arg("name", "String"),
+            arg("kind", "TemplateKind"),
+            arg("width", "u32"),
+            arg("height", "u32"),
+            arg("description", "String"),
+        ],
+    ),
+    MethodInfo::view("get_templates", &[]),
+    MethodInfo::call(
+        "create_from_template",
+        Permission::Anyone,
+        &[arg("name", "String"), arg("params", "TemplateParams")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "create_tournament",
+        Permission::Anyone,
+        &[
+            arg("entry_fee", "U128"),
+            arg("max_entries", "u32"),
+            arg("generations", "u64"),
+            arg("metric", "TournamentMetric"),
+        ],
+    )
+    .payable(),
+    MethodInfo::view("get_tournament", &[arg("id", "TournamentId")]),
+    MethodInfo::call(
+        "enter_tournament",
+        Permission::BoardOwner,
+        &[arg("id", "TournamentId"), arg("index", "BoardIndex")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "advance_tournament",
+        Permission::Organizer,
+        &[arg("id", "TournamentId")],
+    )
+    .payable(),
+    MethodInfo::call(
+        "commit_seeding",
+        Permission::Organizer,
+        &[arg("id", "TournamentId"), arg("seed_block", "BlockHeight")],
+    ),
+    MethodInfo::call("resolve_seeding", Permission::Anyone, &[arg("id", "TournamentId")]),
+    MethodInfo::call("cancel_tournament", Permission::Organizer, &[arg("id", "TournamentId")]),
+    MethodInfo::call(
+        "set_trail",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("length", "Option<u8>")],
+    )
+    .payable(),
+    MethodInfo::view("get_trail", &[arg("index", "BoardIndex")]),
+    MethodInfo::view("get_treasury", &[]),
+    MethodInfo::call(
+        "withdraw_treasury",
+        Permission::ContractOwner,
+        &[arg("amount", "U128"), arg("to", "AccountId")],
+    ),
+    MethodInfo::view("nft_metadata", &[]),
+    MethodInfo::view("nft_token", &[arg("token_id", "TokenId")]),
+    MethodInfo::view("nft_total_supply", &[]),
+    MethodInfo::view(
+        "nft_tokens",
+        &[arg("from_index", "Option<U128>"), arg("limit", "Option<u64>")],
+    ),
+    MethodInfo::view("nft_supply_for_owner", &[arg("account_id", "AccountId")]),
+    MethodInfo::view(
+        "nft_tokens_for_owner",
+        &[
+            arg("account_id", "AccountId"),
+            arg("from_index", "Option<U128>"),
+            arg("limit", "Option<u64>"),
+        ],
+    ),
+    MethodInfo::call(
+        "nft_transfer",
+        Permission::Anyone,
+        &[
+            arg("receiver_id", "AccountId"),
+            arg("token_id", "TokenId"),
+            arg("approval_id", "Option<u64>"),
+            arg("memo", "Option<String>"),
+        ],
+    )
+    .payable(),
+    MethodInfo::call(
+        "nft_transfer_call",
+        Permission::Anyone,
+        &[
+            arg("receiver_id", "AccountId"),
+            arg("token_id", "TokenId"),
+            arg("approval_id", "Option<u64>"),
+            arg("memo", "Option<String>"),
+            arg("msg", "String"),
+        ],
+    )
+    .payable(),
+    MethodInfo::call(
+        "claim_tile",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("x", "i32"), arg("y", "i32")],
+    )
+    .payable(),
+    MethodInfo::call("release_tile", Permission::BoardOwner, &[arg("index", "BoardIndex")]),
+    MethodInfo::call(
+        "set_edge_exchange",
+        Permission::BoardOwner,
+        &[arg("index", "BoardIndex"), arg("enabled", "bool")],
+    ),
+    MethodInfo::call(
+        "sync_tile",
+        Permission::Contract,
+        &[arg("index", "BoardIndex"), arg("account_id", "AccountId")],
+    ),
+    MethodInfo::view("get_tile", &[arg("x", "i32"), arg("y", "i32")]),
+    MethodInfo::view(
+        "get_universe_viewport",
+        &[
+            arg("x", "i64"),
+            arg("y", "i64"),
+            arg("width", "u32"),
+            arg("height", "u32"),
+        ],
+    ),
+];
+
+#[near_bindgen]
+impl Contract {
+    /// The contract's methods with their arguments, payability and permissions.
+    pub fn get_api(&self) -> Vec<MethodInfo> {
+        API.to_vec()
+    }
+}
@@ -0,0 +1,91 @@
+//! Methods working through many items stop before running out of gas and report where to
+//! resume instead of failing the whole call.
+
+use crate::*;
+use near_sdk::{Gas, Promise};
+
+/// Gas kept back to save state and return once a batch stops.
+pub const BATCH_GAS_RESERVE: Gas = 20_000_000_000_000;
+
+/// Outcome of a batch call. Items are identified by the batch's own numbering, e.g. board
+/// indices for `step_many`.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchResult {
+    pub processed: Vec<u64>,
+    /// Not attempted because the call ran low on gas or reached the caller's rate limit.
+    pub skipped: Vec<u64>,
+    /// Attempted but not processable, e.g. missing boards.
+    pub failed: Vec<u64>,
+    /// Position to resume from when `skipped` is not empty.
+    pub next_cursor: Option<u64>,
+}
+
+/// Keeps track of the most gas a single batch item has used so far.
+pub(crate) struct GasBudget {
+    mark: Gas,
+    max_item: Gas,
+}
+
+impl GasBudget {
+    pub fn new() -> Self {
+        Self {
+            mark: env::used_gas(),
+            max_item: 0,
+        }
+    }
+
+    /// Whether another item as expensive as the most expensive one so far still fits.
+    pub fn has_room(&mut self) -> bool {
+        let used = env::used_gas();
+        self.max_item = self.max_item.max(used - self.mark);
+        self.mark = used;
+        env::prepaid_gas().saturating_sub(used) > BATCH_GAS_RESERVE + self.max_item
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Steps each board in `indices` once, from position `cursor` on. Missing and frozen boards,
+    /// and boards stepped by a rule oracle, are reported as failed. Step fees come out of the
+    /// attached deposit board by board; boards it no longer covers are reported as failed and
+    /// what is left of it is refunded. So are boards of others the caller has no action points
+    /// left for. Every board counts as a call against the rate limit.
+    #[payable]
+    pub fn step_many(&mut self, indices: Vec<BoardIndex>, cursor: Option<u64>) -> BatchResult {
+        let account_id = env::predecessor_account_id();
+        let mut deposit = env::attached_deposit();
+        let start = cursor.unwrap_or(0) as usize;
+        let mut result = BatchResult::default();
+        let mut budget = GasBudget::new();
+        for (position, &index) in indices.iter().enumerate().skip(start) {
+            if !budget.has_room() || !self.internal_try_rate_limit(&account_id) {
+                result.skipped = indices[position..].to_vec();
+                result.next_cursor = Some(position as u64);
+                break;
+            }
+            let mut board = match self.boards.get(index) {
+                Some(board) if step_blocker(&board).is_none() => board,
+                _ => {
+                    result.failed.push(index);
+                    continue;
+                }
+            };
+            let fee = self.internal_step_fee(&account_id, &board);
+            if fee > deposit || !self.internal_try_spend_action_points(&account_id, &board, 1, 0) {
+                result.failed.push(index);
+                continue;
+            }
+            deposit -= fee;
+            self.internal_collect_fee(fee);
+            self.internal_catch_up(index, &mut board);
+            self.internal_step(index, &mut board);
+            self.internal_save(index, &board);
+            result.processed.push(index);
+        }
+        if deposit > 0 {
+            Promise::new(account_id).transfer(deposit);
+        }
+        result
+    }
+}
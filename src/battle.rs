@@ -0,0 +1,332 @@
+//! Life war: two players take turns placing cells of their color in their own half, then the
+//! board runs under the two-color (Immigration) rules and the color with more cells left
+//! wins both stakes and all placement fees.
+
+use crate::batch::GasBudget;
+use crate::events::emit_event;
+use crate::*;
+use near_sdk::{Balance, Promise};
+
+pub const MAX_BATTLE_SIZE: u32 = 64;
+pub const MAX_BATTLE_GENERATIONS: u64 = 128;
+pub const MAX_CELLS_PER_PLAYER: u32 = 64;
+/// Blocks a player has for a move before the opponent can claim the battle.
+pub const BATTLE_TURN_TIMEOUT: BlockHeight = 600;
+
+pub type BattleId = u64;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BattleStatus {
+    WaitingForOpponent,
+    Placing,
+    /// Every cell is placed; anyone can run and settle the battle, over several calls if it
+    /// doesn't fit in one.
+    Ready,
+    Finished,
+    Cancelled,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Battle {
+    /// Plays the left half and the first move.
+    pub player_a: AccountId,
+    /// Plays the right half.
+    pub player_b: Option<AccountId>,
+    pub stake: U128,
+    /// Attached to every placement and added to the pot.
+    pub cell_price: U128,
+    pub cells_per_player: u32,
+    pub generations: u64,
+    /// Cells of player A and of player B, evolved as the battle is settled.
+    pub cells_a: Board,
+    pub cells_b: Board,
+    /// Generations run by `settle_battle` so far.
+    pub settled_generations: u64,
+    pub placements: u32,
+    pub last_move_block: BlockHeight,
+    pub status: BattleStatus,
+    pub pot: U128,
+    pub score_a: u32,
+    pub score_b: u32,
+    /// None for a draw, which splits the pot.
+    pub winner: Option<AccountId>,
+}
+
+impl Battle {
+    fn player_to_move(&self) -> &AccountId {
+        match &self.player_b {
+            Some(player_b) if !self.placements.is_multiple_of(2) => player_b,
+            _ => &self.player_a,
+        }
+    }
+}
+
+/// One generation under the two-color rules: life and death follow Conway's rules on both
+/// colors together, survivors keep their color and a newborn takes the majority color of its
+/// three parents.
+pub fn two_color_step(a: &Board, b: &Board) -> (Board, Board) {
+    let (width, height) = (a.width(), a.height());
+    let (mut next_a, mut next_b) = (Board::with_dimensions(width, height), Board::with_dimensions(width, height));
+    for y in 0..height {
+        for x in 0..width {
+            let (mut count_a, mut count_b) = (0, 0);
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    if (nx, ny) == (x, y) {
+                        continue;
+                    }
+                    count_a += a.is_bit_set(nx, ny) as u32;
+                    count_b += b.is_bit_set(nx, ny) as u32;
+                }
+            }
+            let sum = count_a + count_b;
+            if a.is_bit_set(x, y) || b.is_bit_set(x, y) {
+                if sum == 2 || sum == 3 {
+                    next_a.set_bit(x, y, a.is_bit_set(x, y));
+                    next_b.set_bit(x, y, b.is_bit_set(x, y));
+                }
+            } else if sum == 3 {
+                next_a.set_bit(x, y, count_a > count_b);
+                next_b.set_bit(x, y, count_b > count_a);
+            }
+        }
+    }
+    (next_a, next_b)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct BattleData<'a> {
+    battle: BattleId,
+    status: BattleStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    winner: Option<&'a AccountId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Opens a battle on a `width` x `height` board with the caller as player A. The attached
+    /// deposit pays for the storage the battle takes and the rest is the stake both players
+    /// put in.
+    #[payable]
+    pub fn create_battle(
+        &mut self,
+        width: u32,
+        height: u32,
+        cell_price: U128,
+        cells_per_player: u32,
+        generations: u64,
+    ) -> BattleId {
+        assert!(
+            (2..=MAX_BATTLE_SIZE).contains(&width) && (1..=MAX_BATTLE_SIZE).contains(&height),
+            "Battle boards are between 2x1 and {0}x{0}",
+            MAX_BATTLE_SIZE
+        );
+        assert!(
+            (1..=MAX_CELLS_PER_PLAYER).contains(&cells_per_player),
+            "Players place between 1 and {} cells",
+            MAX_CELLS_PER_PLAYER
+        );
+        assert!(
+            cells_per_player <= (width / 2) * height,
+            "Too many cells for the board size"
+        );
+        assert!(
+            (1..=MAX_BATTLE_GENERATIONS).contains(&generations),
+            "Generations must be between 1 and {}",
+            MAX_BATTLE_GENERATIONS
+        );
+        let empty = Board::with_dimensions(width as usize, height as usize);
+        let storage_before = env::storage_usage();
+        let mut battle = Battle {
+            player_a: env::predecessor_account_id(),
+            player_b: None,
+            stake: U128(0),
+            cell_price,
+            cells_per_player,
+            generations,
+            cells_a: empty.clone(),
+            cells_b: empty,
+            settled_generations: 0,
+            placements: 0,
+            last_move_block: env::block_index(),
+            status: BattleStatus::WaitingForOpponent,
+            pot: U128(0),
+            score_a: 0,
+            score_b: 0,
+            winner: None,
+        };
+        self.battles.push(&battle);
+        let id = self.battles.len() - 1;
+
+        let cost = (env::storage_usage() - storage_before) as Balance * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= cost, "Requires attached deposit of at least {}", cost);
+        battle.stake = U128(deposit - cost);
+        battle.pot = battle.stake;
+        self.battles.replace(id, &battle);
+        id
+    }
+
+    pub fn get_battle(&self, id: BattleId) -> Option<Battle> {
+        self.battles.get(id)
+    }
+
+    /// Joins battle `id` as player B. Requires exactly the stake.
+    #[payable]
+    pub fn join_battle(&mut self, id: BattleId) -> Battle {
+        let mut battle = self.internal_get_battle(id);
+        assert_eq!(battle.status, BattleStatus::WaitingForOpponent, "The battle has started");
+        assert_ne!(env::predecessor_account_id(), battle.player_a, "You can't battle yourself");
+        assert_eq!(env::attached_deposit(), battle.stake.0, "Requires attached deposit of the stake");
+        battle.player_b = Some(env::predecessor_account_id());
+        battle.pot = U128(battle.pot.0 + battle.stake.0);
+        battle.status = BattleStatus::Placing;
+        battle.last_move_block = env::block_index();
+        self.battles.replace(id, &battle);
+        self.internal_emit_battle(id, &battle);
+        battle
+    }
+
+    /// Refunds player A's stake of a battle nobody joined. Player A only.
+    pub fn cancel_battle(&mut self, id: BattleId) -> Battle {
+        let mut battle = self.internal_get_battle(id);
+        assert_eq!(env::predecessor_account_id(), battle.player_a, "Only player A can do this");
+        assert_eq!(battle.status, BattleStatus::WaitingForOpponent, "The battle has started");
+        battle.status = BattleStatus::Cancelled;
+        let player_a = battle.player_a.clone();
+        self.internal_pay_out(&mut battle, &[player_a]);
+        self.battles.replace(id, &battle);
+        self.internal_emit_battle(id, &battle);
+        battle
+    }
+
+    /// Places a cell of the caller's color. Players alternate, starting with player A, and
+    /// only place in their own half: player A left of the middle, player B right of it.
+    /// Requires the cell price plus the storage of the placement's replay frame; any surplus is
+    /// refunded.
+    #[payable]
+    pub fn place_battle_cell(&mut self, id: BattleId, x: u32, y: u32) -> Battle {
+        let mut battle = self.internal_get_battle(id);
+        assert_eq!(battle.status, BattleStatus::Placing, "The battle is not taking placements");
+        assert_eq!(&env::predecessor_account_id(), battle.player_to_move(), "It's not your turn");
+        let width = battle.cells_a.width;
+        assert!(x < width && y < battle.cells_a.height, "Cell is outside the board");
+        let a_moves = battle.placements.is_multiple_of(2);
+        let own_half = if a_moves { x < width / 2 } else { x >= width - width / 2 };
+        assert!(own_half, "Cells can only be placed in your own half");
+        let cells = if a_moves { &mut battle.cells_a } else { &mut battle.cells_b };
+        assert!(!cells.is_bit_set(x as usize, y as usize), "The cell is taken");
+        cells.set_bit(x as usize, y as usize, true);
+        battle.placements += 1;
+        let storage_before = env::storage_usage();
+        let frame = ReplayFrame::Placement {
+            player_id: env::predecessor_account_id(),
+            x,
+            y,
+            block_height: env::block_index(),
+        };
+        self.internal_record_frame(MatchId::Battle(id), &frame);
+        battle.pot = U128(battle.pot.0 + battle.cell_price.0);
+        battle.last_move_block = env::block_index();
+        if battle.placements == battle.cells_per_player * 2 {
+            battle.status = BattleStatus::Ready;
+        }
+        self.battles.replace(id, &battle);
+
+        let price = battle.cell_price.0;
+        let cost = env::storage_usage().saturating_sub(storage_before) as Balance * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= price + cost, "Requires attached deposit of at least {}", price + cost);
+        if deposit > price + cost {
+            Promise::new(env::predecessor_account_id()).transfer(deposit - price - cost);
+        }
+        battle
+    }
+
+    /// Runs a battle whose cells are all placed and pays the pot to the player with more
+    /// surviving cells, or half to each on a draw. Anyone can call it. Stops when gas runs low,
+    /// after at least one generation, and leaves the battle `Ready` to be continued by the
+    /// next call.
+    pub fn settle_battle(&mut self, id: BattleId) -> Battle {
+        let mut battle = self.internal_get_battle(id);
+        assert_eq!(battle.status, BattleStatus::Ready, "The battle is not ready");
+        let mut budget = GasBudget::new();
+        while battle.settled_generations < battle.generations {
+            (battle.cells_a, battle.cells_b) = two_color_step(&battle.cells_a, &battle.cells_b);
+            battle.settled_generations += 1;
+            if !budget.has_room() {
+                break;
+            }
+        }
+        battle.score_a = battle.cells_a.population();
+        battle.score_b = battle.cells_b.population();
+        if battle.settled_generations < battle.generations {
+            self.battles.replace(id, &battle);
+            return battle;
+        }
+        let player_b = battle.player_b.clone().expect("Ready battles have two players");
+        let winners = match battle.score_a.cmp(&battle.score_b) {
+            std::cmp::Ordering::Greater => vec![battle.player_a.clone()],
+            std::cmp::Ordering::Less => vec![player_b],
+            std::cmp::Ordering::Equal => vec![battle.player_a.clone(), player_b],
+        };
+        battle.winner = if winners.len() == 1 { Some(winners[0].clone()) } else { None };
+        battle.status = BattleStatus::Finished;
+        self.internal_pay_out(&mut battle, &winners);
+        self.battles.replace(id, &battle);
+        self.internal_emit_battle(id, &battle);
+        battle
+    }
+
+    /// Awards the pot to the waiting player once the player to move has let
+    /// `BATTLE_TURN_TIMEOUT` blocks pass. Anyone can call it.
+    pub fn claim_battle_timeout(&mut self, id: BattleId) -> Battle {
+        let mut battle = self.internal_get_battle(id);
+        assert_eq!(battle.status, BattleStatus::Placing, "The battle is not taking placements");
+        assert!(
+            env::block_index() > battle.last_move_block + BATTLE_TURN_TIMEOUT,
+            "The player to move still has time"
+        );
+        let player_b = battle.player_b.clone().expect("Placing battles have two players");
+        let waiting = if battle.player_to_move() == &battle.player_a { player_b } else { battle.player_a.clone() };
+        battle.winner = Some(waiting.clone());
+        battle.status = BattleStatus::Finished;
+        self.internal_pay_out(&mut battle, &[waiting]);
+        self.battles.replace(id, &battle);
+        self.internal_emit_battle(id, &battle);
+        battle
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_get_battle(&self, id: BattleId) -> Battle {
+        self.battles.get(id).expect("No battle")
+    }
+
+    /// Splits the pot evenly between `winners`; any remainder goes to the first.
+    fn internal_pay_out(&mut self, battle: &mut Battle, winners: &[AccountId]) {
+        let pot: Balance = battle.pot.0;
+        battle.pot = U128(0);
+        let share = pot / winners.len() as Balance;
+        for (i, winner) in winners.iter().enumerate() {
+            let amount = if i == 0 { pot - share * (winners.len() as Balance - 1) } else { share };
+            if amount > 0 {
+                Promise::new(winner.clone()).transfer(amount);
+            }
+        }
+    }
+
+    fn internal_emit_battle(&self, id: BattleId, battle: &Battle) {
+        emit_event(
+            "battle_updated",
+            BattleData {
+                battle: id,
+                status: battle.status,
+                winner: battle.winner.as_ref(),
+            },
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Step callbacks: a board can name a contract method to be called after each of its steps,
+//! so other contracts can react to its evolution.
+
+use crate::api::{Permission, API};
+use crate::*;
+use near_sdk::serde_json::json;
+use near_sdk::{Gas, Promise};
+
+/// Gas attached to each callback; the subscriber has to fit its reaction in it.
+pub const GAS_FOR_STEP_CALLBACK: Gas = 10_000_000_000_000;
+pub const MAX_METHOD_NAME_LEN: usize = 64;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepCallback {
+    pub contract_id: AccountId,
+    /// Called with `index`, `generation` and `hash` (base64 SHA-256 of the field).
+    pub method_name: String,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Calls `callback` after every step of the board at `index`, None to stop. The calls are
+    /// fire-and-forget: a failing subscriber doesn't affect the step. Owner only.
+    ///
+    /// Callbacks come from this contract's account, so they may not target it or its shards,
+    /// which trust it as their owner, nor name a method only the contract may call.
+    pub fn set_step_callback(&mut self, index: BoardIndex, callback: Option<StepCallback>) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        if let Some(callback) = &callback {
+            assert!(
+                env::is_valid_account_id(callback.contract_id.as_bytes()),
+                "Invalid callback contract"
+            );
+            assert!(
+                !callback.method_name.is_empty() && callback.method_name.len() <= MAX_METHOD_NAME_LEN,
+                "Invalid callback method"
+            );
+            let current_account_id = env::current_account_id();
+            assert!(
+                callback.contract_id != current_account_id
+                    && !callback.contract_id.ends_with(&format!(".{}", current_account_id)),
+                "Callbacks can't call this contract or its shards"
+            );
+            assert!(
+                !API.iter().any(|method| {
+                    method.name == callback.method_name
+                        && matches!(method.permission, Permission::Contract | Permission::ContractOwner)
+                }),
+                "Callbacks can't call restricted methods"
+            );
+        }
+        board.step_callback = callback;
+        self.internal_save(index, &board);
+        board
+    }
+}
+
+/// Fires the board's step callback for its current generation.
+pub fn notify_step(index: BoardIndex, board: &BoardWithBlock, callback: &StepCallback) {
+    let args = json!({
+        "index": index,
+        "generation": board.generation,
+        "hash": Base64VecU8(board.board.hash()),
+    });
+    Promise::new(callback.contract_id.clone()).function_call(
+        callback.method_name.clone().into_bytes(),
+        args.to_string().into_bytes(),
+        0,
+        GAS_FOR_STEP_CALLBACK,
+    );
+}
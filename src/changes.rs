@@ -0,0 +1,83 @@
+use crate::*;
+
+/// Changes older than this many sequence numbers are dropped.
+pub const MAX_RETAINED_CHANGES: u64 = 64;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BoardChange {
+    pub seq: u64,
+    pub generation: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Set when the board changed size; `delta` is empty and the board must be refetched.
+    pub resized: bool,
+    pub delta: BoardDelta,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChangesPage {
+    pub latest_seq: u64,
+    /// The requested changes are no longer retained: refetch the board and continue from
+    /// `latest_seq`.
+    pub resync_required: bool,
+    pub changes: Vec<BoardChange>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Changes of the board at `index` after sequence number `seq`, oldest first.
+    pub fn get_changes_since(&self, index: BoardIndex, seq: u64) -> ChangesPage {
+        let board = self.internal_get_board(index);
+        let latest_seq = board.change_seq;
+        let oldest_retained = latest_seq.saturating_sub(MAX_RETAINED_CHANGES) + 1;
+        // Changes can also be missing on a board restored from a snapshot.
+        let changes = if seq >= latest_seq {
+            // Nothing newer; also keeps `seq + 1` from overflowing.
+            Some(vec![])
+        } else if seq + 1 < oldest_retained {
+            None
+        } else {
+            (seq + 1..=latest_seq)
+                .map(|s| self.changes.get(&(index, s)))
+                .collect::<Option<Vec<_>>>()
+        };
+        ChangesPage {
+            latest_seq,
+            resync_required: changes.is_none(),
+            changes: changes.unwrap_or_default(),
+        }
+    }
+}
+
+impl Contract {
+    /// Logs an edit from `before` to the current field, see `internal_log_change`, and emits
+    /// the edited board if it has diff events enabled.
+    pub(crate) fn internal_record_change(&mut self, index: BoardIndex, board: &mut BoardWithBlock, before: &Board) -> BoardChange {
+        let change = self.internal_log_change(index, board, before);
+        if board.diff_events {
+            events::emit_board_snapshot(index, board);
+        }
+        change
+    }
+
+    /// Logs the change from `before` to the current field under the next sequence number.
+    pub(crate) fn internal_log_change(&mut self, index: BoardIndex, board: &mut BoardWithBlock, before: &Board) -> BoardChange {
+        board.change_seq += 1;
+        let resized = before.width != board.board.width || before.height != board.board.height;
+        let change = BoardChange {
+            seq: board.change_seq,
+            generation: board.generation,
+            width: board.board.width,
+            height: board.board.height,
+            resized,
+            delta: if resized { BoardDelta::default() } else { before.diff(&board.board) },
+        };
+        self.changes.insert(&(index, board.change_seq), &change);
+        if board.change_seq > MAX_RETAINED_CHANGES {
+            self.changes.remove(&(index, board.change_seq - MAX_RETAINED_CHANGES));
+        }
+        change
+    }
+}
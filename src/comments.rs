@@ -0,0 +1,48 @@
+use crate::fees::charge_storage;
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+
+/// Longest accepted comment, in bytes.
+pub const MAX_COMMENT_LEN: usize = 500;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Comment {
+    pub author_id: AccountId,
+    pub text: String,
+    pub block_height: BlockHeight,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Appends a comment to the board at `index`. The attached deposit must cover the storage
+    /// the comment takes; any surplus is refunded.
+    #[payable]
+    pub fn post_comment(&mut self, index: BoardIndex, text: String) -> u64 {
+        assert!(!text.is_empty(), "Comment is empty");
+        assert!(text.len() <= MAX_COMMENT_LEN, "Comment is too long");
+        let mut board = self.internal_get_board(index);
+        let storage_before = env::storage_usage();
+
+        let number = board.comment_count;
+        let comment = Comment {
+            author_id: env::predecessor_account_id(),
+            text,
+            block_height: env::block_index(),
+        };
+        self.comments.insert(&(index, number), &comment);
+        board.comment_count += 1;
+        self.internal_save(index, &board);
+        charge_storage(storage_before);
+        number
+    }
+
+    /// Up to `limit` comments of the board at `index` starting at number `from_index`, oldest first.
+    pub fn get_comments(&self, index: BoardIndex, from_index: u64, limit: u64) -> Vec<Comment> {
+        let board = self.internal_get_board(index);
+        let end = board.comment_count.min(from_index.saturating_add(limit.min(MAX_PAGE_LEN)));
+        (from_index..end)
+            .filter_map(|number| self.comments.get(&(index, number)))
+            .collect()
+    }
+}
@@ -0,0 +1,347 @@
+//! Step engines. `Naive` checks the neighbours of every cell; `Sparse` only visits the
+//! neighbourhoods of live cells, which is cheaper on mostly empty boards; `BitParallel` adds
+//! up the neighbours of 64 cells of a row at once; `HashLife` steps a quadtree of the board
+//! and reuses the result of every square it has seen before, which pays off on large boards
+//! made of repeated objects. All compute the same generation, so switching engines never
+//! changes a board's history.
+
+use crate::*;
+use near_sdk::Gas;
+use std::collections::HashMap;
+
+/// Boards with at most one live cell in this many pick `Sparse` automatically.
+pub const SPARSE_MAX_DENSITY: usize = 16;
+/// Boards with at least this many cells and at most one live cell in `HASHLIFE_MAX_DENSITY`
+/// pick `HashLife` automatically.
+pub const HASHLIFE_MIN_CELLS: usize = 64 * 64;
+pub const HASHLIFE_MAX_DENSITY: usize = 4;
+/// Narrower boards fill too little of a 64 bit word for `BitParallel` to pay off.
+pub const BIT_PARALLEL_MIN_WIDTH: usize = 8;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Engine {
+    Naive,
+    Sparse,
+    BitParallel,
+    HashLife,
+}
+
+/// Which engine steps a board.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum EngineHint {
+    /// Picks an engine from the board's density on every step.
+    Auto,
+    Always(Engine),
+}
+
+impl EngineHint {
+    pub fn select(&self, board: &Board) -> Engine {
+        match self {
+            EngineHint::Always(engine) => *engine,
+            EngineHint::Auto => {
+                let cells = board.width() * board.height();
+                let population = board.population() as usize;
+                if population * SPARSE_MAX_DENSITY <= cells {
+                    Engine::Sparse
+                } else if cells >= HASHLIFE_MIN_CELLS && population * HASHLIFE_MAX_DENSITY <= cells {
+                    Engine::HashLife
+                } else if board.width() >= BIT_PARALLEL_MIN_WIDTH {
+                    Engine::BitParallel
+                } else {
+                    Engine::Naive
+                }
+            }
+        }
+    }
+}
+
+/// The engine that ran the latest step and the gas it took.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepReport {
+    pub engine: Engine,
+    /// Gas of computing the next generation, without storage and events.
+    pub gas: Gas,
+}
+
+impl Engine {
+    pub fn step(&self, board: &Board) -> Board {
+        match self {
+            Engine::Naive => board.next_generation(),
+            Engine::Sparse => next_generation_sparse(board),
+            Engine::BitParallel => next_generation_bit_parallel(board),
+            Engine::HashLife => next_generation_hashlife(board),
+        }
+    }
+}
+
+/// Same generation as `Board::next_generation`, counting neighbours outward from live cells.
+pub fn next_generation_sparse(board: &Board) -> Board {
+    let (width, height) = (board.width(), board.height());
+    let mut counts = vec![0u8; width * height];
+    for (byte_index, &byte) in board.field.0.iter().enumerate() {
+        if byte == 0 {
+            continue;
+        }
+        for bit_index in 0..8 {
+            let index = byte_index * 8 + bit_index;
+            if (byte >> bit_index) & 1 == 0 || index >= width * height {
+                continue;
+            }
+            let (x, y) = (index % width, index / width);
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    if (nx, ny) != (x, y) {
+                        counts[ny * width + nx] += 1;
+                    }
+                }
+            }
+        }
+    }
+    let mut next = Board::with_dimensions(width, height);
+    for (index, &count) in counts.iter().enumerate() {
+        if count == 3 || count == 2 && board.is_bit_set(index % width, index / width) {
+            next.set_bit(index % width, index / width, true);
+        }
+    }
+    next
+}
+
+/// Same generation as `Board::next_generation`, with each row packed into 64 bit words and
+/// the neighbour counts of a whole word added up bit-sliced, as a 3 bit counter per cell.
+pub fn next_generation_bit_parallel(board: &Board) -> Board {
+    let (width, height) = (board.width(), board.height());
+    let words = width.div_ceil(64);
+    let mut rows = vec![vec![0u64; words]; height + 2];
+    for (x, y) in board.live_cells() {
+        rows[y + 1][x / 64] |= 1 << (x % 64);
+    }
+    let mut next = Board::with_dimensions(width, height);
+    for y in 0..height {
+        for word in 0..words {
+            let (mut s0, mut s1, mut s2) = (0u64, 0u64, 0u64);
+            for (offset, row) in rows[y..y + 3].iter().enumerate() {
+                let center = row[word];
+                // Bit x of `west` holds cell x - 1 and bit x of `east` cell x + 1.
+                let west = center << 1 | if word > 0 { row[word - 1] >> 63 } else { 0 };
+                let east = center >> 1 | if word + 1 < words { row[word + 1] << 63 } else { 0 };
+                // A cell is not its own neighbour.
+                let neighbours: &[u64] = if offset == 1 { &[west, east] } else { &[west, center, east] };
+                for &cells in neighbours {
+                    let carry0 = s0 & cells;
+                    s0 ^= cells;
+                    let carry1 = s1 & carry0;
+                    s1 ^= carry0;
+                    s2 ^= carry1;
+                }
+            }
+            // Eight neighbours wrap the counter to 0, which neither survives nor gives birth.
+            let alive = rows[y + 1][word];
+            let mut born = s1 & !s2 & (s0 | alive);
+            while born != 0 {
+                let x = word * 64 + born.trailing_zeros() as usize;
+                if x < width {
+                    next.set_bit(x, y, true);
+                }
+                born &= born - 1;
+            }
+        }
+    }
+    next
+}
+
+type NodeId = u32;
+
+/// The dead and the live cell, the leaves of every quadtree.
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Hash-consed quadtree nodes: every distinct square is stored once, so its next generation
+/// is computed once however often it appears.
+struct QuadTree {
+    /// Children (nw, ne, sw, se) and level of every node; a level `k` node is `2^k` cells wide.
+    nodes: Vec<([NodeId; 4], u8)>,
+    ids: HashMap<[NodeId; 4], NodeId>,
+    /// Empty node of every level.
+    empty: Vec<NodeId>,
+    /// Centre of a node, one generation on.
+    steps: HashMap<NodeId, NodeId>,
+}
+
+impl QuadTree {
+    fn new() -> Self {
+        Self {
+            nodes: vec![([DEAD; 4], 0), ([DEAD; 4], 0)],
+            ids: HashMap::new(),
+            empty: vec![DEAD],
+            steps: HashMap::new(),
+        }
+    }
+
+    fn level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].1
+    }
+
+    fn children(&self, node: NodeId) -> [NodeId; 4] {
+        self.nodes[node as usize].0
+    }
+
+    fn node(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.ids.get(&children) {
+            return id;
+        }
+        let id = self.nodes.len() as NodeId;
+        let level = self.level(children[0]) + 1;
+        self.nodes.push((children, level));
+        self.ids.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().expect("Level 0 is dead");
+            let empty = self.node([below; 4]);
+            self.empty.push(empty);
+        }
+        self.empty[level as usize]
+    }
+
+    /// The level `level` square whose top left cell is (`x`, `y`) of `board`; cells outside
+    /// the board are dead.
+    fn build(&mut self, board: &Board, x: usize, y: usize, level: u8) -> NodeId {
+        if x >= board.width() || y >= board.height() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return if board.is_bit_set(x, y) { ALIVE } else { DEAD };
+        }
+        let half = 1 << (level - 1);
+        let children = [
+            self.build(board, x, y, level - 1),
+            self.build(board, x + half, y, level - 1),
+            self.build(board, x, y + half, level - 1),
+            self.build(board, x + half, y + half, level - 1),
+        ];
+        self.node(children)
+    }
+
+    /// Sets the live cells of `node` on `board`, with its top left cell at (`x`, `y`).
+    fn write(&self, node: NodeId, board: &mut Board, x: usize, y: usize) {
+        if x >= board.width() || y >= board.height() || self.empty.get(self.level(node) as usize) == Some(&node) {
+            return;
+        }
+        let level = self.level(node);
+        if level == 0 {
+            board.set_bit(x, y, node == ALIVE);
+            return;
+        }
+        let half = 1 << (level - 1);
+        let [nw, ne, sw, se] = self.children(node);
+        self.write(nw, board, x, y);
+        self.write(ne, board, x + half, y);
+        self.write(sw, board, x, y + half);
+        self.write(se, board, x + half, y + half);
+    }
+
+    /// The level `k - 1` square at the centre of level `k` node `node`, as it stands.
+    fn centre(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(node);
+        let children = [self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]];
+        self.node(children)
+    }
+
+    /// The level `k - 1` square at the centre of level `k >= 2` node `node`, one generation on.
+    fn step(&mut self, node: NodeId) -> NodeId {
+        if let Some(&next) = self.steps.get(&node) {
+            return next;
+        }
+        let next = if self.level(node) == 2 {
+            self.step_4x4(node)
+        } else {
+            // The nine overlapping squares of half the size, on a 3x3 grid.
+            let [nw, ne, sw, se] = self.children(node);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+            let north = self.node([nw_ne, ne_nw, nw_se, ne_sw]);
+            let west = self.node([nw_sw, nw_se, sw_nw, sw_ne]);
+            let middle = self.node([nw_se, ne_sw, sw_ne, se_nw]);
+            let east = self.node([ne_sw, ne_se, se_nw, se_ne]);
+            let south = self.node([sw_ne, se_nw, sw_se, se_sw]);
+            let grid = [nw, north, ne, west, middle, east, sw, south, se].map(|square| self.centre(square));
+            let quadrant = |a: usize| [grid[a], grid[a + 1], grid[a + 3], grid[a + 4]];
+            let quadrants = [quadrant(0), quadrant(1), quadrant(3), quadrant(4)];
+            let mut children = [DEAD; 4];
+            for (child, quadrant) in children.iter_mut().zip(quadrants) {
+                let square = self.node(quadrant);
+                *child = self.step(square);
+            }
+            self.node(children)
+        };
+        self.steps.insert(node, next);
+        next
+    }
+
+    /// The centre 2x2 of a 4x4 node, one generation on, by counting neighbours.
+    fn step_4x4(&mut self, node: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (quadrant, child) in self.children(node).into_iter().enumerate() {
+            for (cell, leaf) in self.children(child).into_iter().enumerate() {
+                let x = quadrant % 2 * 2 + cell % 2;
+                let y = quadrant / 2 * 2 + cell / 2;
+                cells[y][x] = leaf == ALIVE;
+            }
+        }
+        let mut next = [DEAD; 4];
+        for (cell, leaf) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + cell % 2, 1 + cell / 2);
+            let block = cells[y - 1..=y + 1].iter().flat_map(|row| &row[x - 1..=x + 1]);
+            let sum = block.filter(|&&cell| cell).count() - cells[y][x] as usize;
+            if sum == 3 || sum == 2 && cells[y][x] {
+                *leaf = ALIVE;
+            }
+        }
+        self.node(next)
+    }
+}
+
+/// Same generation as `Board::next_generation`, stepping a quadtree at least twice the
+/// board's size with the board in its centre, surrounded by dead cells.
+pub fn next_generation_hashlife(board: &Board) -> Board {
+    let (width, height) = (board.width(), board.height());
+    let mut level = 2;
+    while 1 << (level - 1) < width.max(height) {
+        level += 1;
+    }
+    let mut tree = QuadTree::new();
+    // The board fills the top left of the root's centre square, so its next generation is the
+    // top left of the stepped root.
+    let board_node = tree.build(board, 0, 0, level - 1);
+    let empty = tree.empty(level - 2);
+    let [nw, ne, sw, se] = tree.children(board_node);
+    let top_left = tree.node([empty, empty, empty, nw]);
+    let top_right = tree.node([empty, empty, ne, empty]);
+    let bottom_left = tree.node([empty, sw, empty, empty]);
+    let bottom_right = tree.node([se, empty, empty, empty]);
+    let root = tree.node([top_left, top_right, bottom_left, bottom_right]);
+    let next = tree.step(root);
+    let mut result = Board::with_dimensions(width, height);
+    tree.write(next, &mut result, 0, 0);
+    result
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets how the board at `index` picks its step engine. Owner only.
+    pub fn set_engine_hint(&mut self, index: BoardIndex, hint: EngineHint) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        board.engine_hint = hint;
+        self.internal_save(index, &board);
+        board
+    }
+}
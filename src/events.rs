@@ -0,0 +1,138 @@
+//! NEP-297 events: `EVENT_JSON:{"standard":"game_of_life","version":..,"event":..,"data":[..]}`.
+//! Events about a board carry the board's event tag, if it has one, as `data[0].tag`.
+//!
+//! A board's history can be rebuilt from events alone: `board_created` carries its first
+//! field, and with diff events enabled every later change is either a `board_step_diff` or,
+//! for edits and steps too large to list, a `board_snapshot` with the whole field. Each
+//! carries the change's `seq`, see `get_changes_since`.
+
+use crate::*;
+use near_sdk::serde_json::json;
+
+pub const EVENT_STANDARD: &str = "game_of_life";
+pub const EVENT_VERSION: &str = "1.1.0";
+
+/// Longest accepted event tag, in bytes.
+pub const MAX_EVENT_TAG_LEN: usize = 64;
+
+/// Larger deltas are reported as a `board_snapshot` to stay under the log size limit.
+pub const MAX_DIFF_EVENT_CELLS: usize = 1000;
+
+pub fn emit_event<T: Serialize>(event: &str, data: T) {
+    let event = json!({
+        "standard": EVENT_STANDARD,
+        "version": EVENT_VERSION,
+        "event": event,
+        "data": [data],
+    });
+    env::log(format!("EVENT_JSON:{}", event).as_bytes());
+}
+
+/// Emits `event` about `board`, adding the board's event tag to `data`, which serializes to
+/// an object.
+pub fn emit_board_event<T: Serialize>(event: &str, board: &BoardWithBlock, data: T) {
+    let mut data = near_sdk::serde_json::to_value(data).expect("Event data serializes");
+    if let (Some(tag), Some(object)) = (&board.event_tag, data.as_object_mut()) {
+        object.insert("tag".to_string(), json!(tag));
+    }
+    emit_event(event, data);
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct StepDiffData<'a> {
+    index: BoardIndex,
+    seq: u64,
+    generation: u64,
+    born: &'a [(u32, u32)],
+    died: &'a [(u32, u32)],
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct SnapshotData<'a> {
+    index: BoardIndex,
+    seq: u64,
+    generation: u64,
+    width: u32,
+    height: u32,
+    field: &'a Base64VecU8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner_id: Option<&'a AccountId>,
+}
+
+fn snapshot_data(index: BoardIndex, board: &BoardWithBlock) -> SnapshotData<'_> {
+    SnapshotData {
+        index,
+        seq: board.change_seq,
+        generation: board.generation,
+        width: board.board.width,
+        height: board.board.height,
+        field: &board.board.field,
+        owner_id: None,
+    }
+}
+
+/// Announces a new board with its owner and initial field.
+pub fn emit_board_created(index: BoardIndex, board: &BoardWithBlock) {
+    let data = SnapshotData {
+        owner_id: Some(&board.owner_id),
+        ..snapshot_data(index, board)
+    };
+    emit_board_event("board_created", board, data);
+}
+
+/// Reports the board's whole field, which replaces whatever indexers knew of it.
+pub fn emit_board_snapshot(index: BoardIndex, board: &BoardWithBlock) {
+    emit_board_event("board_snapshot", board, snapshot_data(index, board));
+}
+
+pub fn emit_step_diff(index: BoardIndex, board: &BoardWithBlock, change: &BoardChange) {
+    let delta = &change.delta;
+    if change.resized || delta.born.len() + delta.died.len() > MAX_DIFF_EVENT_CELLS {
+        emit_board_snapshot(index, board);
+        return;
+    }
+    emit_board_event(
+        "board_step_diff",
+        board,
+        StepDiffData {
+            index,
+            seq: change.seq,
+            generation: board.generation,
+            born: &delta.born,
+            died: &delta.died,
+        },
+    );
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Emits a `board_step_diff` event with born and died cells on every step of the board
+    /// at `index`, and a `board_snapshot` on every edit. Owner only.
+    pub fn set_diff_events(&mut self, index: BoardIndex, enabled: bool) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        board.diff_events = enabled;
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Sets the tag included in every event about the board at `index`, so indexers can
+    /// follow their application's boards. `None` clears it. Owner only.
+    pub fn set_event_tag(&mut self, index: BoardIndex, tag: Option<String>) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        if let Some(tag) = &tag {
+            assert!(
+                !tag.is_empty() && tag.len() <= MAX_EVENT_TAG_LEN,
+                "Event tags are 1 to {} bytes",
+                MAX_EVENT_TAG_LEN
+            );
+        }
+        board.event_tag = tag;
+        self.internal_save(index, &board);
+        board
+    }
+}
@@ -0,0 +1,137 @@
+//! Protocol fees. The contract owner changes them through a timelock: a new schedule only
+//! takes effect `FEE_CONFIG_TIMELOCK` blocks after it is proposed, so users see it coming.
+
+use crate::*;
+use near_sdk::{Balance, Promise, StorageUsage};
+
+/// Blocks between proposing a fee schedule and it taking effect, about a day.
+pub const FEE_CONFIG_TIMELOCK: BlockHeight = 86_400;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfig {
+    /// Charged for every board created, whether by `create_board`, `split_board`,
+    /// `create_from_template`, `start_scenario`, an import or a shard.
+    pub creation_fee: U128,
+    /// Charged for stepping a board owned by someone else.
+    pub step_fee: U128,
+}
+
+/// No fees at all.
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            creation_fee: U128(0),
+            step_fee: U128(0),
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingFeeConfig {
+    pub config: FeeConfig,
+    pub effective_at: BlockHeight,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Fees in effect at the current block.
+    pub fn get_fee_config(&self) -> FeeConfig {
+        match &self.pending_fee_config {
+            Some(pending) if env::block_index() >= pending.effective_at => pending.config.clone(),
+            _ => self.fee_config.clone(),
+        }
+    }
+
+    pub fn get_pending_fee_config(&self) -> Option<PendingFeeConfig> {
+        self.pending_fee_config.clone().filter(|pending| env::block_index() < pending.effective_at)
+    }
+
+    /// Proposes `config`, replacing any proposal not in effect yet. Contract owner only.
+    pub fn set_fee_config(&mut self, config: FeeConfig) -> PendingFeeConfig {
+        self.assert_contract_owner();
+        self.fee_config = self.get_fee_config();
+        let pending = PendingFeeConfig {
+            config,
+            effective_at: env::block_index() + FEE_CONFIG_TIMELOCK,
+        };
+        self.pending_fee_config = Some(pending.clone());
+        pending
+    }
+
+    /// Withdraws the proposal not in effect yet. Contract owner only.
+    pub fn cancel_fee_config(&mut self) {
+        self.assert_contract_owner();
+        assert!(self.get_pending_fee_config().is_some(), "No pending fee config");
+        self.pending_fee_config = None;
+    }
+}
+
+impl Contract {
+    /// Takes `fee` from the attached deposit into the treasury and refunds the rest. Returns
+    /// the fee.
+    pub(crate) fn internal_charge_fee(&mut self, fee: Balance) -> Balance {
+        self.internal_collect_fee(hold_fee(&env::predecessor_account_id(), fee));
+        fee
+    }
+
+    pub(crate) fn internal_charge_creation_fee(&mut self, boards: u64) -> Balance {
+        let fee = self.internal_creation_fee(boards);
+        self.internal_charge_fee(fee)
+    }
+
+    pub(crate) fn internal_creation_fee(&self, boards: u64) -> Balance {
+        self.get_fee_config().creation_fee.0 * boards as Balance
+    }
+
+    /// Charges `account_id` the step fee from the attached deposit, refunding the surplus to
+    /// them. Nothing is charged on boards `account_id` owns.
+    pub(crate) fn internal_charge_step_fee_for(&mut self, account_id: &AccountId, board: &BoardWithBlock) -> Balance {
+        let fee = hold_fee(account_id, self.internal_step_fee(account_id, board));
+        self.internal_collect_fee(fee);
+        fee
+    }
+
+    /// Collects a fee held by `hold_fee` if its cross-contract call succeeded and
+    /// refunds it to `payer` otherwise.
+    pub(crate) fn internal_settle_held_fee(&mut self, payer: &AccountId, fee: Balance, succeeded: bool) {
+        if succeeded {
+            self.internal_collect_fee(fee);
+        } else if fee > 0 {
+            Promise::new(payer.clone()).transfer(fee);
+        }
+    }
+
+    /// What `account_id` pays to step `board`: nothing for its own boards.
+    pub(crate) fn internal_step_fee(&self, account_id: &AccountId, board: &BoardWithBlock) -> Balance {
+        if *account_id == board.owner_id {
+            return 0;
+        }
+        self.get_fee_config().step_fee.0
+    }
+}
+
+/// Keeps `fee` of the attached deposit and refunds the rest to `payer`, without booking the
+/// fee into the treasury yet. Cross-contract paths hold the fee until their callback knows
+/// whether to collect or refund it.
+pub(crate) fn hold_fee(payer: &AccountId, fee: Balance) -> Balance {
+    let deposit = env::attached_deposit();
+    assert!(deposit >= fee, "Requires attached deposit of at least {}", fee);
+    if deposit > fee {
+        Promise::new(payer.clone()).transfer(deposit - fee);
+    }
+    fee
+}
+
+/// Charges the predecessor for the storage written since usage was `storage_before`, out of
+/// the attached deposit, and refunds the surplus.
+pub(crate) fn charge_storage(storage_before: StorageUsage) -> Balance {
+    charge_storage_bytes(env::storage_usage().saturating_sub(storage_before))
+}
+
+/// Charges the predecessor for `bytes` of storage, for callers that also pay for storage
+/// written later on.
+pub(crate) fn charge_storage_bytes(bytes: StorageUsage) -> Balance {
+    hold_fee(&env::predecessor_account_id(), bytes as Balance * env::storage_byte_cost())
+}
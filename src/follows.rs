@@ -0,0 +1,65 @@
+use crate::events::emit_board_event;
+use crate::fees::charge_storage;
+use crate::*;
+
+/// Most boards one account can follow.
+pub const MAX_FOLLOWED_BOARDS: usize = 100;
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct SteppedData {
+    index: BoardIndex,
+    generation: u64,
+    followers: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Subscribes the caller to the board at `index`. The attached deposit must cover the
+    /// storage the subscription takes; any surplus is refunded.
+    #[payable]
+    pub fn follow_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        let storage_before = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        let mut followed = self.follows.get(&account_id).unwrap_or_default();
+        assert!(!followed.contains(&index), "Already following");
+        assert!(followed.len() < MAX_FOLLOWED_BOARDS, "Following too many boards");
+        followed.push(index);
+        self.follows.insert(&account_id, &followed);
+        board.followers += 1;
+        self.internal_save(index, &board);
+        charge_storage(storage_before);
+        board
+    }
+
+    pub fn unfollow_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        let account_id = env::predecessor_account_id();
+        let mut followed = self.follows.get(&account_id).unwrap_or_default();
+        let position = followed.iter().position(|&i| i == index).expect("Not following");
+        followed.remove(position);
+        self.follows.insert(&account_id, &followed);
+        board.followers -= 1;
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Boards `account_id` follows, in the order they were followed.
+    pub fn get_followed_boards(&self, account_id: AccountId) -> Vec<BoardIndex> {
+        self.follows.get(&account_id).unwrap_or_default()
+    }
+}
+
+/// Notifies followers of a new generation of the board at `index`.
+pub fn emit_board_stepped(index: BoardIndex, board: &BoardWithBlock) {
+    emit_board_event(
+        "board_stepped",
+        board,
+        SteppedData {
+            index,
+            generation: board.generation,
+            followers: board.followers,
+        },
+    );
+}
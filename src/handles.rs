@@ -0,0 +1,39 @@
+use crate::fees::charge_storage;
+use crate::*;
+
+pub const MIN_HANDLE_LEN: usize = 3;
+pub const MAX_HANDLE_LEN: usize = 64;
+
+/// Lowercase letters, digits, `-` and `_`.
+fn is_valid_handle(handle: &str) -> bool {
+    (MIN_HANDLE_LEN..=MAX_HANDLE_LEN).contains(&handle.len())
+        && handle
+            .bytes()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-' || c == b'_')
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Names the board at `index`, releasing its previous handle. Owner only; handles are unique.
+    /// The attached deposit must cover the storage of a longer handle; any surplus is refunded.
+    #[payable]
+    pub fn claim_handle(&mut self, index: BoardIndex, handle: String) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert!(is_valid_handle(&handle), "Invalid handle");
+        assert!(self.handles.get(&handle).is_none(), "The handle is taken");
+        let storage_before = env::storage_usage();
+        if let Some(old) = board.handle.replace(handle.clone()) {
+            self.handles.remove(&old);
+        }
+        self.handles.insert(&handle, &index);
+        self.internal_save(index, &board);
+        charge_storage(storage_before);
+        board
+    }
+
+    pub fn get_board_by_handle(&self, handle: String) -> Option<(BoardIndex, BoardWithBlock)> {
+        let index = self.handles.get(&handle)?;
+        Some((index, self.internal_get_board(index)))
+    }
+}
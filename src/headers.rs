@@ -0,0 +1,50 @@
+//! Lightweight board headers, stored apart from the boards so gallery pages can list many
+//! boards without reading their fields. Headers also hold the boards' similarity fingerprints.
+
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BoardHeader {
+    pub index: BoardIndex,
+    pub owner_id: AccountId,
+    pub width: u32,
+    pub height: u32,
+    pub generation: u64,
+    pub population: u32,
+    /// Block of the latest step.
+    pub current_block_height: BlockHeight,
+    #[serde(skip)]
+    pub hidden: bool,
+    #[serde(skip)]
+    pub fingerprint: Fingerprint,
+}
+
+impl BoardHeader {
+    pub fn new(index: BoardIndex, board: &BoardWithBlock) -> Self {
+        Self {
+            index,
+            owner_id: board.owner_id.clone(),
+            width: board.board.width,
+            height: board.board.height,
+            generation: board.generation,
+            population: board.board.population(),
+            current_block_height: board.current_block_height,
+            hidden: board.hidden,
+            fingerprint: Fingerprint::new(&board.board),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Headers of up to `limit` boards that are not hidden, starting at index `from_index`.
+    pub fn get_board_headers(&self, from_index: BoardIndex, limit: u64) -> Vec<BoardHeader> {
+        (from_index..self.board_headers.len())
+            .filter_map(|index| self.board_headers.get(index))
+            .filter(|header| !header.hidden)
+            .take(limit.min(MAX_PAGE_LEN) as usize)
+            .collect()
+    }
+}
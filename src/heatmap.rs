@@ -0,0 +1,74 @@
+use crate::fees::charge_storage;
+use crate::*;
+
+/// Number of generations each cell has been alive, row by row.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Heatmap {
+    pub width: u32,
+    pub height: u32,
+    pub counts: Vec<u32>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts (from the current generation) or stops accumulating the heatmap of the board
+    /// at `index`. Owner only. The attached deposit must cover the storage of the counts;
+    /// any surplus is refunded.
+    #[payable]
+    pub fn set_heatmap_tracking(&mut self, index: BoardIndex, enabled: bool) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        let storage_before = env::storage_usage();
+        board.track_heatmap = enabled;
+        self.heatmaps.remove(&index);
+        if enabled {
+            self.internal_update_heatmap(index, &board.board);
+        }
+        self.internal_save(index, &board);
+        charge_storage(storage_before);
+        board
+    }
+
+    /// None unless heatmap tracking is enabled for the board at `index`.
+    pub fn get_heatmap(&self, index: BoardIndex) -> Option<Heatmap> {
+        let board = self.internal_get_board(index);
+        if !board.track_heatmap {
+            return None;
+        }
+        let cells = board.board.width() * board.board.height();
+        // Counts of a board that was just resized are reset on its next step.
+        let counts = self
+            .heatmaps
+            .get(&index)
+            .filter(|counts| counts.len() == cells)
+            .unwrap_or_else(|| vec![0; cells]);
+        Some(Heatmap {
+            width: board.board.width,
+            height: board.board.height,
+            counts,
+        })
+    }
+}
+
+impl Contract {
+    /// Counts another generation for every live cell. A board that changed size starts over.
+    pub(crate) fn internal_update_heatmap(&mut self, index: BoardIndex, board: &Board) {
+        let cells = board.width() * board.height();
+        let mut counts = self
+            .heatmaps
+            .get(&index)
+            .filter(|counts| counts.len() == cells)
+            .unwrap_or_else(|| vec![0; cells]);
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if board.is_bit_set(x, y) {
+                    let count = &mut counts[y * board.width() + x];
+                    *count = count.saturating_add(1);
+                }
+            }
+        }
+        self.heatmaps.insert(&index, &counts);
+    }
+}
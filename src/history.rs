@@ -0,0 +1,254 @@
+use crate::*;
+use near_sdk::{Balance, Promise};
+
+/// Default number of generations between stored snapshots.
+pub const CHECKPOINT_INTERVAL: u64 = 16;
+/// Oldest snapshots are dropped once a board has this many.
+pub const MAX_CHECKPOINTS: usize = 32;
+/// Number of most recent generations kept under `HistoryPolicy::Full`.
+pub const MAX_FULL_HISTORY: usize = 128;
+/// Oldest population entries are dropped once a board has this many.
+pub const MAX_POPULATION_HISTORY: usize = 128;
+
+/// NEAR charges every stored key-value record for this many bytes on top of its size.
+const STORAGE_RECORD_OVERHEAD: u64 = 40;
+
+/// Longest account id NEAR accepts.
+const MAX_ACCOUNT_ID_LEN: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageEstimate {
+    /// Upper bound of the bytes the board and its history occupy.
+    pub bytes: u64,
+    /// Storage staking cost of `bytes`.
+    pub cost: U128,
+    /// Part of `cost` the owner deposits; the rest is covered by the contract.
+    pub required_deposit: U128,
+}
+
+/// How much of a board's past is kept in storage.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum HistoryPolicy {
+    None,
+    PopulationsOnly,
+    /// Populations plus a snapshot every `every` generations.
+    Checkpoints { every: u64 },
+    /// Populations plus a snapshot of every generation.
+    Full,
+}
+
+impl Default for HistoryPolicy {
+    fn default() -> Self {
+        HistoryPolicy::Checkpoints { every: CHECKPOINT_INTERVAL }
+    }
+}
+
+impl HistoryPolicy {
+    pub fn keeps_populations(&self) -> bool {
+        !matches!(self, HistoryPolicy::None)
+    }
+
+    pub fn checkpoint_interval(&self) -> Option<u64> {
+        match self {
+            HistoryPolicy::Checkpoints { every } => Some(*every),
+            HistoryPolicy::Full => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn max_checkpoints(&self) -> usize {
+        match self {
+            HistoryPolicy::Checkpoints { .. } => MAX_CHECKPOINTS,
+            HistoryPolicy::Full => MAX_FULL_HISTORY,
+            _ => 0,
+        }
+    }
+
+    /// Upper bound of the storage the policy uses for a board with a `field_len` bytes field.
+    pub fn storage_bytes(&self, field_len: u64) -> u64 {
+        let mut bytes = 0;
+        if self.keeps_populations() {
+            // Key is the storage prefix plus the board index, value is a length-prefixed Vec.
+            bytes += STORAGE_RECORD_OVERHEAD + 9 + 4 + MAX_POPULATION_HISTORY as u64 * 12;
+        }
+        // Key is the prefix plus (index, generation), value is a Board stored packed at worst,
+        // plus its entry in `BoardWithBlock::checkpoints`.
+        let checkpoint = STORAGE_RECORD_OVERHEAD + 17 + 13 + field_len + 8;
+        bytes += self.max_checkpoints() as u64 * checkpoint;
+        bytes
+    }
+
+    /// Deposit the owner pays for the policy; the default policy is covered by the contract.
+    pub fn required_deposit(&self, field_len: u64) -> Balance {
+        let extra = self
+            .storage_bytes(field_len)
+            .saturating_sub(HistoryPolicy::default().storage_bytes(field_len));
+        extra as Balance * env::storage_byte_cost()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// State of the board at `index` as of a past `generation`, replayed from the nearest
+    /// stored snapshot. None if that generation is no longer (or not yet) available.
+    /// Generations computed by a rule oracle or from a tile's neighbours can't be replayed, so
+    /// they are all snapshotted as they happen.
+    pub fn get_generation(&self, index: BoardIndex, generation: u64) -> Option<Board> {
+        let board = self.internal_get_board(index);
+        if generation > board.generation {
+            return None;
+        }
+        if generation == board.generation {
+            return Some(board.board);
+        }
+        let position = board.checkpoints.partition_point(|&g| g <= generation);
+        if position == 0 {
+            return None;
+        }
+        let checkpoint = board.checkpoints[position - 1];
+        let mut state = self.checkpoints.get(&(index, checkpoint)).expect("Missing checkpoint");
+        for _ in checkpoint..generation {
+            state = state.next_generation();
+        }
+        Some(state)
+    }
+
+    /// Recorded (generation, population) pairs of the board at `index`, oldest first.
+    pub fn get_population_history(&self, index: BoardIndex) -> Vec<(u64, u32)> {
+        self.populations.get(&index).unwrap_or_default()
+    }
+
+    /// Storage a `width` x `height` board keeping `history_policy` will use at most, and the
+    /// deposit its owner has to attach for it.
+    pub fn estimate_storage_cost(&self, width: u32, height: u32, history_policy: HistoryPolicy) -> StorageEstimate {
+        let (width, height) = (width as usize, height as usize);
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert!(width <= MAX_WIDTH && height <= MAX_HEIGHT, "Board dimensions are too large");
+        let field_len = field_len(width, height) as u64;
+
+        let empty = Board::with_dimensions(width, height);
+        let empty_len = empty.try_to_vec().expect("Board serializes").len() as u64;
+        let fresh = BoardWithBlock::new(empty, "x".repeat(MAX_ACCOUNT_ID_LEN), Some(0));
+        let fresh_len = fresh.try_to_vec().expect("Board serializes").len() as u64;
+        // The current, genesis and previous fields stored packed at worst, plus the full
+        // centroid and hash windows.
+        let packed_len = 13 + field_len;
+        let record = fresh_len - 2 * empty_len
+            + 3 * packed_len
+            + analysis::DRIFT_WINDOW as u64 * 24
+            + fresh.max_period * 8;
+        let bytes = STORAGE_RECORD_OVERHEAD + 9 + record + history_policy.storage_bytes(field_len);
+        StorageEstimate {
+            bytes,
+            cost: U128(bytes as Balance * env::storage_byte_cost()),
+            required_deposit: U128(history_policy.required_deposit(field_len)),
+        }
+    }
+
+    /// Changes how much history the board at `index` keeps. Owner only. Policies storing more
+    /// than the default require a deposit covering their storage; any surplus, including a
+    /// deposit made for a previous policy, is refunded.
+    #[payable]
+    pub fn set_history_policy(&mut self, index: BoardIndex, policy: HistoryPolicy) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        if let HistoryPolicy::Checkpoints { every } = policy {
+            assert!(every > 0, "Checkpoint interval must be positive");
+        }
+        let required = policy.required_deposit(board.board.field.0.len() as u64);
+        let available = board.history_deposit.0 + env::attached_deposit();
+        assert!(
+            available >= required,
+            "Requires attached deposit of at least {}",
+            required - board.history_deposit.0
+        );
+        board.history_deposit = U128(required);
+        board.history_policy = policy;
+
+        if policy.checkpoint_interval().is_none() {
+            self.internal_truncate_checkpoints(index, &mut board, 0);
+        }
+        if !policy.keeps_populations() {
+            self.populations.remove(&index);
+        }
+        self.internal_record_edit(index, &mut board);
+        self.internal_save(index, &board);
+
+        let refund = available - required;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+        board
+    }
+}
+
+impl Contract {
+    /// Records a freshly stepped generation according to the board's policy.
+    pub(crate) fn internal_record_step(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        if board.history_policy.keeps_populations() {
+            self.internal_record_population(index, board);
+        }
+        if let Some(every) = board.history_policy.checkpoint_interval() {
+            let replayable = board.rule_oracle.is_none() && !board.edge_exchange;
+            if !replayable || board.generation.is_multiple_of(every) {
+                self.internal_record_checkpoint(index, board);
+            }
+        }
+    }
+
+    /// After an edit the current field becomes the base for replaying later generations.
+    pub(crate) fn internal_record_edit(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        let generation = board.generation;
+        self.internal_truncate_history(index, board, generation);
+        if board.history_policy.keeps_populations() {
+            self.internal_record_population(index, board);
+        }
+        if board.history_policy.checkpoint_interval().is_some() {
+            self.internal_record_checkpoint(index, board);
+        }
+    }
+
+    /// Forgets history from `generation` onwards, which no longer describes the board's past.
+    pub(crate) fn internal_truncate_history(&mut self, index: BoardIndex, board: &mut BoardWithBlock, generation: u64) {
+        self.internal_truncate_checkpoints(index, board, generation);
+        if let Some(mut populations) = self.populations.get(&index) {
+            let len = populations.partition_point(|&(g, _)| g < generation);
+            if len < populations.len() {
+                populations.truncate(len);
+                self.populations.insert(&index, &populations);
+            }
+        }
+    }
+
+    fn internal_truncate_checkpoints(&mut self, index: BoardIndex, board: &mut BoardWithBlock, generation: u64) {
+        while let Some(&last) = board.checkpoints.last() {
+            if last < generation {
+                break;
+            }
+            self.checkpoints.remove(&(index, last));
+            board.checkpoints.pop();
+        }
+    }
+
+    /// Stores a snapshot of the current field, dropping the oldest one if over the limit.
+    fn internal_record_checkpoint(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        self.checkpoints.insert(&(index, board.generation), &board.board);
+        board.checkpoints.push(board.generation);
+        while board.checkpoints.len() > board.history_policy.max_checkpoints() {
+            let oldest = board.checkpoints.remove(0);
+            self.checkpoints.remove(&(index, oldest));
+        }
+    }
+
+    fn internal_record_population(&mut self, index: BoardIndex, board: &BoardWithBlock) {
+        let mut populations = self.populations.get(&index).unwrap_or_default();
+        populations.push((board.generation, board.board.population()));
+        if populations.len() > MAX_POPULATION_HISTORY {
+            populations.remove(0);
+        }
+        self.populations.insert(&index, &populations);
+    }
+}
@@ -0,0 +1,134 @@
+//! Copying boards from other deployments of this contract.
+
+use crate::*;
+use crate::fees::hold_fee;
+use near_sdk::{ext_contract, Gas, Promise, PromiseResult};
+
+const GAS_FOR_SOURCE_VIEW: Gas = 30_000_000_000_000;
+const GAS_FOR_IMPORT_CALLBACK: Gas = 30_000_000_000_000;
+
+/// Where an imported board came from.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Provenance {
+    pub contract_id: AccountId,
+    pub index: BoardIndex,
+    pub owner_id: AccountId,
+    /// Generation of the source board when it was copied.
+    pub generation: u64,
+    pub imported_at: BlockHeight,
+}
+
+/// The part of a remote `BoardWithBlock` an import needs; other fields are ignored so boards
+/// of older and newer deployments can be read. Boards of the first release have neither an
+/// owner nor a generation count.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RemoteBoard {
+    pub board: RemoteField,
+    #[serde(default)]
+    pub owner_id: Option<AccountId>,
+    #[serde(default)]
+    pub generation: u64,
+}
+
+/// A remote `Board`. Boards of the first release were always `WIDTH` x `HEIGHT` and had just
+/// the field.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RemoteField {
+    #[serde(default = "default_width")]
+    pub width: u32,
+    #[serde(default = "default_height")]
+    pub height: u32,
+    pub field: Base64VecU8,
+}
+
+fn default_width() -> u32 {
+    WIDTH as u32
+}
+
+fn default_height() -> u32 {
+    HEIGHT as u32
+}
+
+#[ext_contract(ext_source)]
+pub trait SourceContract {
+    fn get_board(&self, index: BoardIndex) -> Option<RemoteBoard>;
+}
+
+#[ext_contract(ext_import)]
+pub trait ImportCallbacks {
+    fn on_board_imported(
+        &mut self,
+        source_contract: AccountId,
+        source_index: BoardIndex,
+        owner_id: AccountId,
+        fee: U128,
+    ) -> Option<BoardIndex>;
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Recreates board `source_index` of `source_contract` here, owned by the caller and
+    /// recording where it came from. The creation fee is held until the copy succeeds and
+    /// refunded otherwise. Resolves to the new board's index, or `None` if the source had no
+    /// board that fits here.
+    #[payable]
+    pub fn import_board(&mut self, source_contract: AccountId, source_index: BoardIndex) -> Promise {
+        assert!(
+            env::is_valid_account_id(source_contract.as_bytes()),
+            "Invalid source contract"
+        );
+        assert_ne!(source_contract, env::current_account_id(), "The board is already here");
+        let fee = hold_fee(&env::predecessor_account_id(), self.internal_creation_fee(1));
+        ext_source::get_board(source_index, &source_contract, 0, GAS_FOR_SOURCE_VIEW).then(
+            ext_import::on_board_imported(
+                source_contract.clone(),
+                source_index,
+                env::predecessor_account_id(),
+                U128(fee),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_IMPORT_CALLBACK,
+            ),
+        )
+    }
+
+    #[private]
+    pub fn on_board_imported(
+        &mut self,
+        source_contract: AccountId,
+        source_index: BoardIndex,
+        owner_id: AccountId,
+        fee: U128,
+    ) -> Option<BoardIndex> {
+        let remote = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<Option<RemoteBoard>>(&value).ok().flatten()
+            }
+            _ => None,
+        };
+        let remote = remote.filter(|remote| {
+            let (width, height) = (remote.board.width as usize, remote.board.height as usize);
+            (1..=MAX_WIDTH).contains(&width)
+                && (1..=MAX_HEIGHT).contains(&height)
+                && remote.board.field.0.len() == field_len(width, height)
+        });
+        self.internal_settle_held_fee(&owner_id, fee.0, remote.is_some());
+        let remote = remote?;
+        let RemoteField { width, height, field } = remote.board;
+        let board = Board::from_field(width as usize, height as usize, field);
+        let mut imported = BoardWithBlock::new(board, owner_id, None);
+        // Boards without an owner are credited to the contract they came from.
+        let source_owner = remote.owner_id.unwrap_or_else(|| source_contract.clone());
+        imported.imported_from = Some(Provenance {
+            contract_id: source_contract,
+            index: source_index,
+            owner_id: source_owner,
+            generation: remote.generation,
+            imported_at: env::block_index(),
+        });
+        Some(self.internal_add_board(&imported))
+    }
+}
@@ -0,0 +1,94 @@
+//! Consistency checks of stored boards, to catch storage corruption early as the schema grows.
+//! Debug builds run them every time a board is saved.
+
+use crate::*;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvariantReport {
+    pub index: BoardIndex,
+    /// Descriptions of the broken invariants, empty for a consistent board.
+    pub violations: Vec<String>,
+}
+
+impl InvariantReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+fn check_field(name: &str, board: &Board, violations: &mut Vec<String>) {
+    if board.width == 0 || board.height == 0 {
+        violations.push(format!("{} has no cells", name));
+    } else if board.field.0.len() != field_len(board.width(), board.height()) {
+        violations.push(format!(
+            "{} field is {} bytes, {}x{} needs {}",
+            name,
+            board.field.0.len(),
+            board.width,
+            board.height,
+            field_len(board.width(), board.height())
+        ));
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Checks the board at `index`: field lengths match the dimensions, the latest recorded
+    /// population and hash match the field, and generations and blocks are ordered.
+    pub fn check_invariants(&self, index: BoardIndex) -> InvariantReport {
+        let board = self.internal_get_board(index);
+        self.internal_check_invariants(index, &board)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_check_invariants(&self, index: BoardIndex, board: &BoardWithBlock) -> InvariantReport {
+        let mut violations = vec![];
+        check_field("board", &board.board, &mut violations);
+        check_field("genesis", &board.genesis, &mut violations);
+        if let Some(prev) = &board.prev_board {
+            check_field("previous board", prev, &mut violations);
+        }
+        if !violations.is_empty() {
+            // The remaining checks read the field.
+            return InvariantReport { index, violations };
+        }
+
+        if board.recent_hashes.last() != Some(&board.board.short_hash()) {
+            violations.push("latest recorded hash doesn't match the field".to_string());
+        }
+        if let Some((generation, population)) = self.populations.get(&index).and_then(|p| p.last().copied()) {
+            if generation > board.generation {
+                violations.push(format!("population recorded for future generation {}", generation));
+            } else if generation == board.generation && population != board.board.population() {
+                violations.push(format!(
+                    "recorded population {} doesn't match the field's {}",
+                    population,
+                    board.board.population()
+                ));
+            }
+        }
+        if !board.checkpoints.windows(2).all(|pair| pair[0] < pair[1]) {
+            violations.push("checkpoints are out of order".to_string());
+        }
+        if board.checkpoints.last().is_some_and(|&last| last > board.generation) {
+            violations.push("checkpoint of a future generation".to_string());
+        }
+        if board.prev_block_height > board.current_block_height {
+            violations.push("previous step block is after the current one".to_string());
+        }
+        if board.current_block_height > env::block_index() {
+            violations.push("current block is in the future".to_string());
+        }
+        InvariantReport { index, violations }
+    }
+
+    /// Panics on an inconsistent board in debug builds; release builds skip the checks.
+    pub(crate) fn internal_debug_check_invariants(&self, index: BoardIndex, board: &BoardWithBlock) {
+        if cfg!(debug_assertions) {
+            let report = self.internal_check_invariants(index, board);
+            assert!(report.is_ok(), "Board {} is inconsistent: {:?}", index, report.violations);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Collaborative layers: contributors paint cells into their own pending layer, and all
+//! layers are OR-merged into the board right before its next step.
+
+use crate::fees::charge_storage;
+use crate::*;
+
+/// Most contributors with a pending layer on one board.
+pub const MAX_LAYERS: usize = 16;
+
+#[near_bindgen]
+impl Contract {
+    /// Lets anyone paint a layer on the board at `index`. Disabling discards pending layers.
+    /// Owner only.
+    pub fn set_layers_enabled(&mut self, index: BoardIndex, enabled: bool) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        board.layers_enabled = enabled;
+        if !enabled {
+            for account_id in std::mem::take(&mut board.layer_owners) {
+                self.layers.remove(&(index, account_id));
+            }
+        }
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Sets `cells` to `alive` in the caller's pending layer of the board at `index`. The
+    /// attached deposit must cover any storage the layer adds; any surplus is refunded.
+    #[payable]
+    pub fn paint_layer(&mut self, index: BoardIndex, cells: Vec<(u32, u32)>, alive: bool) -> Board {
+        self.internal_check_rate_limit();
+        let storage_before = env::storage_usage();
+        let mut board = self.internal_get_board(index);
+        assert!(board.layers_enabled, "Layers are disabled for this board");
+        assert_not_frozen(&board);
+        self.internal_spend_action_points(&board, 0, cells.len() as u64);
+        let account_id = env::predecessor_account_id();
+        let key = (index, account_id.clone());
+        let mut layer = match self.layers.get(&key) {
+            Some(layer) => layer,
+            None => {
+                assert!(board.layer_owners.len() < MAX_LAYERS, "Too many pending layers");
+                board.layer_owners.push(account_id);
+                self.internal_save(index, &board);
+                Board::with_dimensions(board.board.width(), board.board.height())
+            }
+        };
+        for (x, y) in cells {
+            assert!(x < layer.width && y < layer.height, "Cell is outside the board");
+            layer.set_bit(x as usize, y as usize, alive);
+        }
+        self.layers.insert(&key, &layer);
+        charge_storage(storage_before);
+        layer
+    }
+
+    pub fn get_layer(&self, index: BoardIndex, account_id: AccountId) -> Option<Board> {
+        self.layers.get(&(index, account_id))
+    }
+}
+
+impl Contract {
+    /// Adds the live cells of every pending layer to the board and clears the layers. Layers
+    /// painted before the board was resized are dropped.
+    pub(crate) fn internal_merge_layers(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        if board.layer_owners.is_empty() {
+            return;
+        }
+        let mut merged = board.board.clone();
+        for account_id in std::mem::take(&mut board.layer_owners) {
+            let layer = match self.layers.remove(&(index, account_id)) {
+                Some(layer) if layer.width == merged.width && layer.height == merged.height => layer,
+                _ => continue,
+            };
+            for (x, y) in layer.live_cells() {
+                merged.set_bit(x, y, true);
+            }
+        }
+        if merged != board.board {
+            let before = board.set_board(merged);
+            self.internal_record_edit(index, board);
+            self.internal_record_change(index, board, &before);
+        }
+    }
+}
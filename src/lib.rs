@@ -1,8 +1,95 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::Vector;
-use near_sdk::json_types::Base64VecU8;
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near_bindgen, BorshStorageKey, PanicOnDefault, BlockHeight};
+use near_sdk::{env, near_bindgen, AccountId, BorshStorageKey, PanicOnDefault, BlockHeight};
+
+mod achievements;
+mod action_points;
+mod analysis;
+mod api;
+mod batch;
+mod battle;
+mod callbacks;
+mod changes;
+mod comments;
+mod engines;
+mod events;
+mod fees;
+mod follows;
+mod font;
+mod handles;
+mod headers;
+mod heatmap;
+mod history;
+mod imports;
+mod invariants;
+mod layers;
+mod limits;
+mod metadata;
+mod migration;
+mod moderation;
+mod museum;
+mod patterns;
+mod rate_limit;
+mod render;
+mod replays;
+mod rle;
+mod rule_oracle;
+mod scenarios;
+mod schedule;
+mod seasons;
+mod shards;
+mod share;
+mod similarity;
+#[cfg(test)]
+mod simulation;
+mod snapshots;
+mod templates;
+mod tournament;
+mod trail;
+mod treasury;
+mod trophies;
+mod universe;
+
+pub use crate::achievements::{Achievement, AchievementKind};
+pub use crate::action_points::ActionPointConfig;
+pub use crate::api::{ArgInfo, MethodInfo, MethodKind, Permission};
+pub use crate::analysis::{
+    BoundingBox, Census, Centroid, Complexity, Direction, Drift, ObjectCount, SpaceshipReport, Symmetry,
+    SymmetryReport,
+};
+pub use crate::moderation::Flag;
+pub use crate::patterns::PatternKind;
+pub use crate::batch::BatchResult;
+pub use crate::battle::{Battle, BattleId, BattleStatus};
+pub use crate::callbacks::StepCallback;
+pub use crate::changes::{BoardChange, ChangesPage};
+pub use crate::comments::Comment;
+pub use crate::engines::{Engine, EngineHint, StepReport};
+pub use crate::fees::{FeeConfig, PendingFeeConfig};
+pub use crate::headers::BoardHeader;
+pub use crate::heatmap::Heatmap;
+pub use crate::history::{HistoryPolicy, StorageEstimate};
+pub use crate::imports::Provenance;
+pub use crate::invariants::InvariantReport;
+pub use crate::rate_limit::RateLimit;
+pub use crate::rule_oracle::HeldStep;
+pub use crate::render::{ImageFormat, RenderMode};
+pub use crate::replays::{MatchId, ReplayFrame};
+pub use crate::schedule::{ScheduleId, ScheduledStep};
+pub use crate::seasons::{LeaderboardEntry, Season, SeasonId};
+pub use crate::shards::{BoardLocation, RoutedBoard, Shard, ShardId};
+pub use crate::similarity::Fingerprint;
+pub use crate::templates::{Template, TemplateKind, TemplateParams};
+pub use crate::trail::Trail;
+pub use crate::treasury::Treasury;
+pub use crate::trophies::Trophy;
+pub use crate::tournament::{
+    Contender, Matchup, MatchupProgress, Tournament, TournamentEntry, TournamentId, TournamentMetric,
+};
+
+use crate::fees::charge_storage_bytes;
 
 near_sdk::setup_alloc!(); // Memory init
 
@@ -13,36 +100,132 @@ const HEIGHT: usize = 16;
 
 const FIELD_LEN: usize = (WIDTH / 8) * HEIGHT;
 
+/// Most generations an auto-stepping board catches up in one call; older lag is skipped.
+const MAX_CATCH_UP_STEPS: u64 = 16;
+
+/// Most generations a preview view may compute.
+const MAX_PREVIEW_GENERATIONS: u64 = 256;
+
+/// Most characters `write_text` draws in one call.
+const MAX_TEXT_LEN: usize = 64;
+
+const MAX_WIDTH: usize = 256;
+const MAX_HEIGHT: usize = 256;
+
+/// Tallest board `debug_logs` prints row by row.
+const MAX_LOGGED_ROWS: usize = 32;
+
+/// Which part of a board stays in place when it is resized.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Offset applied to existing cells along each axis when going from `old` to `new` size.
+    fn offsets(&self, old: (usize, usize), new: (usize, usize)) -> (i64, i64) {
+        // 0 keeps the start edge, 1 the middle and 2 the end edge in place.
+        let (horizontal, vertical) = match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        };
+        let offset = |old: usize, new: usize, k: i64| (new as i64 - old as i64) * k / 2;
+        (offset(old.0, new.0, horizontal), offset(old.1, new.1, vertical))
+    }
+}
+
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+/// Cells that changed between two generations of a board, as (x, y).
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BoardDelta {
+    pub born: Vec<(u32, u32)>,
+    pub died: Vec<(u32, u32)>,
+}
+
+/// Stored with a custom Borsh encoding, see `rle`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Board {
+    pub width: u32,
+    pub height: u32,
     pub field: Base64VecU8
 }
 
+pub fn field_len(width: usize, height: usize) -> usize {
+    (width * height).div_ceil(8)
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Board { 
     pub fn new() -> Self {
         Self {
+            width: WIDTH as u32,
+            height: HEIGHT as u32,
             field: Base64VecU8::from(vec![0u8; FIELD_LEN]),
         }
     }
 
+    pub fn with_dimensions(width: usize, height: usize) -> Self {
+        Self {
+            width: width as u32,
+            height: height as u32,
+            field: Base64VecU8::from(vec![0u8; field_len(width, height)]),
+        }
+    }
+
     pub fn from(field: Base64VecU8) -> Self {
-        assert_eq!(field.0.len(), FIELD_LEN);
+        Self::from_field(WIDTH, HEIGHT, field)
+    }
+
+    pub fn from_field(width: usize, height: usize, field: Base64VecU8) -> Self {
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert_eq!(field.0.len(), field_len(width, height));
         Self {
+            width: width as u32,
+            height: height as u32,
             field,
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.height as usize
+    }
+
     pub fn is_bit_set(&self, x: usize, y: usize) -> bool {
-        let index = y * WIDTH + x;
+        let index = y * self.width() + x;
         let byte_index = index / 8;
         let bit_index = index & 7; // byte_index % 8
         ((self.field.0[byte_index] >> bit_index) & 1) != 0
     }
 
     pub fn set_bit(&mut self, x: usize, y: usize, bit: bool) {
-        let index = y * WIDTH + x;
+        let index = y * self.width() + x;
         let byte_index = index / 8;
         let bit_index = index & 7; // byte_index % 8
         self.field.0[byte_index] |= 1 << bit_index ;
@@ -51,17 +234,182 @@ impl Board {
         }
     }
 
+    /// Copies the `width` x `height` region starting at (`x`, `y`) into a new board.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Board {
+        let mut board = Board::with_dimensions(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                if self.is_bit_set(x + i, y + j) {
+                    board.set_bit(i, j, true);
+                }
+            }
+        }
+        board
+    }
+
+    /// Copies live cells into a new `width` x `height` board, moved by (`dx`, `dy`).
+    /// Cells that end up outside the new board are dropped.
+    fn translated(&self, width: usize, height: usize, dx: i64, dy: i64) -> Board {
+        let mut board = Board::with_dimensions(width, height);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let nx = x as i64 + dx;
+                let ny = y as i64 + dy;
+                if nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height
+                    && self.is_bit_set(x, y) {
+                    board.set_bit(nx as usize, ny as usize, true);
+                }
+            }
+        }
+        board
+    }
+
+    /// Grows (padding with dead cells) or crops the board, keeping content attached to `anchor`.
+    pub fn resized(&self, width: usize, height: usize, anchor: Anchor) -> Board {
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert!(width <= MAX_WIDTH && height <= MAX_HEIGHT, "Board dimensions are too large");
+        let (dx, dy) = anchor.offsets((self.width(), self.height()), (width, height));
+        self.translated(width, height, dx, dy)
+    }
+
+    /// Moves every cell by (`dx`, `dy`). With `wrap` cells leaving one edge re-enter on the
+    /// opposite one, otherwise they are discarded.
+    pub fn shifted(&self, dx: i64, dy: i64, wrap: bool) -> Board {
+        if !wrap {
+            return self.translated(self.width(), self.height(), dx, dy);
+        }
+        let width = self.width() as i64;
+        let height = self.height() as i64;
+        let mut board = Board::with_dimensions(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.is_bit_set(x, y) {
+                    let nx = (x as i64 + dx).rem_euclid(width);
+                    let ny = (y as i64 + dy).rem_euclid(height);
+                    board.set_bit(nx as usize, ny as usize, true);
+                }
+            }
+        }
+        board
+    }
+
+    /// Top-left, top-right, bottom-left and bottom-right quarters of the board.
+    /// Odd dimensions give the extra row/column to the bottom/right quarters.
+    pub fn quadrants(&self) -> Vec<Board> {
+        assert!(self.width() >= 2 && self.height() >= 2, "Board is too small to split");
+        let left = self.width() / 2;
+        let top = self.height() / 2;
+        let right = self.width() - left;
+        let bottom = self.height() - top;
+        vec![
+            self.crop(0, 0, left, top),
+            self.crop(left, 0, right, top),
+            self.crop(0, top, left, bottom),
+            self.crop(left, top, right, bottom),
+        ]
+    }
+
+    /// SHA-256 of the dimensions and packed field.
+    pub fn hash(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(8 + self.field.0.len());
+        data.extend(&self.width.to_le_bytes());
+        data.extend(&self.height.to_le_bytes());
+        data.extend(&self.field.0);
+        env::sha256(&data)
+    }
+
+    /// First 8 bytes of `hash`.
+    pub fn short_hash(&self) -> u64 {
+        let hash = self.hash();
+        u64::from_le_bytes([hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7]])
+    }
+
+    /// Coordinates of live cells, row by row.
+    pub fn live_cells(&self) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.is_bit_set(x, y) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    pub fn population(&self) -> u32 {
+        self.field.0.iter().map(|byte| byte.count_ones()).sum()
+    }
+
+    pub fn next_generation(&self) -> Board {
+        let width = self.width();
+        let height = self.height();
+        let mut new_board = Board::with_dimensions(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let bit = self.is_bit_set(x, y);
+                let mut sum = 0;
+                for off_y in 0..=2 {
+                    let ny = y + off_y;
+                    for off_x in 0..=2 {
+                        if off_x == 1 && off_y == 1 {
+                            continue;
+                        }
+                        let nx = x + off_x;
+                        if ny >= 1 && nx >= 1 && ny <= height && nx <= width
+                            && self.is_bit_set(nx - 1, ny - 1) {
+                            sum += 1;
+                        }
+                    }
+                }
+                if bit && sum == 2 || sum == 3 {
+                    new_board.set_bit(x, y, true)
+                }
+            }
+        }
+        new_board
+    }
+
     pub fn to_string(&self) -> Vec<String> {
-        (0..HEIGHT)
+        (0..self.height())
             .map(|i| {
-            (0..WIDTH)
+            (0..self.width())
                 .map(|j| if self.is_bit_set(j, i) { 'X' } else { '.' })
                 .collect()
         })
         .collect()
     }
 
+    /// Cells that differ in `next`, a board of the same size.
+    pub fn diff(&self, next: &Board) -> BoardDelta {
+        let mut delta = BoardDelta::default();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                match (self.is_bit_set(x, y), next.is_bit_set(x, y)) {
+                    (false, true) => delta.born.push((x as u32, y as u32)),
+                    (true, false) => delta.died.push((x as u32, y as u32)),
+                    _ => {}
+                }
+            }
+        }
+        delta
+    }
+
+    pub fn to_matrix(&self) -> Vec<Vec<bool>> {
+        (0..self.height())
+            .map(|y| (0..self.width()).map(|x| self.is_bit_set(x, y)).collect())
+            .collect()
+    }
+
+    /// Logs the board row by row, or only its size and population if it is taller than
+    /// `MAX_LOGGED_ROWS`: a receipt may log at most 100 lines and `step` logs the board twice.
     pub fn debug_logs(&self) {
+        if self.height() > MAX_LOGGED_ROWS {
+            let summary = format!("{}x{} board, {} live cells", self.width, self.height, self.population());
+            env::log(summary.as_bytes());
+            return;
+        }
         self.to_string()
             .into_iter()
             .for_each(|s| env::log(s.as_bytes()))
@@ -70,68 +418,223 @@ impl Board {
 
 // ----------
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BoardWithBlock {
     pub board: Board,
     pub current_block_height: BlockHeight,
     pub prev_block_height: BlockHeight,
+    pub owner_id: AccountId,
+    /// Board this one was derived from (e.g. by splitting).
+    pub parent: Option<BoardIndex>,
+    /// Number of steps since creation or the last clear/reset.
+    pub generation: u64,
+    /// Field the board was created with.
+    pub genesis: Board,
+    /// State before the last step, kept until it is undone or the board is edited.
+    pub prev_board: Option<Board>,
+    /// Generations with a stored snapshot, oldest first.
+    pub checkpoints: Vec<u64>,
+    pub history_policy: HistoryPolicy,
+    /// Paid by the owner for history storage beyond the default policy.
+    pub history_deposit: U128,
+    /// Advance one generation per elapsed block before any interaction.
+    pub auto_step: bool,
+    /// Emit the born/died cells of every step as an event.
+    pub diff_events: bool,
+    /// Sequence number of the latest change to the field, see `get_changes_since`.
+    pub change_seq: u64,
+    pub track_heatmap: bool,
+    /// Live-cell centroids of the most recent generations since the last edit, oldest first.
+    pub recent_centroids: Vec<Centroid>,
+    /// Short hashes of the last `max_period` generations since the last edit, oldest first.
+    pub recent_hashes: Vec<u64>,
+    /// Longest period the oscillator detector looks for.
+    pub max_period: u64,
+    /// Smallest number of generations after which the field repeats: 1 for a still life
+    /// (or a dead board), more for an oscillator. None until a repeat is seen.
+    pub period: Option<u64>,
+    /// Left out of public enumeration by moderation.
+    pub hidden: bool,
+    /// Can't be stepped or edited, set by moderation.
+    pub frozen: bool,
+    /// Unique name the board can be looked up by, see `claim_handle`.
+    pub handle: Option<String>,
+    /// Number of accounts following the board.
+    pub followers: u64,
+    /// Number of comments posted, see `post_comment`.
+    pub comment_count: u64,
+    /// Anyone may paint a pending layer, see `paint_layer`.
+    pub layers_enabled: bool,
+    /// Contributors with a pending layer, in the order they started it.
+    pub layer_owners: Vec<AccountId>,
+    /// Set on boards copied from another deployment, see `import_board`.
+    pub imported_from: Option<Provenance>,
+    /// Contract method called after every step, see `set_step_callback`.
+    pub step_callback: Option<StepCallback>,
+    /// Contract computing the board's generations instead of Conway's rules, see
+    /// `set_rule_oracle`.
+    pub rule_oracle: Option<AccountId>,
+    /// Block the board was permanently locked at, see `finalize_board`.
+    pub finalized_at: Option<BlockHeight>,
+    /// Caps after which the board freezes itself, see `set_board_limits`.
+    pub max_population: Option<u32>,
+    pub max_generation: Option<u64>,
+    /// Frozen by reaching a cap rather than by moderation.
+    pub frozen_by_limit: bool,
+    /// Tutorial scenario the board plays, see `start_scenario`.
+    pub scenario: Option<String>,
+    /// Coordinate of the board in the shared universe, see `claim_tile`.
+    pub tile: Option<universe::TileCoord>,
+    /// Cells cross the borders to linked neighbouring tiles, see `set_edge_exchange`.
+    pub edge_exchange: bool,
+    /// Included in every event about the board, see `set_event_tag`.
+    pub event_tag: Option<String>,
+    /// Steps a cell's trail lasts after it dies, see `set_trail`.
+    pub trail_length: Option<u8>,
+    /// Generation of the board a share payload was taken from, see
+    /// `create_board_from_payload`.
+    pub shared_generation: Option<u64>,
+    /// How the board picks its step engine, see `set_engine_hint`.
+    pub engine_hint: EngineHint,
+    /// Engine and gas of the latest step; None before the first and after oracle steps.
+    pub last_step: Option<StepReport>,
 }
 
 impl BoardWithBlock { 
-    pub fn new(board: Board) -> Self {
+    pub fn new(board: Board, owner_id: AccountId, parent: Option<BoardIndex>) -> Self {
+        let genesis_hash = board.short_hash();
         Self {
+            genesis: board.clone(),
             board,
             current_block_height: env::block_index(),
             prev_block_height: 0,
+            owner_id,
+            parent,
+            generation: 0,
+            prev_board: None,
+            checkpoints: vec![],
+            history_policy: HistoryPolicy::default(),
+            history_deposit: U128(0),
+            auto_step: false,
+            diff_events: false,
+            change_seq: 0,
+            track_heatmap: false,
+            recent_centroids: vec![],
+            recent_hashes: vec![genesis_hash],
+            max_period: analysis::DEFAULT_MAX_PERIOD,
+            period: None,
+            hidden: false,
+            frozen: false,
+            handle: None,
+            followers: 0,
+            comment_count: 0,
+            layers_enabled: false,
+            layer_owners: vec![],
+            imported_from: None,
+            step_callback: None,
+            rule_oracle: None,
+            finalized_at: None,
+            max_population: None,
+            max_generation: None,
+            frozen_by_limit: false,
+            scenario: None,
+            tile: None,
+            edge_exchange: false,
+            event_tag: None,
+            trail_length: None,
+            shared_generation: None,
+            engine_hint: EngineHint::Auto,
+            last_step: None,
         }
     }
 
-    pub fn step(&self) -> BoardWithBlock {
-        let board = &self.board;
-        let mut new_board = Board::new();
-        let block_height = env::block_index();
+    /// Replaces the field after an edit, returning the old one; an edit can't be undone
+    /// as a step.
+    pub fn set_board(&mut self, board: Board) -> Board {
+        self.prev_board = None;
+        self.recent_centroids.clear();
+        self.recent_hashes = vec![board.short_hash()];
+        self.period = None;
+        std::mem::replace(&mut self.board, board)
+    }
 
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                let bit = board.is_bit_set(x, y);
-                let mut sum = 0;
-                for off_y in 0..=2 {
-                    let ny = y + off_y;
-                    for off_x in 0..=2 {
-                        if off_x == 1 && off_y == 1 {
-                            continue;
-                        }
-                        let nx = x + off_x;
-                        if ny >= 1 && nx >= 1 && ny <= HEIGHT && nx <= WIDTH {
-                            if board.is_bit_set(nx - 1, ny - 1) {
-                                sum += 1;
-                            }
-                        }
-                    }
-                }
-                if bit && sum == 2 || sum == 3 {
-                    new_board.set_bit(x, y, true)
-                }
+    /// Rolls back the last step. Returns false if there is nothing to undo.
+    pub fn undo_step(&mut self) -> bool {
+        match self.prev_board.take() {
+            Some(board) => {
+                self.board = board;
+                self.generation -= 1;
+                self.recent_centroids.retain(|centroid| centroid.generation <= self.generation);
+                self.recent_hashes.pop();
+                self.period = None;
+                true
             }
+            None => false,
         }
-        let prev_block_height = if block_height == self.current_block_height {
-            self.prev_block_height
-        } else {
-            self.current_block_height
-        };
-        Self {
-            board: new_board,
-            current_block_height: block_height,
-            prev_block_height,
-        }
+    }
 
+    pub fn step(&mut self) {
+        let new_board = self.board.next_generation();
+        self.advance(new_board);
+    }
+
+    /// Makes `new_board` the next generation, however it was computed.
+    pub fn advance(&mut self, new_board: Board) {
+        let block_height = env::block_index();
+        if block_height != self.current_block_height {
+            self.prev_block_height = self.current_block_height;
+        }
+        self.prev_board = Some(std::mem::replace(&mut self.board, new_board));
+        self.current_block_height = block_height;
+        self.generation += 1;
+        match self.board.centroid(self.generation) {
+            Some(centroid) => {
+                self.recent_centroids.push(centroid);
+                if self.recent_centroids.len() > analysis::DRIFT_WINDOW {
+                    self.recent_centroids.remove(0);
+                }
+            }
+            None => self.recent_centroids.clear(),
+        }
+        self.detect_period();
     }
 }
 
 #[derive(BorshSerialize, BorshStorageKey)]
 pub enum StorageKey {
     Boards, //0x00
+    Checkpoints,
+    Populations,
+    Changes,
+    Heatmaps,
+    Achievements,
+    Tournaments,
+    Seasons,
+    Leaderboards,
+    Trophies,
+    TrophiesPerOwner,
+    CallCounts,
+    Flags,
+    Moderators,
+    Handles,
+    Follows,
+    Comments,
+    Layers,
+    Schedules,
+    Templates,
+    Shards,
+    Museum,
+    ScenarioCompletions,
+    Battles,
+    ReplayFrames,
+    ReplayLengths,
+    ActionPoints,
+    Tiles,
+    BoardHeaders,
+    Trails,
+    Discoveries,
+    RoutedBoards,
 }
 
 
@@ -142,7 +645,63 @@ pub type BoardIndex = u64;
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
+    /// Account that deployed the contract; manages contract-wide settings.
+    pub owner_id: AccountId,
     pub boards: Vector<BoardWithBlock>,
+    pub checkpoints: LookupMap<(BoardIndex, u64), Board>,
+    /// (generation, population) log per board, oldest first.
+    pub populations: LookupMap<BoardIndex, Vec<(u64, u32)>>,
+    pub changes: LookupMap<(BoardIndex, u64), BoardChange>,
+    /// Per-cell live generation counts of boards tracking a heatmap.
+    pub heatmaps: LookupMap<BoardIndex, Vec<u32>>,
+    pub achievements: LookupMap<AccountId, Vec<Achievement>>,
+    pub tournaments: Vector<Tournament>,
+    pub seasons: Vector<Season>,
+    /// Highest scoring accounts per season, best first.
+    pub leaderboards: LookupMap<SeasonId, Vec<LeaderboardEntry>>,
+    /// Soul-bound trophy tokens; the token id is the index.
+    pub trophies: Vector<Trophy>,
+    pub trophies_per_owner: LookupMap<AccountId, Vec<u64>>,
+    pub rate_limit: Option<RateLimit>,
+    /// (window, calls made in it) per account, see `RateLimit`.
+    pub call_counts: LookupMap<AccountId, (u64, u32)>,
+    pub flags: LookupMap<BoardIndex, Vec<Flag>>,
+    /// Accounts allowed to hide and freeze boards besides the contract owner.
+    pub moderators: UnorderedSet<AccountId>,
+    pub handles: LookupMap<String, BoardIndex>,
+    /// Boards each account follows.
+    pub follows: LookupMap<AccountId, Vec<BoardIndex>>,
+    pub comments: LookupMap<(BoardIndex, u64), Comment>,
+    /// Pending layer of each contributor, merged on the board's next step.
+    pub layers: LookupMap<(BoardIndex, AccountId), Board>,
+    /// Pending one-shot steps by (block, id).
+    pub schedules: TreeMap<(BlockHeight, ScheduleId), ScheduledStep>,
+    pub next_schedule_id: ScheduleId,
+    pub templates: UnorderedMap<String, Template>,
+    /// Child contracts this one created boards on, see `deploy_shard`.
+    pub shards: Vector<Shard>,
+    /// Boards created on shards, in creation order.
+    pub routed_boards: Vector<RoutedBoard>,
+    /// Finalized boards in the order they were finalized, with the block they were finalized
+    /// at. An entry whose board was restored to another state no longer matches it.
+    pub museum: Vector<(BoardIndex, BlockHeight)>,
+    pub scenario_completions: LookupMap<AccountId, Vec<String>>,
+    pub battles: Vector<Battle>,
+    pub replay_frames: LookupMap<(MatchId, u32), ReplayFrame>,
+    pub replay_lengths: LookupMap<MatchId, u32>,
+    pub action_point_config: Option<ActionPointConfig>,
+    /// Points of each account and the block they were last spent at.
+    pub action_points: LookupMap<AccountId, (u32, BlockHeight)>,
+    pub fee_config: FeeConfig,
+    pub pending_fee_config: Option<PendingFeeConfig>,
+    pub treasury: Treasury,
+    pub tiles: LookupMap<universe::TileCoord, BoardIndex>,
+    /// Kept in step with `boards` by `internal_save` and `internal_add_board`.
+    pub board_headers: Vector<BoardHeader>,
+    /// Trail counters of boards recording a trail, row by row.
+    pub trails: LookupMap<BoardIndex, Vec<u8>>,
+    /// Account that first claimed each spaceship, by the hash of its canonical shape.
+    pub discoveries: LookupMap<Vec<u8>, AccountId>,
 }
 
 #[near_bindgen]
@@ -150,19 +709,225 @@ impl Contract {
     #[init]
     pub fn new() -> Self {
         Self {
-            boards: Vector::new(StorageKey::Boards)
+            owner_id: env::predecessor_account_id(),
+            boards: Vector::new(StorageKey::Boards),
+            checkpoints: LookupMap::new(StorageKey::Checkpoints),
+            populations: LookupMap::new(StorageKey::Populations),
+            changes: LookupMap::new(StorageKey::Changes),
+            heatmaps: LookupMap::new(StorageKey::Heatmaps),
+            achievements: LookupMap::new(StorageKey::Achievements),
+            tournaments: Vector::new(StorageKey::Tournaments),
+            seasons: Vector::new(StorageKey::Seasons),
+            leaderboards: LookupMap::new(StorageKey::Leaderboards),
+            trophies: Vector::new(StorageKey::Trophies),
+            trophies_per_owner: LookupMap::new(StorageKey::TrophiesPerOwner),
+            rate_limit: None,
+            call_counts: LookupMap::new(StorageKey::CallCounts),
+            flags: LookupMap::new(StorageKey::Flags),
+            moderators: UnorderedSet::new(StorageKey::Moderators),
+            handles: LookupMap::new(StorageKey::Handles),
+            follows: LookupMap::new(StorageKey::Follows),
+            comments: LookupMap::new(StorageKey::Comments),
+            layers: LookupMap::new(StorageKey::Layers),
+            schedules: TreeMap::new(StorageKey::Schedules),
+            next_schedule_id: 0,
+            templates: UnorderedMap::new(StorageKey::Templates),
+            shards: Vector::new(StorageKey::Shards),
+            routed_boards: Vector::new(StorageKey::RoutedBoards),
+            museum: Vector::new(StorageKey::Museum),
+            scenario_completions: LookupMap::new(StorageKey::ScenarioCompletions),
+            battles: Vector::new(StorageKey::Battles),
+            replay_frames: LookupMap::new(StorageKey::ReplayFrames),
+            replay_lengths: LookupMap::new(StorageKey::ReplayLengths),
+            action_point_config: None,
+            action_points: LookupMap::new(StorageKey::ActionPoints),
+            fee_config: FeeConfig::default(),
+            pending_fee_config: None,
+            treasury: Treasury::default(),
+            tiles: LookupMap::new(StorageKey::Tiles),
+            board_headers: Vector::new(StorageKey::BoardHeaders),
+            trails: LookupMap::new(StorageKey::Trails),
+            discoveries: LookupMap::new(StorageKey::Discoveries),
         }
     }
 
+    /// Requires the creation fee, see `get_fee_config`; any surplus is refunded.
+    #[payable]
     pub fn create_board(&mut self, field: Base64VecU8) -> BoardIndex {
+        self.internal_charge_creation_fee(1);
         let board = Board::from(field);
         board.debug_logs();
-        let board_with_blocks = BoardWithBlock::new(board);
-        let index = self.boards.len();
-        self.boards.push(&board_with_blocks);
-        index
+        let board_with_blocks = BoardWithBlock::new(board, env::predecessor_account_id(), None);
+        self.internal_add_board(&board_with_blocks)
     } 
 
+    /// Creates four new boards owned by the caller, one per quadrant of the board at `index`.
+    /// Returns their indices in top-left, top-right, bottom-left, bottom-right order. Requires
+    /// the creation fee for each.
+    #[payable]
+    pub fn split_board(&mut self, index: BoardIndex) -> Vec<BoardIndex> {
+        self.internal_charge_creation_fee(4);
+        let parent = self.internal_get_board(index);
+        let owner_id = env::predecessor_account_id();
+        parent
+            .board
+            .quadrants()
+            .into_iter()
+            .map(|board| {
+                let board_with_blocks = BoardWithBlock::new(board, owner_id.clone(), Some(index));
+                self.internal_add_board(&board_with_blocks)
+            })
+            .collect()
+    }
+
+    /// Grows or crops the board at `index` to `new_w` x `new_h`. Only the board owner can resize.
+    /// The attached deposit must cover the extra storage, including the checkpoints the board's
+    /// history policy keeps of the bigger field and the bigger heatmap and trail; any surplus is
+    /// refunded.
+    #[payable]
+    pub fn resize_board(&mut self, index: BoardIndex, new_w: u32, new_h: u32, anchor: Anchor) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        assert!(board.tile.is_none(), "Tiles can't be resized, see release_tile");
+        let storage_before = env::storage_usage();
+        self.internal_catch_up(index, &mut board);
+        let resized = board.board.resized(new_w as usize, new_h as usize, anchor);
+        let before = board.set_board(resized);
+        self.internal_record_edit(index, &mut board);
+        self.internal_record_change(index, &mut board, &before);
+        self.internal_save(index, &board);
+
+        // Checkpoints, heatmap counts and trail counters of the bigger field are written by later steps, so
+        // they are paid now.
+        let policy = board.history_policy;
+        let mut later = policy
+            .storage_bytes(board.board.field.0.len() as u64)
+            .saturating_sub(policy.storage_bytes(before.field.0.len() as u64));
+        let grown_cells = (board.board.width() * board.board.height()).saturating_sub(before.width() * before.height());
+        if board.track_heatmap {
+            later += grown_cells as u64 * 4;
+        }
+        if board.trail_length.is_some() {
+            later += grown_cells as u64;
+        }
+        charge_storage_bytes(env::storage_usage().saturating_sub(storage_before) + later);
+        board
+    }
+
+    /// Translates all cells of the board at `index`. Only the board owner can shift.
+    pub fn shift_board(&mut self, index: BoardIndex, dx: i32, dy: i32, wrap: bool) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        self.internal_catch_up(index, &mut board);
+        let shifted = board.board.shifted(dx as i64, dy as i64, wrap);
+        let before = board.set_board(shifted);
+        self.internal_record_edit(index, &mut board);
+        self.internal_record_change(index, &mut board, &before);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Draws `text` in live cells of the built-in 5x7 font with its top-left corner at
+    /// (`x`, `y`); cells already alive stay alive. Owner only.
+    pub fn write_text(&mut self, index: BoardIndex, text: String, x: u32, y: u32) -> BoardWithBlock {
+        self.internal_check_rate_limit();
+        assert!(text.chars().count() <= MAX_TEXT_LEN, "Text is too long");
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        self.internal_catch_up(index, &mut board);
+        let mut written = board.board.clone();
+        written.draw_text(&text, x as usize, y as usize);
+        let before = board.set_board(written);
+        self.internal_record_edit(index, &mut board);
+        self.internal_record_change(index, &mut board, &before);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Kills every cell of the board at `index` and resets its generation. Owner only.
+    pub fn clear_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        let before = board.set_board(Board::with_dimensions(board.board.width(), board.board.height()));
+        board.generation = 0;
+        self.internal_record_edit(index, &mut board);
+        self.internal_record_change(index, &mut board, &before);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Restores the field the board at `index` was created with. Owner only.
+    pub fn reset_to_genesis(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        let before = board.set_board(board.genesis.clone());
+        board.generation = 0;
+        self.internal_record_edit(index, &mut board);
+        self.internal_record_change(index, &mut board, &before);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Reverts the last step of the board at `index`. Owner only, once per step.
+    pub fn undo_step(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        let before = board.board.clone();
+        assert!(board.undo_step(), "Nothing to undo");
+        let undone_generation = board.generation + 1;
+        self.internal_truncate_history(index, &mut board, undone_generation);
+        self.internal_record_change(index, &mut board, &before);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Makes the board at `index` advance one generation per elapsed block. Owner only.
+    pub fn set_auto_step(&mut self, index: BoardIndex, enabled: bool) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        board.auto_step = enabled;
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Catches an auto-stepping board up with the blocks elapsed since its last step.
+    /// Anyone can call it.
+    pub fn poke(&mut self, index: BoardIndex) -> BoardWithBlock {
+        self.internal_check_rate_limit();
+        let mut board = self.internal_get_board(index);
+        assert!(board.auto_step, "Auto-step is disabled for this board");
+        self.internal_catch_up(index, &mut board);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Renders the board at `index` `every_n`, 2 * `every_n`, ... generations ahead,
+    /// `count` frames in total, without changing it.
+    pub fn preview_timeline(&self, index: BoardIndex, every_n: u64, count: u64) -> Vec<Vec<String>> {
+        assert!(every_n > 0, "every_n must be positive");
+        assert!(
+            every_n.saturating_mul(count) <= MAX_PREVIEW_GENERATIONS,
+            "Preview is limited to {} generations",
+            MAX_PREVIEW_GENERATIONS
+        );
+        let mut state = self.internal_get_board(index).board;
+        (0..count)
+            .map(|_| {
+                for _ in 0..every_n {
+                    state = state.next_generation();
+                }
+                state.to_string()
+            })
+            .collect()
+    }
+
     pub fn get_board(&self, index: BoardIndex) -> Option<BoardWithBlock> {
         let board = self.boards.get(index);
         if let Some(board) = board.as_ref() {
@@ -171,14 +936,138 @@ impl Contract {
         board
     }
 
+    /// Stepping a board owned by someone else requires the step fee; any surplus is refunded.
+    #[payable]
     pub fn step(&mut self, index: BoardIndex) -> BoardWithBlock {
+        self.internal_step_for(&env::predecessor_account_id(), index)
+    }
+}
+
+impl Contract {
+    /// `step` on behalf of `account_id`, who is held to the rate limit, action points and
+    /// step fee whether they called directly or through a router.
+    pub(crate) fn internal_step_for(&mut self, account_id: &AccountId, index: BoardIndex) -> BoardWithBlock {
+        self.internal_check_rate_limit_for(account_id);
         env::log(b"Old board");
-        let board = self.get_board(index).expect("No board");
-        let new_board = board.step();
-        self.boards.replace(index, &new_board);
+        let mut board = self.get_board(index).expect("No board");
+        if let Some(reason) = step_blocker(&board) {
+            env::panic(reason.as_bytes());
+        }
+        self.internal_spend_action_points_for(account_id, &board, 1, 0);
+        self.internal_charge_step_fee_for(account_id, &board);
+        self.internal_catch_up(index, &mut board);
+        self.internal_step(index, &mut board);
+        self.internal_save(index, &board);
         env::log(b"New board");
-        new_board.board.debug_logs();
-        new_board
+        board.board.debug_logs();
+        board
+    }
+}
+
+pub(crate) fn assert_owner(board: &BoardWithBlock) {
+    assert_eq!(env::predecessor_account_id(), board.owner_id, "Only the board owner can do this");
+}
+
+pub(crate) fn assert_not_frozen(board: &BoardWithBlock) {
+    assert!(!board.frozen, "The board is frozen");
+}
+
+/// Why `step` and the batch methods can't step `board`, if they can't. Batches report such
+/// boards as failed rather than panicking, so one board can't revert the rest.
+pub(crate) fn step_blocker(board: &BoardWithBlock) -> Option<&'static str> {
+    if board.frozen {
+        Some("The board is frozen")
+    } else if board.rule_oracle.is_some() {
+        Some("The board steps through its rule oracle")
+    } else {
+        None
+    }
+}
+
+pub(crate) fn assert_not_finalized(board: &BoardWithBlock) {
+    assert!(board.finalized_at.is_none(), "The board is finalized");
+}
+
+impl Contract {
+    pub(crate) fn internal_get_board(&self, index: BoardIndex) -> BoardWithBlock {
+        self.boards.get(index).expect("No board")
+    }
+
+    /// Does nothing for a board that froze on the way, e.g. at its limits during catch-up.
+    pub(crate) fn internal_step(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        if board.frozen {
+            return;
+        }
+        self.internal_merge_layers(index, board);
+        let engine = board.engine_hint.select(&board.board);
+        let start = env::used_gas();
+        let next = self.internal_next_generation(index, board, engine);
+        board.last_step = Some(StepReport {
+            engine,
+            gas: env::used_gas() - start,
+        });
+        self.internal_advance(index, board, next);
+    }
+
+    /// Records `next` as the board's next generation, see `internal_step`.
+    pub(crate) fn internal_advance(&mut self, index: BoardIndex, board: &mut BoardWithBlock, next: Board) {
+        board.advance(next);
+        self.internal_record_step(index, board);
+        let before = board.prev_board.clone().expect("Stepped board keeps its previous state");
+        let change = self.internal_log_change(index, board, &before);
+        if board.diff_events {
+            events::emit_step_diff(index, board, &change);
+        }
+        if board.track_heatmap {
+            self.internal_update_heatmap(index, &board.board);
+        }
+        if board.trail_length.is_some() {
+            self.internal_update_trail(index, board);
+        }
+        if board.followers > 0 {
+            follows::emit_board_stepped(index, board);
+        }
+        if let Some(callback) = &board.step_callback {
+            callbacks::notify_step(index, board, callback);
+        }
+        self.internal_check_scenario(index, board);
+        self.internal_check_limits(index, board);
+    }
+
+    /// Steps an auto-stepping board once per block elapsed since its last step.
+    pub(crate) fn internal_catch_up(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        if !board.auto_step || board.frozen || board.rule_oracle.is_some() {
+            return;
+        }
+        let elapsed = env::block_index().saturating_sub(board.current_block_height);
+        for _ in 0..elapsed.min(MAX_CATCH_UP_STEPS) {
+            if board.frozen {
+                break;
+            }
+            self.internal_step(index, board);
+        }
+    }
+
+    pub(crate) fn internal_save(&mut self, index: BoardIndex, board: &BoardWithBlock) {
+        self.internal_debug_check_invariants(index, board);
+        self.boards.replace(index, board);
+        self.board_headers.replace(index, &BoardHeader::new(index, board));
+        // Steps of `sync_tile` send their own syncs on behalf of the account that started them.
+        let account_id = env::predecessor_account_id();
+        if board.edge_exchange && account_id != env::current_account_id() {
+            self.internal_sync_neighbours(index, board, &account_id);
+        }
+    }
+
+    pub(crate) fn internal_add_board(&mut self, board: &BoardWithBlock) -> BoardIndex {
+        let index = self.boards.len();
+        let mut board = board.clone();
+        self.internal_record_edit(index, &mut board);
+        self.internal_debug_check_invariants(index, &board);
+        self.boards.push(&board);
+        self.board_headers.push(&BoardHeader::new(index, &board));
+        events::emit_board_created(index, &board);
+        index
     }
 }
 
@@ -187,8 +1076,11 @@ impl Contract {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::VMContextBuilder;
-    use near_sdk::MockedBlockchain;
+    use crate::changes::MAX_RETAINED_CHANGES;
+    use near_sdk::test_utils::{
+        accounts, get_created_receipts, get_logs, testing_env_with_promise_results, VMContextBuilder,
+    };
+    use near_sdk::{MockedBlockchain, PromiseResult};
     use near_sdk::{testing_env, VMContext};
 
     fn get_context(is_view: bool) -> VMContext{
@@ -269,5 +1161,2934 @@ mod tests {
         }
 
     }
-}
 
+    #[test]
+    fn test_split_board() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = Contract::new();
+
+        let mut board = Board::new();
+        board.set_bit(1, 1, true);
+        board.set_bit(9, 2, true);
+        board.set_bit(15, 15, true);
+        contract.create_board(board.field);
+
+        let quadrants = contract.split_board(0);
+        assert_eq!(quadrants, vec![1, 2, 3, 4]);
+
+        let top_left = contract.get_board(1).unwrap();
+        assert_eq!((top_left.board.width, top_left.board.height), (8, 8));
+        assert_eq!(top_left.parent, Some(0));
+        assert!(top_left.board.is_bit_set(1, 1));
+
+        let top_right = contract.get_board(2).unwrap();
+        assert!(top_right.board.is_bit_set(1, 2));
+
+        let bottom_right = contract.get_board(4).unwrap();
+        assert!(bottom_right.board.is_bit_set(7, 7));
+        assert_eq!(contract.get_board(3).unwrap().board.field.0, vec![0u8; 8]);
+    }
+
+    #[test]
+    fn test_resize_board() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = Contract::new();
+
+        let mut board = Board::new();
+        board.set_bit(0, 0, true);
+        board.set_bit(15, 15, true);
+        contract.create_board(board.field);
+
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(24)).build());
+        let grown = contract.resize_board(0, 20, 18, Anchor::Center);
+        assert_eq!((grown.board.width, grown.board.height), (20, 18));
+        assert!(grown.board.is_bit_set(2, 1));
+        assert!(grown.board.is_bit_set(17, 16));
+
+        let cropped = contract.resize_board(0, 4, 4, Anchor::BottomRight);
+        assert_eq!(cropped.board.field.0.len(), 2);
+        assert!(cropped.board.is_bit_set(1, 2));
+        assert!(!cropped.board.is_bit_set(0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the board owner can do this")]
+    fn test_resize_board_not_owner() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        contract.resize_board(0, 8, 8, Anchor::TopLeft);
+    }
+
+    #[test]
+    fn test_shift_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+
+        let mut board = Board::new();
+        board.set_bit(0, 0, true);
+        board.set_bit(14, 3, true);
+        contract.create_board(board.field);
+
+        let shifted = contract.shift_board(0, 2, -1, true);
+        assert!(shifted.board.is_bit_set(2, 15));
+        assert!(shifted.board.is_bit_set(0, 2));
+
+        let shifted = contract.shift_board(0, 0, 1, false);
+        assert!(shifted.board.is_bit_set(0, 3));
+        assert!(!shifted.board.is_bit_set(2, 0));
+        assert!(!shifted.board.is_bit_set(2, 15));
+    }
+
+    #[test]
+    fn test_clear_and_reset_to_genesis() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field.clone());
+
+        contract.step(0);
+        let stepped = contract.step(0);
+        assert_eq!(stepped.generation, 2);
+
+        let cleared = contract.clear_board(0);
+        assert_eq!(cleared.generation, 0);
+        assert_eq!(cleared.board.field.0, vec![0u8; FIELD_LEN]);
+
+        let reset = contract.reset_to_genesis(0);
+        assert_eq!(reset.generation, 0);
+        assert_eq!(reset.board.field.0, board.field.0);
+    }
+
+    #[test]
+    fn test_undo_step() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field.clone());
+
+        contract.step(0);
+        let undone = contract.undo_step(0);
+        assert_eq!(undone.generation, 0);
+        assert_eq!(undone.board.field.0, board.field.0);
+        assert!(undone.prev_board.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing to undo")]
+    fn test_undo_step_twice() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+
+        contract.step(0);
+        contract.undo_step(0);
+        contract.undo_step(0);
+    }
+
+    #[test]
+    fn test_get_generation() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        board.set_bit(6, 3, true);
+        board.set_bit(5, 2, true);
+        contract.create_board(board.field.clone());
+
+        let mut states = vec![board.field.0.clone()];
+        for _ in 0..20 {
+            testing_env!(get_context(false));
+            states.push(contract.step(0).board.field.0);
+        }
+        assert_eq!(contract.get_board(0).unwrap().checkpoints, vec![0, 16]);
+        for (generation, field) in states.iter().enumerate() {
+            assert_eq!(&contract.get_generation(0, generation as u64).unwrap().field.0, field);
+        }
+        assert!(contract.get_generation(0, 21).is_none());
+
+        // An edit starts a new base for replaying later generations.
+        contract.shift_board(0, 1, 0, true);
+        assert_eq!(contract.get_board(0).unwrap().checkpoints, vec![0, 16, 20]);
+        assert_eq!(contract.get_generation(0, 17).unwrap().field.0, states[17]);
+    }
+
+    #[test]
+    fn test_set_history_policy() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+
+        let required = HistoryPolicy::Full.required_deposit(FIELD_LEN as u64);
+        assert!(required > 0);
+        testing_env!(VMContextBuilder::new().attached_deposit(required).build());
+        let board = contract.set_history_policy(0, HistoryPolicy::Full);
+        assert_eq!(board.history_deposit.0, required);
+
+        for generation in 1..=3 {
+            testing_env!(get_context(false));
+            contract.step(0);
+            assert_eq!(contract.get_board(0).unwrap().checkpoints.last(), Some(&generation));
+        }
+        assert_eq!(contract.get_population_history(0).len(), 4);
+
+        testing_env!(get_context(false));
+        let board = contract.set_history_policy(0, HistoryPolicy::PopulationsOnly);
+        assert_eq!(board.history_deposit.0, 0);
+        assert!(board.checkpoints.is_empty());
+        assert!(contract.get_generation(0, 1).is_none());
+
+        contract.set_history_policy(0, HistoryPolicy::None);
+        assert!(contract.get_population_history(0).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_set_history_policy_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_history_policy(0, HistoryPolicy::Full);
+    }
+
+    #[test]
+    fn test_auto_step_poke() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_auto_step(0, true);
+
+        testing_env!(VMContextBuilder::new().block_index(5).build());
+        let board = contract.poke(0);
+        assert_eq!(board.generation, 5);
+        assert_eq!(board.current_block_height, 5);
+
+        // Already caught up within the same block.
+        assert_eq!(contract.poke(0).generation, 5);
+
+        testing_env!(VMContextBuilder::new().block_index(100).build());
+        assert_eq!(contract.poke(0).generation, 5 + MAX_CATCH_UP_STEPS);
+    }
+
+    #[test]
+    fn test_preview_timeline() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+
+        testing_env!(get_context(true));
+        let frames = contract.preview_timeline(0, 1, 2);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0][3], ".....X..........");
+        assert_eq!(frames[1][4], "....XXX.........");
+        assert_eq!(contract.get_board(0).unwrap().generation, 0);
+    }
+
+    #[test]
+    fn test_render_board_ansi() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        contract.step(0);
+
+        let rows = contract.render_board(0, RenderMode::Ansi);
+        assert_eq!(rows[3], "\x1b[90m.....\x1b[92mX\x1b[90m..........\x1b[0m");
+        assert_eq!(rows[4], "\x1b[90m....\x1b[31mx\x1b[97mX\x1b[31mx\x1b[90m.........\x1b[0m");
+        assert_eq!(contract.render_board(0, RenderMode::Ascii)[4], ".....X..........");
+    }
+
+    #[test]
+    fn test_export_image() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(0, 0, true);
+        board.set_bit(15, 15, true);
+        contract.create_board(board.field);
+
+        let pbm = contract.export_image(0, ImageFormat::Pbm, 1).0;
+        assert_eq!(&pbm[..9], b"P4\n16 16\n");
+        assert_eq!(pbm.len(), 9 + 32);
+        assert_eq!(pbm[9], 0x80);
+        assert_eq!(pbm[40], 0x01);
+
+        let bmp = contract.export_image(0, ImageFormat::Bmp, 2).0;
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(bmp.len(), 62 + 4 * 32);
+        assert_eq!(u32::from_le_bytes([bmp[2], bmp[3], bmp[4], bmp[5]]) as usize, bmp.len());
+        // Bottom row comes first: the (15, 15) cell covers its last two pixels.
+        assert_eq!(&bmp[62..66], &[0x00, 0x00, 0x00, 0x03]);
+        assert_eq!(&bmp[bmp.len() - 4..], &[0xc0, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_get_board_matrix() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(3, 1, true);
+        contract.create_board(board.field);
+
+        let matrix = contract.get_board_matrix(0);
+        assert_eq!(matrix.len(), HEIGHT);
+        assert!(matrix.iter().all(|row| row.len() == WIDTH));
+        assert!(matrix[1][3]);
+        assert_eq!(matrix.iter().flatten().filter(|&&alive| alive).count(), 1);
+    }
+
+    #[test]
+    fn test_board_storage_encoding() {
+        let empty = Board::with_dimensions(64, 64);
+        let encoded = empty.try_to_vec().unwrap();
+        assert!(encoded.len() < 16);
+        assert_eq!(Board::try_from_slice(&encoded).unwrap().field.0, empty.field.0);
+
+        let mut noisy = Board::with_dimensions(13, 7);
+        for i in 0..13 * 7 {
+            noisy.set_bit(i % 13, i / 13, i % 3 != 0);
+        }
+        let encoded = noisy.try_to_vec().unwrap();
+        assert_eq!(encoded.len(), 4 + 4 + 1 + 4 + noisy.field.0.len());
+        let decoded = Board::try_from_slice(&encoded).unwrap();
+        assert_eq!((decoded.width, decoded.height), (13, 7));
+        assert_eq!(decoded.field.0, noisy.field.0);
+
+        let mut sparse = Board::new();
+        sparse.set_bit(15, 15, true);
+        sparse.set_bit(0, 3, true);
+        let decoded = Board::try_from_slice(&sparse.try_to_vec().unwrap()).unwrap();
+        assert_eq!(decoded.field.0, sparse.field.0);
+    }
+
+    #[test]
+    fn test_step_diff_events() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        contract.set_diff_events(0, true);
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        let event = get_logs().into_iter().find(|log| log.starts_with("EVENT_JSON:")).unwrap();
+        let event: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&event["EVENT_JSON:".len()..]).unwrap();
+        assert_eq!(event["standard"], "game_of_life");
+        assert_eq!(event["event"], "board_step_diff");
+        let data = &event["data"][0];
+        assert_eq!(data["generation"], 1);
+        assert_eq!(data["born"], near_sdk::serde_json::json!([[5, 3], [5, 5]]));
+        assert_eq!(data["died"], near_sdk::serde_json::json!([[4, 4], [6, 4]]));
+    }
+
+    #[test]
+    fn test_get_changes_since() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+
+        contract.step(0);
+        testing_env!(get_context(false));
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(24)).build());
+        contract.resize_board(0, 20, 20, Anchor::TopLeft);
+
+        let page = contract.get_changes_since(0, 0);
+        assert_eq!(page.latest_seq, 2);
+        assert!(!page.resync_required);
+        assert_eq!(page.changes[0].delta.born, vec![(5, 3), (5, 5)]);
+        assert!(page.changes[1].resized);
+        assert!(contract.get_changes_since(0, 2).changes.is_empty());
+        let page = contract.get_changes_since(0, u64::MAX);
+        assert!(page.changes.is_empty() && !page.resync_required);
+
+        for _ in 0..MAX_RETAINED_CHANGES {
+            testing_env!(get_context(false));
+            contract.step(0);
+        }
+        assert!(contract.get_changes_since(0, 1).resync_required);
+        assert_eq!(contract.get_changes_since(0, 2).changes.len(), MAX_RETAINED_CHANGES as usize);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        assert!(contract.get_heatmap(0).is_none());
+
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(23)).build());
+        contract.set_heatmap_tracking(0, true);
+        contract.step(0);
+        testing_env!(get_context(false));
+        contract.step(0);
+
+        let heatmap = contract.get_heatmap(0).unwrap();
+        assert_eq!(heatmap.counts.len(), WIDTH * HEIGHT);
+        assert_eq!(heatmap.counts[4 * WIDTH + 5], 3);
+        assert_eq!(heatmap.counts[4 * WIDTH + 4], 2);
+        assert_eq!(heatmap.counts[3 * WIDTH + 5], 1);
+        assert_eq!(heatmap.counts.iter().sum::<u32>(), 9);
+    }
+
+    #[test]
+    fn test_get_complexity() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        assert_eq!(
+            contract.get_complexity(0),
+            Complexity { density_bps: 0, block_entropy_millibits: 0, change_rate_bps: 0, sampled_changes: 0 }
+        );
+
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        contract.step(1);
+        let complexity = contract.get_complexity(1);
+        assert_eq!(complexity.density_bps, 3 * 10_000 / 256);
+        assert!(complexity.block_entropy_millibits > 0);
+        // A blinker flips 4 of 256 cells per generation.
+        assert_eq!(complexity.change_rate_bps, 4 * 10_000 / 256);
+        assert_eq!(complexity.sampled_changes, 1);
+    }
+
+    #[test]
+    fn test_detect_symmetries() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        // Horizontal bar through the middle rows: symmetric under 180 and both flips.
+        let mut board = Board::new();
+        for x in 2..14 {
+            board.set_bit(x, 7, true);
+            board.set_bit(x, 8, true);
+        }
+        contract.create_board(board.field.clone());
+        board.set_bit(0, 0, true);
+        contract.create_board(board.field);
+
+        let exact = |index| -> Vec<Symmetry> {
+            contract.detect_symmetries(index).iter().filter(|r| r.exact).map(|r| r.symmetry).collect()
+        };
+        assert_eq!(
+            exact(0),
+            vec![Symmetry::Identity, Symmetry::Rotate180, Symmetry::FlipHorizontal, Symmetry::FlipVertical]
+        );
+        assert_eq!(exact(1), vec![Symmetry::Identity]);
+        let reports = contract.detect_symmetries(1);
+        let rotate_180 = reports.iter().find(|r| r.symmetry == Symmetry::Rotate180).unwrap();
+        assert_eq!(rotate_180.score_bps, 24 * 10_000 / 25);
+
+        let glider = Board::from_field(3, 2, vec![0b0010_1110].into());
+        let rotated = glider.transformed(Symmetry::Rotate90);
+        assert_eq!((rotated.width, rotated.height), (2, 3));
+        assert_eq!(rotated.transformed(Symmetry::Rotate270).field.0, glider.field.0);
+    }
+
+    #[test]
+    fn test_get_bounding_box() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        assert!(contract.get_bounding_box(0).is_none());
+
+        let mut board = Board::new();
+        board.set_bit(3, 9, true);
+        board.set_bit(12, 2, true);
+        board.set_bit(5, 5, true);
+        contract.create_board(board.field);
+        assert_eq!(
+            contract.get_bounding_box(1),
+            Some(BoundingBox { min_x: 3, min_y: 2, max_x: 12, max_y: 9, population: 3 })
+        );
+    }
+
+    #[test]
+    fn test_get_drift() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        // Glider heading south-east.
+        let mut board = Board::new();
+        board.set_bit(1, 0, true);
+        board.set_bit(2, 1, true);
+        board.set_bit(0, 2, true);
+        board.set_bit(1, 2, true);
+        board.set_bit(2, 2, true);
+        contract.create_board(board.field);
+        assert!(contract.get_drift(0).is_none());
+
+        for _ in 0..9 {
+            testing_env!(get_context(false));
+            contract.step(0);
+        }
+        let drift = contract.get_drift(0).unwrap();
+        assert_eq!(drift.generations, 7);
+        assert!(drift.moving);
+        assert_eq!(drift.direction, Some(Direction::SouthEast));
+
+        // A blinker stays in place.
+        testing_env!(get_context(false));
+        let mut blinker = Board::new();
+        blinker.set_bit(4, 4, true);
+        blinker.set_bit(5, 4, true);
+        blinker.set_bit(6, 4, true);
+        contract.create_board(blinker.field);
+        for _ in 0..3 {
+            testing_env!(get_context(false));
+            contract.step(1);
+        }
+        let drift = contract.get_drift(1).unwrap();
+        assert!(!drift.moving);
+        assert_eq!(drift.direction, None);
+    }
+
+    fn board_from_rows(rows: &[&str]) -> Board {
+        let mut board = Board::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                board.set_bit(x + 4, y + 4, cell == 'X');
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn test_get_spaceship() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let spaceships = patterns::KNOWN_PATTERNS.iter().filter(|p| p.kind == PatternKind::Spaceship);
+        for pattern in spaceships {
+            let index = contract.create_board(board_from_rows(pattern.rows).field);
+            let report = contract.get_spaceship(index).unwrap();
+            assert_eq!(report.known_as.as_deref(), Some(pattern.name));
+            testing_env!(get_context(false));
+        }
+        assert_eq!(contract.get_spaceship(0).unwrap().speed, "c/4 diagonal");
+        assert_eq!(contract.get_spaceship(1).unwrap().speed, "c/2 orthogonal");
+
+        let blinker = contract.create_board(board_from_rows(&["XXX"]).field);
+        assert!(contract.get_spaceship(blinker).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The spaceship is already known")]
+    fn test_claim_known_spaceship() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(board_from_rows(patterns::KNOWN_PATTERNS[0].rows).field);
+        contract.claim_spaceship_discovery(0);
+    }
+
+    #[test]
+    fn test_format_speed() {
+        assert_eq!(analysis::format_speed(4, 1, 1), "c/4 diagonal");
+        assert_eq!(analysis::format_speed(4, -2, 0), "c/2 orthogonal");
+        assert_eq!(analysis::format_speed(5, 0, 2), "2c/5 orthogonal");
+        assert_eq!(analysis::format_speed(6, 2, 1), "c/3 oblique");
+    }
+
+    #[test]
+    fn test_period_detection() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(board_from_rows(&["XXX"]).field);
+        contract.create_board(board_from_rows(&["XX", "XX"]).field);
+
+        testing_env!(get_context(false));
+        assert_eq!(contract.step(0).period, None);
+        testing_env!(get_context(false));
+        assert_eq!(contract.step(0).period, Some(2));
+
+        testing_env!(get_context(false));
+        assert_eq!(contract.step(1).period, Some(1));
+
+        testing_env!(get_context(false));
+        contract.set_max_period(0, 1);
+        let board = contract.step(0);
+        assert_eq!(board.recent_hashes.len(), 1);
+        assert_eq!(board.period, None);
+    }
+
+    #[test]
+    fn test_get_census() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let board = board_from_rows(&[
+            "XX.....X..",
+            "XX.....X..",
+            ".......X..",
+            "..........",
+            ".X.....XX.",
+            "..X....X.X",
+            "XXX.....X.",
+            "..........",
+            ".....X....",
+        ]);
+        contract.create_board(board.field);
+
+        let census = contract.get_census(0);
+        let objects: Vec<(&str, PatternKind, u32)> =
+            census.objects.iter().map(|o| (o.name.as_str(), o.kind, o.count)).collect();
+        assert_eq!(
+            objects,
+            vec![
+                ("glider", PatternKind::Spaceship, 1),
+                ("block", PatternKind::StillLife, 1),
+                ("boat", PatternKind::StillLife, 1),
+                ("blinker", PatternKind::Oscillator, 1),
+            ]
+        );
+        assert_eq!(census.unknown, 1);
+    }
+
+    #[test]
+    fn test_tournament() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(10), 4, 8, TournamentMetric::FinalPopulation);
+        let entrants = [
+            board_from_rows(&["X"]),
+            board_from_rows(&["XX", "XX"]),
+            board_from_rows(&["XXX"]),
+        ];
+        for (i, board) in entrants.iter().enumerate() {
+            testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(i)).build());
+            let index = contract.create_board(board.field.clone());
+            testing_env!(VMContextBuilder::new()
+                .predecessor_account_id(accounts(i))
+                .attached_deposit(10u128.pow(22))
+                .build());
+            assert_eq!(contract.enter_tournament(id, index), i as u32);
+        }
+        assert_eq!(contract.get_tournament(id).unwrap().prize_pool.0, 30);
+
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        assert_eq!(contract.advance_tournament(id).processed, vec![0, 1]);
+        let round = contract.get_tournament(id).unwrap().matchups;
+        assert_eq!((round[0].score_a, round[0].score_b, round[0].winner), (0, 4, 1));
+        assert_eq!((round[1].b, round[1].winner), (None, 2));
+
+        contract.advance_tournament(id);
+        let tournament = contract.get_tournament(id).unwrap();
+        let round = &tournament.matchups;
+        assert_eq!((round[0].score_a, round[0].score_b, round[0].winner), (4, 3, 1));
+        assert_eq!(tournament.champion, Some(1));
+        assert_eq!(tournament.prize_pool.0, 0);
+
+        let replay = contract.get_match_replay(MatchId::Tournament(id), 0, 10);
+        assert_eq!(replay.len(), 3);
+        match &replay[2] {
+            ReplayFrame::Matchup { round, field_a, field_b, .. } => {
+                assert_eq!(*round, 2);
+                assert_eq!(field_a.population(), 4);
+                assert_eq!(field_b.as_ref().unwrap().population(), 3);
+            }
+            frame => panic!("Unexpected frame {:?}", frame),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Entries are closed")]
+    fn test_tournament_entries_close() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(0), 4, 8, TournamentMetric::SurvivalLength);
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        contract.enter_tournament(id, 0);
+        contract.enter_tournament(id, 1);
+        contract.advance_tournament(id);
+        contract.create_board(Board::new().field);
+        contract.enter_tournament(id, 2);
+    }
+
+    #[test]
+    fn test_tournament_seeding() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(0), 4, 8, TournamentMetric::FinalPopulation);
+        for _ in 0..4 {
+            let index = contract.create_board(board_from_rows(&["XX", "XX"]).field);
+            contract.enter_tournament(id, index);
+        }
+        contract.commit_seeding(id, 10);
+
+        testing_env!(VMContextBuilder::new()
+            .block_index(11)
+            .random_seed(vec![7; 32])
+            .attached_deposit(10u128.pow(22))
+            .build());
+        let mut bracket = contract.resolve_seeding(id);
+        assert_eq!(contract.get_tournament(id).unwrap().remaining, bracket);
+        bracket.sort_unstable();
+        assert_eq!(bracket, vec![0, 1, 2, 3]);
+
+        // Every matchup is a tie of two blocks, broken by the seed.
+        contract.advance_tournament(id);
+        contract.advance_tournament(id);
+        let tournament = contract.get_tournament(id).unwrap();
+        assert!(tournament.champion.is_some());
+        assert_eq!(tournament.seed.unwrap().0, vec![7; 32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The seed block has not passed yet")]
+    fn test_tournament_seeding_too_early() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(0), 4, 8, TournamentMetric::FinalPopulation);
+        contract.commit_seeding(id, 10);
+        testing_env!(VMContextBuilder::new().block_index(10).build());
+        contract.resolve_seeding(id);
+    }
+
+    #[test]
+    fn test_cancel_tournament() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(10), 4, 8, TournamentMetric::FinalPopulation);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        let index = contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10u128.pow(22))
+            .build());
+        contract.enter_tournament(id, index);
+
+        testing_env!(get_context(false));
+        contract.cancel_tournament(id);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 1);
+        assert!(receipts.contains("charlie"));
+        let tournament = contract.get_tournament(id).unwrap();
+        assert!(tournament.cancelled);
+        assert_eq!(tournament.prize_pool.0, 0);
+    }
+
+    /// A tournament of two 16x16 entries, each entered by its own board owner.
+    fn tournament_of_two(contract: &mut Contract, entry_fee: u128, generations: u64) -> TournamentId {
+        let id = contract.create_tournament(U128(entry_fee), 2, generations, TournamentMetric::FinalPopulation);
+        for (account, rows) in [(2, ["XXX"]), (3, ["XX."])] {
+            testing_env!(VMContextBuilder::new()
+                .predecessor_account_id(accounts(account))
+                .attached_deposit(10u128.pow(22))
+                .build());
+            let index = contract.create_board(board_from_rows(&rows).field);
+            contract.enter_tournament(id, index);
+        }
+        id
+    }
+
+    #[test]
+    fn test_matchup_spans_calls() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = tournament_of_two(&mut contract, 0, 3);
+        // With no gas to spare every call plays a single generation.
+        for generation in 1..3 {
+            testing_env!(VMContextBuilder::new()
+                .prepaid_gas(batch::BATCH_GAS_RESERVE)
+                .attached_deposit(10u128.pow(22))
+                .build());
+            let result = contract.advance_tournament(id);
+            assert_eq!((result.processed.len(), result.next_cursor), (0, Some(0)));
+            let tournament = contract.get_tournament(id).unwrap();
+            assert_eq!(tournament.matchup_progress.unwrap().generation, generation);
+        }
+        testing_env!(VMContextBuilder::new()
+            .prepaid_gas(batch::BATCH_GAS_RESERVE)
+            .attached_deposit(10u128.pow(22))
+            .build());
+        assert_eq!(contract.advance_tournament(id).processed, vec![0]);
+        let tournament = contract.get_tournament(id).unwrap();
+        // The blinker outlives the domino.
+        assert_eq!((tournament.champion, tournament.matchups[0].score_a), (Some(0), 3));
+        assert!(tournament.matchup_progress.is_none());
+    }
+
+    #[test]
+    fn test_entrant_cancels_stalled_tournament() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = tournament_of_two(&mut contract, 10, 3);
+        testing_env!(VMContextBuilder::new()
+            .prepaid_gas(batch::BATCH_GAS_RESERVE)
+            .block_index(5)
+            .attached_deposit(10u128.pow(22))
+            .build());
+        contract.advance_tournament(id);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .block_index(6 + tournament::TOURNAMENT_STALL_TIMEOUT)
+            .build());
+        contract.cancel_tournament(id);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 2);
+        assert!(contract.get_tournament(id).unwrap().cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "The tournament is still active")]
+    fn test_entrant_cancels_active_tournament() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = tournament_of_two(&mut contract, 10, 3);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .block_index(tournament::TOURNAMENT_STALL_TIMEOUT)
+            .build());
+        contract.cancel_tournament(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Entries are closed")]
+    fn test_cancelled_tournament_entries_close() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(0), 4, 8, TournamentMetric::FinalPopulation);
+        contract.create_board(Board::new().field);
+        contract.cancel_tournament(id);
+        contract.enter_tournament(id, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_enter_tournament_without_storage_deposit() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(10), 4, 8, TournamentMetric::FinalPopulation);
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10).build());
+        contract.enter_tournament(id, 0);
+    }
+
+    #[test]
+    fn test_seasons() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.add_season("one".to_string(), 0, 10);
+        contract.add_season("two".to_string(), 10, 20);
+        assert_eq!(contract.get_current_season().unwrap().id, 0);
+
+        let account_id: AccountId = accounts(1).into();
+        contract.internal_award(
+            &account_id,
+            Achievement {
+                kind: AchievementKind::TournamentChampion { tournament: 0 },
+                board: 0,
+                block_height: 0,
+                season: None,
+            },
+        );
+        let standings = vec![LeaderboardEntry { account_id: account_id.clone(), points: 25 }];
+        assert_eq!(contract.get_leaderboard(0), standings);
+        assert_eq!(contract.get_season_achievements(account_id, 0).len(), 1);
+
+        testing_env!(VMContextBuilder::new().block_index(15).build());
+        assert_eq!(contract.get_current_season().unwrap().id, 1);
+        assert!(contract.get_leaderboard(1).is_empty());
+        assert_eq!(contract.get_leaderboard(0), standings);
+
+        testing_env!(VMContextBuilder::new().block_index(25).build());
+        assert!(contract.get_current_season().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Seasons may not overlap")]
+    fn test_overlapping_seasons() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.add_season("one".to_string(), 0, 10);
+        contract.add_season("two".to_string(), 5, 20);
+    }
+
+    #[test]
+    fn test_tournament_trophy() {
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1_700_000_000_000_000_000)
+            .attached_deposit(10u128.pow(22))
+            .build());
+        let mut contract = Contract::new();
+        let id = contract.create_tournament(U128(0), 2, 4, TournamentMetric::FinalPopulation);
+        contract.create_board(board_from_rows(&["XX", "XX"]).field);
+        contract.create_board(Board::new().field);
+        contract.enter_tournament(id, 0);
+        contract.enter_tournament(id, 1);
+        contract.advance_tournament(id);
+
+        let owner_id = contract.get_board(0).unwrap().owner_id;
+        assert_eq!(contract.nft_supply_for_owner(owner_id.clone()).0, 1);
+        let token = contract.nft_token("0".to_string()).unwrap();
+        assert_eq!(token.owner_id, owner_id);
+        let metadata = token.metadata.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Champion of tournament #0"));
+        assert_eq!(metadata.issued_at.as_deref(), Some("2023-11-14"));
+        assert_eq!(contract.nft_tokens(None, None).len(), 1);
+        assert_eq!(contract.nft_tokens_for_owner(owner_id, Some(U128(1)), None).len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Trophies are soul-bound")]
+    fn test_trophy_transfer() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.nft_transfer(accounts(1).into(), "0".to_string(), None, None);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_rate_limit(Some(RateLimit { max_calls: 2, window_blocks: 1 }));
+        contract.step(0);
+        testing_env!(get_context(false));
+        contract.step(0);
+        testing_env!(VMContextBuilder::new().block_index(1).build());
+        contract.step(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rate limit exceeded")]
+    fn test_rate_limit_exceeded() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_rate_limit(Some(RateLimit { max_calls: 1, window_blocks: 10 }));
+        contract.step(0);
+        testing_env!(VMContextBuilder::new().block_index(9).build());
+        contract.step(0);
+    }
+
+    #[test]
+    fn test_flag_and_hide_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        for _ in 0..3 {
+            contract.create_board(Board::new().field);
+        }
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(10u128.pow(22))
+            .build());
+        contract.flag_board(1, "offensive".to_string());
+        assert_eq!(contract.get_flags(1)[0].reason, "offensive");
+
+        testing_env!(get_context(false));
+        contract.hide_board(1);
+        let visible: Vec<BoardIndex> = contract.get_boards(0, 10).into_iter().map(|(i, _)| i).collect();
+        assert_eq!(visible, vec![0, 2]);
+        assert!(contract.get_board(1).is_some());
+
+        contract.unhide_board(1);
+        assert_eq!(contract.get_boards(1, 1)[0].0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_flag_board_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.flag_board(0, "spam".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only moderators can do this")]
+    fn test_hide_board_not_moderator() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(1)).build());
+        contract.hide_board(0);
+    }
+
+    #[test]
+    fn test_moderator_freeze_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.add_moderator(accounts(1).into());
+        assert_eq!(contract.get_moderators(), vec![AccountId::from(accounts(1))]);
+
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(1)).build());
+        assert!(contract.freeze_board(0).frozen);
+        contract.hide_board(0);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"board_hidden\"")));
+        assert!(!contract.unfreeze_board(0).frozen);
+
+        testing_env!(get_context(false));
+        contract.remove_moderator(accounts(1).into());
+        assert!(contract.get_moderators().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "The board is frozen")]
+    fn test_step_frozen_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.freeze_board(0);
+        contract.step(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only moderators can do this")]
+    fn test_freeze_board_not_moderator() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(1)).build());
+        contract.freeze_board(0);
+    }
+
+    #[test]
+    fn test_estimate_storage_cost() {
+        testing_env!(get_context(false));
+        let contract = Contract::new();
+        let default = contract.estimate_storage_cost(16, 16, HistoryPolicy::default());
+        assert_eq!(default.required_deposit.0, 0);
+        assert_eq!(default.cost.0, default.bytes as u128 * env::storage_byte_cost());
+
+        let full = contract.estimate_storage_cost(16, 16, HistoryPolicy::Full);
+        assert_eq!(full.required_deposit.0, HistoryPolicy::Full.required_deposit(FIELD_LEN as u64));
+        assert!(full.bytes > default.bytes);
+        let larger = contract.estimate_storage_cost(64, 64, HistoryPolicy::default());
+        assert!(larger.bytes > default.bytes);
+
+        let board = BoardWithBlock::new(Board::new(), accounts(0).into(), None);
+        assert!(board.try_to_vec().unwrap().len() as u64 <= default.bytes);
+    }
+
+    #[test]
+    fn test_step_many() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        contract.freeze_board(1);
+
+        let result = contract.step_many(vec![0, 1, 7], None);
+        assert_eq!(result.processed, vec![0]);
+        assert_eq!(result.failed, vec![1, 7]);
+        assert_eq!(result.next_cursor, None);
+        assert_eq!(contract.get_board(0).unwrap().generation, 1);
+
+        testing_env!(VMContextBuilder::new().prepaid_gas(batch::BATCH_GAS_RESERVE).build());
+        let result = contract.step_many(vec![0, 1], Some(0));
+        assert!(result.processed.is_empty());
+        assert_eq!(result.skipped, vec![0, 1]);
+        assert_eq!(result.next_cursor, Some(0));
+    }
+
+    #[test]
+    fn test_claim_handle() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_handle(0, "glider-gun-1".to_string());
+        assert_eq!(contract.get_board_by_handle("glider-gun-1".to_string()).unwrap().0, 0);
+
+        contract.claim_handle(0, "gun".to_string());
+        assert!(contract.get_board_by_handle("glider-gun-1".to_string()).is_none());
+        assert_eq!(contract.get_board(0).unwrap().handle.as_deref(), Some("gun"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The handle is taken")]
+    fn test_claim_taken_handle() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_handle(0, "gun".to_string());
+        contract.claim_handle(1, "gun".to_string());
+    }
+
+    #[test]
+    fn test_follow_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(10u128.pow(22))
+            .build());
+        contract.follow_board(1);
+        assert_eq!(contract.follow_board(0).followers, 1);
+        assert_eq!(contract.get_followed_boards(accounts(1).into()), vec![1, 0]);
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"board_stepped\"")));
+
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(1)).build());
+        assert_eq!(contract.unfollow_board(0).followers, 0);
+        assert_eq!(contract.get_followed_boards(accounts(1).into()), vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_follow_board_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.follow_board(0);
+    }
+
+    #[test]
+    fn test_post_comment() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        let deposit = 10u128.pow(22);
+        for text in ["nice", "very nice", "wow"] {
+            testing_env!(VMContextBuilder::new().attached_deposit(deposit).build());
+            contract.post_comment(0, text.to_string());
+        }
+        let page: Vec<String> = contract.get_comments(0, 1, 5).into_iter().map(|c| c.text).collect();
+        assert_eq!(page, vec!["very nice", "wow"]);
+        assert_eq!(contract.get_board(0).unwrap().comment_count, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_post_comment_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.post_comment(0, "free".to_string());
+    }
+
+    #[test]
+    fn test_layers_merge_on_step() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_layers_enabled(0, true);
+
+        for (i, cells) in [vec![(4, 4), (5, 4)], vec![(5, 4), (6, 4)]].into_iter().enumerate() {
+            testing_env!(VMContextBuilder::new()
+                .predecessor_account_id(accounts(i))
+                .attached_deposit(10u128.pow(22))
+                .build());
+            contract.paint_layer(0, cells, true);
+        }
+        assert_eq!(contract.get_board(0).unwrap().layer_owners.len(), 2);
+        assert_eq!(contract.get_layer(0, accounts(1).into()).unwrap().population(), 2);
+
+        testing_env!(get_context(false));
+        let board = contract.step(0);
+        // The merged blinker has flipped to its vertical phase.
+        assert_eq!(board.board.live_cells(), vec![(5, 3), (5, 4), (5, 5)]);
+        assert!(board.layer_owners.is_empty());
+        assert!(contract.get_layer(0, accounts(0).into()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_paint_layer_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_layers_enabled(0, true);
+        contract.paint_layer(0, vec![(4, 4)], true);
+    }
+
+    #[test]
+    fn test_scheduled_steps() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.schedule_step(0, 5);
+        contract.schedule_step(0, 3);
+        contract.schedule_step(0, 9);
+        let due: Vec<BlockHeight> = contract.get_scheduled_steps(0, 10).iter().map(|s| s.at_block).collect();
+        assert_eq!(due, vec![3, 5, 9]);
+
+        testing_env!(VMContextBuilder::new().block_index(6).predecessor_account_id(accounts(1)).build());
+        let result = contract.execute_due_steps(10);
+        assert_eq!(result.processed, vec![1, 0]);
+        assert_eq!(contract.get_board(0).unwrap().generation, 2);
+        assert_eq!(contract.get_scheduled_steps(0, 10).len(), 1);
+
+        assert!(contract.execute_due_steps(10).processed.is_empty());
+
+        // Schedules left for lack of gas stay due; there is no cursor to resume from.
+        testing_env!(VMContextBuilder::new().block_index(10).prepaid_gas(batch::BATCH_GAS_RESERVE).build());
+        let result = contract.execute_due_steps(10);
+        assert_eq!((result.skipped, result.next_cursor), (vec![2], None));
+        assert_eq!(contract.get_scheduled_steps(0, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_schedule_step_pays_storage_from_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let storage_before = env::storage_usage();
+        let schedule = contract.schedule_step(0, 5);
+        let cost = (env::storage_usage() - storage_before) as u128 * env::storage_byte_cost();
+        assert_eq!(schedule.tip.0, 10u128.pow(22) - cost);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_schedule_step_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.schedule_step(0, 5);
+    }
+
+    #[test]
+    fn test_create_from_template() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.register_template("soup".to_string(), TemplateKind::SymmetricSoup, 16, 16, String::new());
+        contract.register_template("ring".to_string(), TemplateKind::BlinkerRing, 32, 32, String::new());
+        contract.register_template("banner".to_string(), TemplateKind::TextBanner, 32, 16, String::new());
+        assert_eq!(contract.get_templates().len(), 3);
+
+        let params = TemplateParams { seed: Some(7), ..Default::default() };
+        let index = contract.create_from_template("soup".to_string(), params);
+        let soup = contract.get_board(index).unwrap().board;
+        assert!(soup.population() > 0);
+        assert_eq!(soup.transformed(Symmetry::FlipHorizontal), soup);
+        assert_eq!(soup.transformed(Symmetry::FlipVertical), soup);
+
+        let params = TemplateParams { count: Some(6), ..Default::default() };
+        let index = contract.create_from_template("ring".to_string(), params);
+        let ring = contract.get_board(index).unwrap().board;
+        assert_eq!(ring.population(), 18);
+        assert_eq!(ring.next_generation().next_generation(), ring);
+
+        let params = TemplateParams { text: Some("Hi".to_string()), ..Default::default() };
+        let index = contract.create_from_template("banner".to_string(), params);
+        let banner = contract.get_board(index).unwrap().board;
+        let (_, bounds) = banner.trimmed().unwrap();
+        assert_eq!((bounds.min_x, bounds.max_x, bounds.min_y, bounds.max_y), (10, 19, 4, 10));
+    }
+
+    #[test]
+    fn test_write_text() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        let board = contract.write_text(0, "I".to_string(), 2, 3).board;
+        // Serifs on the top and bottom rows, a stem in between.
+        assert_eq!(board.live_cells().len(), 11);
+        assert!(board.is_bit_set(3, 3) && board.is_bit_set(4, 6) && board.is_bit_set(5, 9));
+
+        let changes = contract.get_changes_since(0, 0).changes;
+        assert_eq!(changes[0].delta.born.len(), 11);
+    }
+
+    #[test]
+    fn test_shard_routing() {
+        let mut context = get_context(false);
+        context.input = b"\0asm".to_vec();
+        testing_env!(context);
+        let mut contract = Contract::new();
+        contract.set_shard_code();
+        contract.deploy_shard("shard0".to_string(), 1);
+        assert_eq!(contract.get_shards()[0].account_id, "shard0.alice.near");
+
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(vec![]));
+        contract.on_shard_deployed(0, U128(0));
+        assert!(contract.get_shards()[0].active);
+
+        let index = near_sdk::serde_json::to_vec(&5u64).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(index));
+        let routed = contract.on_shard_board_created(0, accounts(1).into(), U128(0), U128(0)).unwrap();
+        assert_eq!(routed.account_id, "shard0.alice.near");
+        assert_eq!(contract.get_total_boards(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "No shard has room left")]
+    fn test_create_board_routed_without_shards() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board_routed(Board::new().field);
+    }
+
+    #[test]
+    fn test_import_board_callback() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let remote = near_sdk::serde_json::json!({
+            "board": board_from_rows(&["XXX"]),
+            "owner_id": accounts(3),
+            "generation": 42,
+        });
+        let remote = near_sdk::serde_json::to_vec(&remote).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(remote));
+        let index = contract.on_board_imported(accounts(2).into(), 7, accounts(1).into(), U128(0)).unwrap();
+        let board = contract.get_board(index).unwrap();
+        assert_eq!(board.owner_id, AccountId::from(accounts(1)));
+        assert_eq!(board.board.population(), 3);
+        let provenance = board.imported_from.unwrap();
+        assert_eq!((provenance.index, provenance.generation), (7, 42));
+        assert_eq!(provenance.contract_id, AccountId::from(accounts(2)));
+    }
+
+    #[test]
+    fn test_export_and_import_snapshot() {
+        testing_env!(get_context(false));
+        let mut source = Contract::new();
+        source.create_board(board_from_rows(&["XXX"]).field);
+        source.create_board(board_from_rows(&["XX", "XX"]).field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        source.claim_handle(1, "block".to_string());
+        testing_env!(get_context(false));
+        source.step(0);
+        let snapshots = source.export_all_boards(0, 10);
+        assert_eq!(snapshots.len(), 2);
+
+        testing_env!(get_context(false));
+        let mut target = Contract::new();
+        assert_eq!(target.import_snapshot(snapshots), 2);
+        let restored = target.get_board(0).unwrap();
+        assert_eq!(restored.generation, 1);
+        assert_eq!(restored.board, source.get_board(0).unwrap().board);
+        assert_eq!(target.get_board_by_handle("block".to_string()).unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_step_callback() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        let callback = StepCallback {
+            contract_id: accounts(2).into(),
+            method_name: "on_board_step".to_string(),
+        };
+        contract.set_step_callback(index, Some(callback));
+        contract.step(index);
+
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains(&AccountId::from(accounts(2))));
+        assert!(receipts.contains("on_board_step"));
+        assert!(receipts.contains(r#"\"generation\":1"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "Callbacks can't call this contract or its shards")]
+    fn test_step_callback_to_self() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(Board::new().field);
+        let callback = StepCallback {
+            contract_id: env::current_account_id(),
+            method_name: "hide_board".to_string(),
+        };
+        contract.set_step_callback(index, Some(callback));
+    }
+
+    #[test]
+    #[should_panic(expected = "Callbacks can't call restricted methods")]
+    fn test_step_callback_to_private_method() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(Board::new().field);
+        let callback = StepCallback {
+            contract_id: accounts(2).into(),
+            method_name: "sync_tile".to_string(),
+        };
+        contract.set_step_callback(index, Some(callback));
+    }
+
+    #[test]
+    fn test_rule_oracle() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut field = Board::with_dimensions(3, 1);
+        (0..3).for_each(|x| field.set_bit(x, 0, true));
+        let index = contract.internal_add_board(&BoardWithBlock::new(field, "bob.near".to_string(), None));
+        contract.set_rule_oracle(index, Some(accounts(2).into()));
+        contract.step_with_oracle(index);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains("evaluate_row"));
+
+        let change_seq = contract.get_board(index).unwrap().change_seq;
+        let row = near_sdk::serde_json::to_vec(&Base64VecU8(vec![1, 0, 1])).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(row));
+        let board = contract.on_oracle_rows(index, change_seq, HeldStep { account_id: "bob.near".to_string(), fee: U128(0), points: 0 }).unwrap();
+        assert_eq!(board.generation, 1);
+        assert_eq!(board.board.to_string(), vec!["X.X".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The board steps through its rule oracle")]
+    fn test_rule_oracle_blocks_step() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(Board::new().field);
+        contract.set_rule_oracle(index, Some(accounts(2).into()));
+        contract.step(index);
+    }
+
+    #[test]
+    fn test_finalize_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        testing_env!(VMContextBuilder::new().block_index(7).build());
+        let board = contract.finalize_board(index);
+        assert_eq!(board.finalized_at, Some(7));
+        assert!(board.frozen);
+        let museum = contract.get_museum(0, 10);
+        assert_eq!(museum.len(), 1);
+        assert_eq!(museum[0].0, index);
+        assert_eq!(contract.get_museum_size(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The board is finalized")]
+    fn test_finalized_board_cannot_be_unfrozen() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(Board::new().field);
+        contract.finalize_board(index);
+        contract.unfreeze_board(index);
+    }
+
+    #[test]
+    fn test_board_metadata() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        let metadata = contract.get_board_metadata(index);
+        assert_eq!(metadata.title.unwrap(), "Board #0");
+        let media = metadata.media.unwrap();
+        let svg = near_sdk::base64::decode(media.strip_prefix("data:image/svg+xml;base64,").unwrap()).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains(r#"viewBox="0 0 16 16""#));
+        assert!(svg.contains(r#"d="M4 4h3v1h-3z""#));
+        let extra: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&metadata.extra.unwrap()).unwrap();
+        assert_eq!(extra["attributes"][0]["value"], "B3/S23");
+        assert_eq!(extra["attributes"][4]["value"], 3);
+    }
+
+    #[test]
+    fn test_board_limits() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        contract.set_board_limits(index, None, Some(2));
+        assert!(!contract.step(index).frozen);
+        testing_env!(get_context(false));
+        let board = contract.step(index);
+        assert!(board.frozen && board.frozen_by_limit);
+        assert!(get_logs().iter().any(|log| log.contains("board_limit_reached")));
+
+        testing_env!(get_context(false));
+        let board = contract.set_board_limits(index, Some(100), Some(5));
+        assert!(!board.frozen);
+        assert_eq!(contract.step(index).generation, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "The board is frozen")]
+    fn test_population_limit_stops_steps() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        contract.set_board_limits(index, Some(3), None);
+        contract.step(index);
+        testing_env!(get_context(false));
+        contract.step(index);
+    }
+
+    #[test]
+    fn test_scenarios() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let owner_id = env::predecessor_account_id();
+        let index = contract.start_scenario("blinker_survival".to_string(), vec![(5, 3)]);
+        for _ in 0..10 {
+            testing_env!(get_context(false));
+            contract.step(index);
+        }
+        assert_eq!(contract.get_completed_scenarios(owner_id.clone()), vec!["blinker_survival".to_string()]);
+        assert!(contract.get_board(index).unwrap().frozen);
+
+        let glider = vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let index = contract.start_scenario("glider_corner".to_string(), glider);
+        let mut generations = 0;
+        while contract.get_completed_scenarios(owner_id.clone()).len() < 2 {
+            testing_env!(get_context(false));
+            generations = contract.step(index).generation;
+        }
+        assert!(generations <= 48);
+    }
+
+    #[test]
+    fn test_scenario_edit_does_not_count() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let owner_id = env::predecessor_account_id();
+        let index = contract.start_scenario("blinker_survival".to_string(), vec![]);
+        contract.write_text(index, "I".to_string(), 0, 0);
+        for _ in 0..10 {
+            testing_env!(get_context(false));
+            contract.step(index);
+        }
+        assert!(contract.get_board(index).unwrap().board.population() > 0);
+        assert!(contract.get_completed_scenarios(owner_id).is_empty());
+    }
+
+    fn battle_context(account: usize, deposit: u128) -> VMContext {
+        VMContextBuilder::new()
+            .predecessor_account_id(accounts(account))
+            .attached_deposit(deposit)
+            .build()
+    }
+
+    #[test]
+    fn test_battle() {
+        testing_env!(battle_context(0, 10u128.pow(22)));
+        let mut contract = Contract::new();
+        let id = contract.create_battle(6, 3, U128(1), 3, 4);
+        // The storage the battle takes comes out of player A's deposit.
+        let stake = contract.get_battle(id).unwrap().stake.0;
+        assert!(stake > 0 && stake < 10u128.pow(22));
+        testing_env!(battle_context(1, stake));
+        contract.join_battle(id);
+        // Player A's L grows into a block, player B's lone cells die.
+        let moves = [(0, (0, 0)), (1, (5, 0)), (0, (1, 0)), (1, (5, 2)), (0, (0, 1)), (1, (3, 2))];
+        for (player, (x, y)) in moves {
+            testing_env!(battle_context(player, 10u128.pow(22)));
+            contract.place_battle_cell(id, x, y);
+        }
+        testing_env!(battle_context(2, 0));
+        let battle = contract.settle_battle(id);
+        assert_eq!((battle.score_a, battle.score_b), (4, 0));
+        assert_eq!(battle.winner, Some(accounts(0).into()));
+        assert_eq!(battle.status, BattleStatus::Finished);
+
+        assert_eq!(contract.get_match_replay_len(MatchId::Battle(id)), 6);
+        match &contract.get_match_replay(MatchId::Battle(id), 1, 1)[0] {
+            ReplayFrame::Placement { player_id, x, y, .. } => {
+                assert_eq!((player_id.clone(), *x, *y), (accounts(1).into(), 5, 0));
+            }
+            frame => panic!("Unexpected frame {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn test_battle_settles_over_several_calls() {
+        testing_env!(battle_context(0, 10u128.pow(22)));
+        let mut contract = Contract::new();
+        let id = contract.create_battle(6, 3, U128(0), 1, 4);
+        let stake = contract.get_battle(id).unwrap().stake.0;
+        testing_env!(battle_context(1, stake));
+        contract.join_battle(id);
+        for (player, x) in [(0, 0), (1, 5)] {
+            testing_env!(battle_context(player, 10u128.pow(22)));
+            contract.place_battle_cell(id, x, 0);
+        }
+        // With no gas to spare every call runs a single generation.
+        for settled in 1..4 {
+            testing_env!(VMContextBuilder::new().prepaid_gas(batch::BATCH_GAS_RESERVE).build());
+            let battle = contract.settle_battle(id);
+            assert_eq!((battle.status, battle.settled_generations), (BattleStatus::Ready, settled));
+        }
+        let battle = contract.settle_battle(id);
+        assert_eq!((battle.status, battle.settled_generations), (BattleStatus::Finished, 4));
+        assert_eq!(battle.winner, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cells can only be placed in your own half")]
+    fn test_battle_placement_zone() {
+        testing_env!(battle_context(0, 10u128.pow(22)));
+        let mut contract = Contract::new();
+        let id = contract.create_battle(6, 3, U128(0), 1, 4);
+        let stake = contract.get_battle(id).unwrap().stake.0;
+        testing_env!(battle_context(1, stake));
+        contract.join_battle(id);
+        testing_env!(battle_context(0, 0));
+        contract.place_battle_cell(id, 3, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "It's not your turn")]
+    fn test_battle_turns() {
+        testing_env!(battle_context(0, 10u128.pow(22)));
+        let mut contract = Contract::new();
+        let id = contract.create_battle(6, 3, U128(0), 2, 4);
+        let stake = contract.get_battle(id).unwrap().stake.0;
+        testing_env!(battle_context(1, stake));
+        contract.join_battle(id);
+        contract.place_battle_cell(id, 4, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_create_battle_without_deposit() {
+        testing_env!(battle_context(0, 0));
+        let mut contract = Contract::new();
+        contract.create_battle(6, 3, U128(0), 1, 4);
+    }
+
+    #[test]
+    fn test_action_points() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        let config = ActionPointConfig { max_points: 3, points_per_block: 1, step_cost: 2, cell_cost: 1 };
+        contract.set_action_point_config(Some(config));
+        // The owner steps for free.
+        contract.step(0);
+        assert_eq!(contract.get_action_points(env::predecessor_account_id()), Some(3));
+
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        contract.step(0);
+        assert_eq!(contract.get_action_points(accounts(2).into()), Some(1));
+        testing_env!(VMContextBuilder::new().block_index(1).build());
+        assert_eq!(contract.get_action_points(accounts(2).into()), Some(2));
+        testing_env!(VMContextBuilder::new().block_index(5).build());
+        assert_eq!(contract.get_action_points(accounts(2).into()), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough action points")]
+    fn test_action_points_exhausted() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        let config = ActionPointConfig { max_points: 3, points_per_block: 1, step_cost: 2, cell_cost: 1 };
+        contract.set_action_point_config(Some(config));
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        contract.step(0);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        contract.step(0);
+    }
+
+    #[test]
+    fn test_simulation() {
+        let mut runner = simulation::ScenarioRunner::new();
+        runner.create(board_from_rows(&[".X.", "..X", "XXX"]));
+        runner.create(board_from_rows(&["XXX"]));
+        let mut wide = Board::with_dimensions(32, 32);
+        (0..32).for_each(|i| wide.set_bit(i, i, true));
+        runner.create(wide);
+        runner.run(30);
+        assert!(runner.max_step_gas > 0);
+        assert_eq!(runner.contract.get_board(1).unwrap().period, Some(2));
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        contract.step(index);
+        assert!(contract.check_invariants(index).is_ok());
+
+        let mut board = contract.get_board(index).unwrap();
+        board.board.set_bit(0, 0, true);
+        board.prev_block_height = 5;
+        contract.boards.replace(index, &board);
+        let report = contract.check_invariants(index);
+        assert_eq!(
+            report.violations,
+            vec![
+                "latest recorded hash doesn't match the field".to_string(),
+                "recorded population 3 doesn't match the field's 4".to_string(),
+                "previous step block is after the current one".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fee_config_timelock() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let config = FeeConfig { creation_fee: U128(5), ..FeeConfig::default() };
+        let pending = contract.set_fee_config(config.clone());
+        assert_eq!(pending.effective_at, fees::FEE_CONFIG_TIMELOCK);
+        assert_eq!(contract.get_fee_config(), FeeConfig::default());
+        contract.create_board(Board::new().field);
+
+        let effective = fees::FEE_CONFIG_TIMELOCK;
+        testing_env!(VMContextBuilder::new().block_index(effective).attached_deposit(7).build());
+        assert_eq!(contract.get_fee_config(), config);
+        assert!(contract.get_pending_fee_config().is_none());
+        contract.create_board(Board::new().field);
+        assert_eq!(get_created_receipts().len(), 1, "The surplus is refunded");
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 5")]
+    fn test_creation_fee() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.set_fee_config(FeeConfig { creation_fee: U128(5), ..FeeConfig::default() });
+        testing_env!(VMContextBuilder::new().block_index(fees::FEE_CONFIG_TIMELOCK).build());
+        contract.create_board(Board::new().field);
+    }
+
+    #[test]
+    fn test_treasury() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.set_fee_config(FeeConfig { creation_fee: U128(5), ..FeeConfig::default() });
+        testing_env!(VMContextBuilder::new().block_index(fees::FEE_CONFIG_TIMELOCK).attached_deposit(20).build());
+        contract.create_board(Board::new().field);
+        assert_eq!(contract.get_treasury().balance.0, 5);
+        testing_env!(VMContextBuilder::new().block_index(fees::FEE_CONFIG_TIMELOCK).attached_deposit(20).build());
+        contract.split_board(0);
+        assert_eq!(contract.get_treasury().total_collected.0, 25);
+
+        let treasury = contract.withdraw_treasury(U128(10), accounts(3).into());
+        assert_eq!((treasury.balance.0, treasury.total_withdrawn.0), (15, 10));
+    }
+
+    #[test]
+    #[should_panic(expected = "The treasury holds only 0")]
+    fn test_treasury_overdraw() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.withdraw_treasury(U128(1), accounts(3).into());
+    }
+
+    #[test]
+    fn test_universe_viewport() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut left = Board::new();
+        left.set_bit(15, 0, true);
+        let mut right = Board::new();
+        right.set_bit(0, 0, true);
+        let left = contract.create_board(left.field);
+        let right = contract.create_board(right.field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(left, -1, 0);
+        contract.claim_tile(right, 0, 0);
+        assert_eq!(contract.get_tile(0, 0), Some(right));
+        assert_eq!(contract.get_universe_viewport(-2, 0, 4, 2), vec![".XX.".to_string(), "....".to_string()]);
+
+        contract.release_tile(left);
+        assert_eq!(contract.get_universe_viewport(-2, 0, 4, 1), vec!["..X.".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The tile is taken")]
+    fn test_tile_taken() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(0, 3, 4);
+        contract.claim_tile(1, 3, 4);
+    }
+
+    #[test]
+    fn test_edge_exchange() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        // A blinker lying across the border of two tiles.
+        let mut left = Board::new();
+        left.set_bit(15, 5, true);
+        let mut right = Board::new();
+        right.set_bit(0, 5, true);
+        right.set_bit(1, 5, true);
+        let left = contract.create_board(left.field);
+        let right = contract.create_board(right.field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(left, 0, 0);
+        contract.claim_tile(right, 1, 0);
+        contract.set_edge_exchange(left, true);
+        contract.set_edge_exchange(right, true);
+
+        // The tiles are stepped one at a time; the left one reads the right one's previous state.
+        testing_env!(get_context(false));
+        contract.step(right);
+        testing_env!(get_context(false));
+        contract.step(left);
+        assert_eq!(
+            contract.get_universe_viewport(15, 4, 3, 3),
+            vec![".X.".to_string(), ".X.".to_string(), ".X.".to_string()]
+        );
+        testing_env!(get_context(false));
+        contract.step(left);
+        testing_env!(get_context(false));
+        contract.step(right);
+        assert_eq!(
+            contract.get_universe_viewport(15, 4, 3, 3),
+            vec!["...".to_string(), "XXX".to_string(), "...".to_string()]
+        );
+
+        // Without the exchange the half on the right dies out on its own.
+        contract.set_edge_exchange(left, false);
+        testing_env!(get_context(false));
+        contract.step(right);
+        assert_eq!(contract.get_board(right).unwrap().board.population(), 0);
+    }
+
+    /// A call the contract makes to itself, like a `sync_tile` promise.
+    fn self_call_context() -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .build()
+    }
+
+    #[test]
+    fn test_edge_exchange_sync() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        for _ in 0..3 {
+            contract.create_board(Board::new().field);
+        }
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(0, 0, 0);
+        contract.claim_tile(1, 0, 1);
+        contract.claim_tile(2, 1, 0);
+        for index in 0..3 {
+            contract.set_edge_exchange(index, true);
+        }
+        // A frozen neighbour lends its latest state instead of blocking its neighbours.
+        contract.add_moderator(env::predecessor_account_id());
+        contract.freeze_board(2);
+
+        testing_env!(get_context(false));
+        contract.step(1);
+        testing_env!(get_context(false));
+        contract.step(1);
+        assert_eq!(contract.get_board(1).unwrap().generation, 2);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains("sync_tile"));
+        assert!(receipts.contains(r#"{\"account_id\":\"bob.near\",\"index\":0}"#));
+
+        // The neighbour catches up one step per sync, sending itself again while behind.
+        testing_env!(self_call_context());
+        assert_eq!(contract.sync_tile(0, "bob.near".to_string()).generation, 1);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains(r#"\"index\":0"#));
+        testing_env!(self_call_context());
+        assert_eq!(contract.sync_tile(0, "bob.near".to_string()).generation, 2);
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(contract.get_board(2).unwrap().generation, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rate limit exceeded")]
+    fn test_sync_tile_rate_limit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(0, 0, 0);
+        contract.claim_tile(1, 1, 0);
+        contract.set_edge_exchange(0, true);
+        contract.set_edge_exchange(1, true);
+        contract.set_rate_limit(Some(RateLimit { max_calls: 1, window_blocks: 10 }));
+        testing_env!(get_context(false));
+        contract.step(1);
+        testing_env!(self_call_context());
+        contract.sync_tile(0, "bob.near".to_string());
+    }
+
+    #[test]
+    fn test_sync_skips_tiles_with_a_fee() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.internal_add_board(&BoardWithBlock::new(Board::new(), accounts(3).into(), None));
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(0, 0, 0);
+        contract.set_edge_exchange(0, true);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(10u128.pow(22))
+            .build());
+        contract.claim_tile(1, 1, 0);
+        contract.set_edge_exchange(1, true);
+        testing_env!(get_context(false));
+        with_fees(&mut contract, FeeConfig { step_fee: U128(4), ..FeeConfig::default() }, 0);
+        contract.step(0);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(!receipts.contains("sync_tile"));
+    }
+
+    #[test]
+    fn test_edge_exchange_auto_step() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(0, 0, 0);
+        contract.claim_tile(1, 1, 0);
+        contract.set_edge_exchange(0, true);
+        contract.set_edge_exchange(1, true);
+        contract.set_auto_step(0, true);
+
+        // Catch-up runs several generations ahead of the idle neighbour.
+        testing_env!(VMContextBuilder::new().block_index(5).build());
+        let board = contract.poke(0);
+        assert_eq!(board.generation, 5);
+    }
+
+    #[test]
+    fn test_event_tag() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_diff_events(0, true);
+        contract.set_event_tag(0, Some("my-app".to_string()));
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        let event = get_logs().into_iter().find(|log| log.starts_with("EVENT_JSON:")).unwrap();
+        let event: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&event["EVENT_JSON:".len()..]).unwrap();
+        assert_eq!(event["event"], "board_step_diff");
+        assert_eq!(event["data"][0]["tag"], "my-app");
+
+        contract.set_event_tag(0, None);
+        testing_env!(get_context(false));
+        contract.step(0);
+        let event = get_logs().into_iter().find(|log| log.starts_with("EVENT_JSON:")).unwrap();
+        assert!(!event.contains("\"tag\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "Event tags are 1 to 64 bytes")]
+    fn test_event_tag_too_long() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_event_tag(0, Some("x".repeat(65)));
+    }
+
+    #[test]
+    fn test_board_headers() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(1, 1, true);
+        contract.create_board(board.field);
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        contract.add_moderator(env::predecessor_account_id());
+        contract.hide_board(1);
+        testing_env!(VMContextBuilder::new().block_index(7).build());
+        contract.step(0);
+
+        let headers = contract.get_board_headers(0, 10);
+        assert_eq!(headers.iter().map(|header| header.index).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(
+            headers[0],
+            BoardHeader {
+                index: 0,
+                owner_id: env::predecessor_account_id(),
+                width: 16,
+                height: 16,
+                generation: 1,
+                population: 0,
+                current_block_height: 7,
+                hidden: false,
+                fingerprint: Fingerprint::new(&Board::new()),
+            }
+        );
+        assert_eq!(contract.get_board_headers(1, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_api_lists_every_method() {
+        // Collects the `pub fn`s of every `#[near_bindgen] impl Contract` block in the sources.
+        let mut methods = vec![];
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src")).unwrap() {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let mut in_bindgen = false;
+            let mut lines = source.lines().peekable();
+            while let Some(line) = lines.next() {
+                if line == "#[near_bindgen]" && lines.peek() == Some(&"impl Contract {") {
+                    in_bindgen = true;
+                } else if line == "}" {
+                    in_bindgen = false;
+                } else if let Some(signature) = line.strip_prefix("    pub fn ").filter(|_| in_bindgen) {
+                    methods.push(signature.split('(').next().unwrap().to_string());
+                }
+            }
+        }
+        let mut registered: Vec<String> = api::API.iter().map(|method| method.name.to_string()).collect();
+        methods.sort();
+        registered.sort();
+        assert_eq!(registered, methods);
+
+        testing_env!(get_context(false));
+        let contract = Contract::new();
+        let step = contract.get_api().into_iter().find(|method| method.name == "step").unwrap();
+        assert_eq!((step.kind, step.payable, step.permission), (MethodKind::Call, true, Permission::Anyone));
+        let json = near_sdk::serde_json::to_value(step).unwrap();
+        assert_eq!(json["args"], near_sdk::serde_json::json!([{"name": "index", "type": "BoardIndex"}]));
+    }
+
+    #[test]
+    fn test_trail() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        assert!(contract.get_trail(0).is_none());
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.set_trail(0, Some(3));
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        let rows = contract.render_board(0, RenderMode::Trail);
+        assert_eq!(rows[3], ".....X..........");
+        assert_eq!(rows[4], "....2X2.........");
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        let rows = contract.render_board(0, RenderMode::Trail);
+        assert_eq!(rows[3], ".....2..........");
+        assert_eq!(rows[4], "....XXX.........");
+        let trail = contract.get_trail(0).unwrap();
+        assert_eq!((trail.length, trail.counters[3 * 16 + 5], trail.counters[4 * 16 + 4]), (3, 2, 3));
+
+        contract.set_trail(0, None);
+        assert_eq!(contract.render_board(0, RenderMode::Trail), contract.render_board(0, RenderMode::Ascii));
+    }
+
+    #[test]
+    fn test_find_similar_boards() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let glider = |dx: usize| {
+            let mut board = Board::new();
+            for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+                board.set_bit(x + dx, y, true);
+            }
+            board.field
+        };
+        contract.create_board(glider(0));
+        let mut crowded = Board::new();
+        for x in 0..16 {
+            crowded.set_bit(x, 8, true);
+            crowded.set_bit(x, 12, true);
+        }
+        contract.create_board(crowded.field);
+        contract.create_board(glider(0));
+        contract.create_board(glider(1));
+
+        let similar = contract.find_similar_boards(0, 10);
+        assert_eq!(similar.iter().map(|&(index, _)| index).collect::<Vec<_>>(), vec![2, 3, 1]);
+        assert_eq!(similar[0].1, 0);
+        assert!(similar[1].1 < similar[2].1);
+        assert_eq!(contract.find_similar_boards(0, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_share_payload() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        testing_env!(get_context(false));
+        contract.step(0);
+        contract.set_rule_oracle(0, Some("rules.near".to_string()));
+
+        let payload = contract.get_share_payload(0);
+        assert!(payload.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let index = contract.create_board_from_payload(payload);
+        let copy = contract.get_board(index).unwrap();
+        let original = contract.get_board(0).unwrap();
+        assert_eq!(copy.board, original.board);
+        assert_eq!(copy.rule_oracle, Some("rules.near".to_string()));
+        assert_eq!((copy.generation, copy.shared_generation), (0, Some(1)));
+        assert_eq!(copy.owner_id, env::predecessor_account_id());
+
+        let packed = share::decode_share_payload(&contract.get_share_payload(index)).unwrap();
+        assert_eq!(packed.generation, 0);
+        // A checkerboard is shorter packed than as run lengths.
+        let mut checkerboard = Board::new();
+        for y in 0..16 {
+            for x in (y % 2..16).step_by(2) {
+                checkerboard.set_bit(x, y, true);
+            }
+        }
+        let checkerboard = BoardWithBlock::new(checkerboard, env::predecessor_account_id(), None);
+        let decoded = share::decode_share_payload(&share::encode_share_payload(&checkerboard)).unwrap();
+        assert_eq!(decoded.board, checkerboard.board);
+        assert!(share::decode_share_payload("AQ").is_none());
+        assert!(share::decode_share_payload("not base64!").is_none());
+    }
+
+    #[test]
+    fn test_engines_agree() {
+        // A fixed pseudo-random stream, so the boards are the same on every run.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        for (width, height) in [(1, 1), (3, 7), (16, 16), (33, 5), (70, 3), (130, 65)] {
+            for density in [2, 4, 16] {
+                let mut board = Board::with_dimensions(width, height);
+                for y in 0..height {
+                    for x in 0..width {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        board.set_bit(x, y, state.is_multiple_of(density));
+                    }
+                }
+                let naive = Engine::Naive.step(&board);
+                for engine in [Engine::Sparse, Engine::BitParallel, Engine::HashLife] {
+                    assert_eq!(engine.step(&board), naive, "{:?} on {}x{}", engine, width, height);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_selection() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut board = Board::new();
+        board.set_bit(4, 4, true);
+        board.set_bit(5, 4, true);
+        board.set_bit(6, 4, true);
+        contract.create_board(board.field);
+        assert_eq!(contract.get_board(0).unwrap().last_step, None);
+
+        testing_env!(get_context(false));
+        let stepped = contract.step(0);
+        assert_eq!(stepped.last_step.map(|report| report.engine), Some(Engine::Sparse));
+        assert_eq!(stepped.board.population(), 3);
+
+        contract.set_engine_hint(0, EngineHint::Always(Engine::Naive));
+        testing_env!(get_context(false));
+        let stepped = contract.step(0);
+        assert_eq!(stepped.last_step.map(|report| report.engine), Some(Engine::Naive));
+
+        let mut crowded = Board::new();
+        for x in 0..16 {
+            crowded.set_bit(x, 8, true);
+        }
+        assert_eq!(EngineHint::Auto.select(&crowded), Engine::Sparse);
+        crowded.set_bit(0, 9, true);
+        assert_eq!(EngineHint::Auto.select(&crowded), Engine::BitParallel);
+        let mut narrow = Board::with_dimensions(4, 4);
+        narrow.set_bit(1, 1, true);
+        narrow.set_bit(2, 1, true);
+        assert_eq!(EngineHint::Auto.select(&narrow), Engine::Naive);
+        let mut large = Board::with_dimensions(64, 64);
+        (0..64).for_each(|x| (0..5).for_each(|y| large.set_bit(x, y, true)));
+        assert_eq!(EngineHint::Auto.select(&large), Engine::HashLife);
+        (0..64).for_each(|x| (5..20).for_each(|y| large.set_bit(x, y, true)));
+        assert_eq!(EngineHint::Auto.select(&large), Engine::BitParallel);
+
+        // Every engine reports its own gas, which is part of the call's gas.
+        for engine in [Engine::BitParallel, Engine::HashLife] {
+            contract.set_engine_hint(0, EngineHint::Always(engine));
+            testing_env!(get_context(false));
+            let report = contract.step(0).last_step.unwrap();
+            assert_eq!(report.engine, engine);
+            assert!(report.gas <= env::used_gas());
+        }
+    }
+
+    #[test]
+    fn test_batches_record_failures() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.create_board(Board::new().field);
+        contract.set_board_limits(0, None, Some(1));
+
+        // The first step reaches the cap and freezes the board; the second fails on its own.
+        testing_env!(get_context(false));
+        let result = contract.step_many(vec![0, 0, 1, 7], None);
+        assert_eq!(result.processed, vec![0, 1]);
+        assert_eq!(result.failed, vec![0, 7]);
+
+        // A failing schedule is refunded and dropped without holding up later ones.
+        testing_env!(VMContextBuilder::new().block_index(1).attached_deposit(10u128.pow(22)).build());
+        contract.schedule_step(0, 5);
+        contract.schedule_step(1, 6);
+        testing_env!(VMContextBuilder::new().block_index(6).build());
+        let result = contract.execute_due_steps(10);
+        assert_eq!((result.processed, result.failed), (vec![1], vec![0]));
+        assert!(contract.get_scheduled_steps(0, 10).is_empty());
+        assert_eq!(get_created_receipts().len(), 2, "One refund and one payout");
+    }
+
+    /// Sets fees and moves to the block they take effect at, with `deposit` attached.
+    fn with_fees(contract: &mut Contract, config: FeeConfig, deposit: u128) {
+        contract.set_fee_config(config);
+        testing_env!(VMContextBuilder::new()
+            .block_index(fees::FEE_CONFIG_TIMELOCK)
+            .attached_deposit(deposit)
+            .build());
+    }
+
+    fn transfers(receipts: &str) -> usize {
+        receipts.matches("Transfer").count()
+    }
+
+    #[test]
+    fn test_step_many_fee() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        for _ in 0..3 {
+            contract.internal_add_board(&BoardWithBlock::new(Board::new(), accounts(3).into(), None));
+        }
+        with_fees(&mut contract, FeeConfig { step_fee: U128(4), ..FeeConfig::default() }, 9);
+        let result = contract.step_many(vec![0, 1, 2], None);
+        assert_eq!((result.processed, result.failed), (vec![0, 1], vec![2]));
+        assert_eq!(contract.get_treasury().balance.0, 8);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains(r#""deposit":1"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 4")]
+    fn test_step_with_oracle_fee() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.internal_add_board(&BoardWithBlock::new(Board::new(), "bob.near".to_string(), None));
+        contract.set_rule_oracle(index, Some(accounts(2).into()));
+        let mut board = contract.get_board(index).unwrap();
+        board.owner_id = accounts(3).into();
+        contract.boards.replace(index, &board);
+        with_fees(&mut contract, FeeConfig { step_fee: U128(4), ..FeeConfig::default() }, 0);
+        contract.step_with_oracle(index);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 5")]
+    fn test_start_scenario_fee() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        with_fees(&mut contract, FeeConfig { creation_fee: U128(5), ..FeeConfig::default() }, 0);
+        contract.start_scenario("blinker_survival".to_string(), vec![]);
+    }
+
+    #[test]
+    fn test_import_board_fee() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        with_fees(&mut contract, FeeConfig { creation_fee: U128(5), ..FeeConfig::default() }, 5);
+        contract.import_board(accounts(2).into(), 7);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains(r#"\"fee\":\"5\""#));
+        assert_eq!(contract.get_treasury().balance.0, 0);
+
+        // The fee is collected once the board is copied and refunded if there was none.
+        let remote = near_sdk::serde_json::json!({
+            "board": board_from_rows(&["XXX"]),
+            "owner_id": accounts(3),
+            "generation": 0,
+        });
+        let remote = near_sdk::serde_json::to_vec(&remote).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(remote));
+        assert!(contract.on_board_imported(accounts(2).into(), 7, accounts(1).into(), U128(5)).is_some());
+        assert_eq!(contract.get_treasury().balance.0, 5);
+
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(b"null".to_vec()));
+        assert!(contract.on_board_imported(accounts(2).into(), 8, accounts(1).into(), U128(5)).is_none());
+        assert_eq!(contract.get_treasury().balance.0, 5);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 1);
+        assert_eq!(contract.get_total_boards(), 1);
+    }
+
+    #[test]
+    fn test_create_board_routed_fee() {
+        let mut context = get_context(false);
+        context.input = b"\0asm".to_vec();
+        testing_env!(context);
+        let mut contract = Contract::new();
+        contract.set_shard_code();
+        contract.deploy_shard("shard0".to_string(), 2);
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(vec![]));
+        contract.on_shard_deployed(0, U128(0));
+        with_fees(&mut contract, FeeConfig { creation_fee: U128(5), ..FeeConfig::default() }, 5);
+        contract.create_board_routed(Board::new().field);
+        assert_eq!(contract.get_treasury().balance.0, 0);
+
+        testing_env_with_promise_results(get_context(false), PromiseResult::Failed);
+        assert!(contract.on_shard_board_created(0, accounts(1).into(), U128(5), U128(0)).is_none());
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 1);
+        assert_eq!((contract.get_treasury().balance.0, contract.get_total_boards()), (0, 0));
+
+        testing_env!(VMContextBuilder::new().block_index(fees::FEE_CONFIG_TIMELOCK).attached_deposit(5).build());
+        contract.create_board_routed(Board::new().field);
+        let index = near_sdk::serde_json::to_vec(&0u64).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(index));
+        assert!(contract.on_shard_board_created(0, accounts(1).into(), U128(5), U128(0)).is_some());
+        assert_eq!((contract.get_treasury().balance.0, contract.get_total_boards()), (5, 1));
+    }
+
+    #[test]
+    fn test_step_many_action_points() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        (0..3).for_each(|_| {
+            contract.create_board(Board::new().field);
+        });
+        let config = ActionPointConfig { max_points: 4, points_per_block: 1, step_cost: 2, cell_cost: 1 };
+        contract.set_action_point_config(Some(config));
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        let result = contract.step_many(vec![0, 1, 2], None);
+        assert_eq!((result.processed, result.failed), (vec![0, 1], vec![2]));
+        assert_eq!(contract.get_action_points(accounts(2).into()), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough action points")]
+    fn test_step_with_oracle_action_points() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.create_board(board_from_rows(&["XXX"]).field);
+        contract.set_rule_oracle(index, Some(accounts(3).into()));
+        let config = ActionPointConfig { max_points: 1, points_per_block: 1, step_cost: 2, cell_cost: 1 };
+        contract.set_action_point_config(Some(config));
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        contract.step_with_oracle(index);
+    }
+
+    #[test]
+    fn test_failed_oracle_refunds_step() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index =
+            contract.internal_add_board(&BoardWithBlock::new(Board::with_dimensions(3, 1), "bob.near".to_string(), None));
+        contract.set_rule_oracle(index, Some(accounts(3).into()));
+        let config = ActionPointConfig { max_points: 5, points_per_block: 0, step_cost: 2, cell_cost: 1 };
+        contract.set_action_point_config(Some(config));
+        with_fees(&mut contract, FeeConfig { step_fee: U128(4), ..FeeConfig::default() }, 4);
+        testing_env!(VMContextBuilder::new()
+            .block_index(fees::FEE_CONFIG_TIMELOCK)
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(4)
+            .build());
+        contract.step_with_oracle(index);
+        assert_eq!(contract.get_action_points(accounts(2).into()), Some(3));
+        assert_eq!(contract.get_treasury().balance.0, 0);
+
+        let change_seq = contract.get_board(index).unwrap().change_seq;
+        testing_env_with_promise_results(get_context(false), PromiseResult::Failed);
+        assert!(contract.on_oracle_rows(index, change_seq, HeldStep { account_id: accounts(2).into(), fee: U128(4), points: 2 }).is_none());
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 1);
+        assert_eq!(contract.get_treasury().balance.0, 0);
+        assert_eq!(contract.get_action_points(accounts(2).into()), Some(5));
+        assert_eq!(contract.get_board(index).unwrap().generation, 0);
+    }
+
+    #[test]
+    fn test_step_many_rate_limit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        (0..3).for_each(|_| {
+            contract.create_board(Board::new().field);
+        });
+        contract.set_rate_limit(Some(RateLimit { max_calls: 2, window_blocks: 10 }));
+        let result = contract.step_many(vec![0, 1, 2], None);
+        assert_eq!((result.processed, result.skipped), (vec![0, 1], vec![2]));
+        assert_eq!(result.next_cursor, Some(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Rate limit exceeded")]
+    fn test_execute_due_steps_rate_limit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.set_rate_limit(Some(RateLimit { max_calls: 1, window_blocks: 10 }));
+        contract.execute_due_steps(10);
+        contract.execute_due_steps(10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rate limit exceeded")]
+    fn test_step_with_oracle_rate_limit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.internal_add_board(&BoardWithBlock::new(Board::with_dimensions(3, 1), "bob.near".to_string(), None));
+        contract.set_rule_oracle(index, Some(accounts(3).into()));
+        contract.set_rate_limit(Some(RateLimit { max_calls: 1, window_blocks: 10 }));
+        contract.step_with_oracle(index);
+        testing_env!(get_context(false));
+        contract.step_with_oracle(index);
+    }
+
+    #[test]
+    fn test_generation_of_oracle_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut field = Board::with_dimensions(3, 1);
+        (0..3).for_each(|x| field.set_bit(x, 0, true));
+        let index = contract.internal_add_board(&BoardWithBlock::new(field.clone(), "bob.near".to_string(), None));
+        contract.set_rule_oracle(index, Some(accounts(2).into()));
+        // The oracle keeps every cell alive, which B3/S23 would not.
+        for _ in 0..2 {
+            let change_seq = contract.get_board(index).unwrap().change_seq;
+            let row = near_sdk::serde_json::to_vec(&Base64VecU8(vec![1, 1, 1])).unwrap();
+            testing_env_with_promise_results(get_context(false), PromiseResult::Successful(row));
+            contract.on_oracle_rows(index, change_seq, HeldStep { account_id: "bob.near".to_string(), fee: U128(0), points: 0 });
+        }
+        assert_eq!(contract.get_generation(index, 1), Some(field.clone()));
+        assert_eq!(contract.get_generation(index, 0), Some(field));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_rebuilds_entries() {
+        testing_env!(get_context(false));
+        let mut source = Contract::new();
+        source.create_board(board_from_rows(&["XXX"]).field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        source.claim_handle(0, "blinker".to_string());
+        source.claim_tile(0, 1, 2);
+        for text in ["nice", "wow"] {
+            testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+            source.post_comment(0, text.to_string());
+        }
+        source.follow_board(0);
+        source.finalize_board(0);
+        let snapshots = source.export_all_boards(0, 1);
+
+        testing_env!(get_context(false));
+        let mut target = Contract::new();
+        target.import_snapshot(snapshots.clone());
+        let restored = target.get_board(0).unwrap();
+        assert_eq!((restored.comment_count, restored.followers), (0, 0));
+        assert!(target.get_comments(0, 0, 10).is_empty());
+        assert_eq!(target.get_board_by_handle("blinker".to_string()).unwrap().0, 0);
+        assert_eq!(target.get_tile(1, 2), Some(0));
+        assert_eq!(target.get_museum_size(), 1);
+
+        // Restoring over the same board again keeps a single museum entry.
+        target.import_snapshot(snapshots.clone());
+        assert_eq!(target.get_museum_size(), 1);
+
+        // Entries another board took in the meantime aren't stolen.
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        target.claim_handle(0, "renamed".to_string());
+        target.release_tile(0);
+        target.create_board(Board::new().field);
+        target.claim_handle(1, "blinker".to_string());
+        target.claim_tile(1, 1, 2);
+        target.import_snapshot(snapshots);
+        let restored = target.get_board(0).unwrap();
+        assert_eq!((restored.handle, restored.tile), (None, None));
+        assert!(target.get_board_by_handle("renamed".to_string()).is_none());
+        assert_eq!(target.get_board_by_handle("blinker".to_string()).unwrap().0, 1);
+        assert_eq!(target.get_tile(1, 2), Some(1));
+    }
+
+    fn copperhead() -> Board {
+        let rows = [
+            ".XX..XX.", "...XX...", "...XX...", "X.X..X.X", "X......X", "........", "X......X", ".XX..XX.",
+            "..XXXX..", "........", "...XX...", "...XX...",
+        ];
+        let mut board = Board::with_dimensions(16, 28);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                board.set_bit(x + 4, y + 8, cell == 'X');
+            }
+        }
+        board
+    }
+
+    #[test]
+    #[should_panic(expected = "The spaceship was already discovered by bob.near")]
+    fn test_spaceship_discovered_once() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let index = contract.internal_add_board(&BoardWithBlock::new(copperhead(), "bob.near".to_string(), None));
+        let achievement = contract.claim_spaceship_discovery(index);
+        assert_eq!(achievement.kind, AchievementKind::SpaceshipDiscovered { period: 10, dx: 0, dy: -1 });
+
+        // Another phase of it, mirrored, on someone else's board.
+        let mut later = copperhead();
+        (0..3).for_each(|_| later = later.next_generation());
+        let later = later.transformed(analysis::Symmetry::FlipVertical);
+        let other = contract.internal_add_board(&BoardWithBlock::new(later, accounts(2).into(), None));
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        assert!(contract.get_spaceship(other).is_some());
+        contract.claim_spaceship_discovery(other);
+    }
+
+    #[test]
+    #[should_panic(expected = "The spaceship is made of known objects")]
+    fn test_claim_glider_pair() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(board_from_rows(&[".X.....X.", "..X.....X", "XXX...XXX"]).field);
+        contract.claim_spaceship_discovery(0);
+    }
+
+    /// A router with one active shard of `capacity` boards.
+    fn router_with_shard(capacity: u64) -> Contract {
+        let mut context = get_context(false);
+        context.input = b"\0asm".to_vec();
+        testing_env!(context);
+        let mut contract = Contract::new();
+        contract.set_shard_code();
+        contract.deploy_shard("shard0".to_string(), capacity);
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(vec![]));
+        contract.on_shard_deployed(0, U128(0));
+        contract
+    }
+
+    #[test]
+    fn test_routed_boards_are_listed() {
+        let mut contract = router_with_shard(1);
+        testing_env!(get_context(false));
+        contract.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        contract.create_board_routed(Board::new().field);
+        // The slot is taken while the shard creates the board.
+        assert_eq!(contract.get_shards()[0].board_count, 1);
+        assert_eq!(contract.get_total_boards(), 1);
+
+        let index = near_sdk::serde_json::to_vec(&3u64).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(index));
+        contract.on_shard_board_created(0, accounts(2).into(), U128(0), U128(0));
+        let locations = contract.get_board_locations(0, 10);
+        assert_eq!(locations.len(), 2);
+        assert_eq!((locations[0].shard_id, locations[0].index), (None, 0));
+        assert_eq!(
+            locations[1],
+            BoardLocation {
+                account_id: "shard0.alice.near".to_string(),
+                shard_id: Some(0),
+                index: 3,
+                owner_id: accounts(2).into(),
+            }
+        );
+        assert_eq!(contract.get_board_locations(1, 10).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "No shard has room left")]
+    fn test_create_board_routed_reserves_slot() {
+        let mut contract = router_with_shard(1);
+        contract.create_board_routed(Board::new().field);
+        testing_env!(get_context(false));
+        contract.create_board_routed(Board::new().field);
+    }
+
+    #[test]
+    fn test_step_routed_forwards_caller() {
+        let mut contract = router_with_shard(1);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).attached_deposit(7).build());
+        contract.step_routed(0, 3);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains("step_for"));
+        assert!(receipts.contains(r#"\"account_id\":\"charlie\""#));
+        assert!(receipts.contains(r#""deposit":7"#));
+
+        // A failed step gives the whole deposit back.
+        testing_env_with_promise_results(get_context(false), PromiseResult::Failed);
+        assert!(contract.on_routed_step(accounts(2).into(), U128(7)).is_none());
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 1);
+    }
+
+    #[test]
+    fn test_step_for_charges_the_user() {
+        testing_env!(get_context(false));
+        let mut shard = Contract::new();
+        shard.internal_add_board(&BoardWithBlock::new(Board::new(), accounts(3).into(), None));
+        shard.set_rate_limit(Some(RateLimit { max_calls: 1, window_blocks: 10 }));
+        // Routed steps are the user's calls, not the router's.
+        shard.step(0);
+        with_fees(&mut shard, FeeConfig { step_fee: U128(4), ..FeeConfig::default() }, 4);
+        assert_eq!(shard.step_for(accounts(2).into(), 0).generation, 2);
+        assert_eq!(shard.get_treasury().balance.0, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the contract owner can do this")]
+    fn test_step_for_is_router_only() {
+        testing_env!(get_context(false));
+        let mut shard = Contract::new();
+        shard.create_board(Board::new().field);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).build());
+        shard.step_for(accounts(2).into(), 0);
+    }
+
+    #[test]
+    fn test_migrate_first_release_state() {
+        use crate::migration::{BoardV0, BoardWithBlockV0, ContractV0};
+        testing_env!(VMContextBuilder::new().block_index(7).build());
+        let mut boards = Vector::new(StorageKey::Boards);
+        for (field, heights) in [(board_from_rows(&["XXX"]).field, (5, 3)), (Board::new().field, (6, 0))] {
+            boards.push(&BoardWithBlockV0 {
+                board: BoardV0 { field },
+                current_block_height: heights.0,
+                prev_block_height: heights.1,
+            });
+        }
+        env::state_write(&ContractV0 { boards });
+
+        let mut contract = Contract::migrate(accounts(3).into());
+        assert_eq!(contract.get_total_boards(), 2);
+        let board = contract.get_board(0).unwrap();
+        assert_eq!(board.board, board_from_rows(&["XXX"]));
+        assert_eq!((board.current_block_height, board.prev_block_height), (5, 3));
+        assert_eq!(board.owner_id, accounts(3).to_string());
+        assert_eq!(contract.get_board(1).unwrap().board, Board::new());
+        assert_eq!(contract.get_boards(0, 10).len(), 2);
+
+        // The converted boards behave like new ones.
+        assert_eq!(contract.step(0).generation, 1);
+        assert_eq!(contract.create_board(Board::new().field), 2);
+    }
+
+    #[test]
+    fn test_step_big_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(board_from_rows(&["XXX"]).field);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(24)).build());
+        contract.resize_board(0, 64, 64, Anchor::TopLeft);
+        // Too tall to print, so the logs stay within a receipt's limit.
+        testing_env!(get_context(false));
+        assert_eq!(contract.step(0).generation, 1);
+        assert!(get_logs().contains(&"64x64 board, 3 live cells".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_resize_board_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.resize_board(0, 64, 64, Anchor::TopLeft);
+    }
+
+    /// Applies the board events in `logs` to `board`, as an indexer would.
+    fn replay_events(logs: &[String], board: &mut Option<Board>) {
+        for log in logs.iter().filter_map(|log| log.strip_prefix("EVENT_JSON:")) {
+            let event: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(log).unwrap();
+            let data = &event["data"][0];
+            match event["event"].as_str().unwrap() {
+                "board_created" | "board_snapshot" => {
+                    let mut field = Board::with_dimensions(
+                        data["width"].as_u64().unwrap() as usize,
+                        data["height"].as_u64().unwrap() as usize,
+                    );
+                    field.field = near_sdk::serde_json::from_value(data["field"].clone()).unwrap();
+                    *board = Some(field);
+                }
+                "board_step_diff" => {
+                    let field = board.as_mut().unwrap();
+                    for (cells, alive) in [(&data["born"], true), (&data["died"], false)] {
+                        for cell in cells.as_array().unwrap() {
+                            let (x, y) = (cell[0].as_u64().unwrap(), cell[1].as_u64().unwrap());
+                            field.set_bit(x as usize, y as usize, alive);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_board_history_from_events() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let mut indexed = None;
+        contract.create_board(board_from_rows(&["XXX"]).field);
+        replay_events(&get_logs(), &mut indexed);
+        contract.set_diff_events(0, true);
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        contract.write_text(0, "I".to_string(), 10, 2);
+        replay_events(&get_logs(), &mut indexed);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(24)).build());
+        contract.resize_board(0, 64, 64, Anchor::TopLeft);
+        replay_events(&get_logs(), &mut indexed);
+
+        testing_env!(get_context(false));
+        contract.step(0);
+        replay_events(&get_logs(), &mut indexed);
+        assert_eq!(indexed, Some(contract.get_board(0).unwrap().board));
+
+        // A checkerboard dies off almost entirely, too many cells to list.
+        let mut checkers = Board::with_dimensions(64, 64);
+        for (x, y) in (0..64).flat_map(|y| (0..64).map(move |x| (x, y))) {
+            checkers.set_bit(x, y, (x + y) % 2 == 0);
+        }
+        testing_env!(get_context(false));
+        let index = contract.internal_add_board(&BoardWithBlock::new(checkers, "bob.near".to_string(), None));
+        let mut indexed = None;
+        replay_events(&get_logs(), &mut indexed);
+        contract.set_diff_events(index, true);
+        testing_env!(get_context(false));
+        contract.step(index);
+        let logs = get_logs();
+        assert!(logs.iter().any(|log| log.contains("\"event\":\"board_snapshot\"")));
+        assert!(!logs.iter().any(|log| log.contains("\"event\":\"board_step_diff\"")));
+        replay_events(&logs, &mut indexed);
+        assert_eq!(indexed, Some(contract.get_board(index).unwrap().board));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_heatmap_tracking_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_heatmap_tracking(0, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_set_trail_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.set_trail(0, Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_claim_handle_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.claim_handle(0, "gun".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_claim_tile_without_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.claim_tile(0, 0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_place_battle_cell_without_storage_deposit() {
+        testing_env!(battle_context(0, 10u128.pow(22)));
+        let mut contract = Contract::new();
+        let id = contract.create_battle(6, 3, U128(1), 1, 4);
+        let stake = contract.get_battle(id).unwrap().stake.0;
+        testing_env!(battle_context(1, stake));
+        contract.join_battle(id);
+        testing_env!(battle_context(0, 1));
+        contract.place_battle_cell(id, 0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_advance_tournament_without_deposit() {
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        let mut contract = Contract::new();
+        let id = tournament_of_two(&mut contract, 0, 3);
+        testing_env!(VMContextBuilder::new().build());
+        contract.advance_tournament(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "The board is frozen")]
+    fn test_claim_tile_frozen() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board(Board::new().field);
+        contract.finalize_board(0);
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+        contract.claim_tile(0, 0, 0);
+    }
+
+    #[test]
+    fn test_late_seeding_ignores_the_block() {
+        let brackets: Vec<Vec<u32>> = [(11, 1), (25, 2), (90, 3)]
+            .into_iter()
+            .map(|(block, seed)| {
+                testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+                let mut contract = Contract::new();
+                let id = contract.create_tournament(U128(0), 8, 8, TournamentMetric::FinalPopulation);
+                for _ in 0..8 {
+                    testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(22)).build());
+                    let index = contract.create_board(board_from_rows(&["XX", "XX"]).field);
+                    contract.enter_tournament(id, index);
+                }
+                contract.commit_seeding(id, 10);
+                testing_env!(VMContextBuilder::new()
+                    .block_index(block + tournament::SEEDING_WINDOW)
+                    .random_seed(vec![seed; 32])
+                    .build());
+                contract.resolve_seeding(id)
+            })
+            .collect();
+        assert_eq!(brackets[0], brackets[1]);
+        assert_eq!(brackets[1], brackets[2]);
+    }
+
+    #[test]
+    fn test_import_snapshot_resets_board_state() {
+        testing_env!(get_context(false));
+        let mut source = Contract::new();
+        source.create_board(board_from_rows(&["XXX"]).field);
+        let snapshots = source.export_all_boards(0, 1);
+
+        testing_env!(VMContextBuilder::new().attached_deposit(10u128.pow(23)).build());
+        let mut target = Contract::new();
+        target.create_board(board_from_rows(&["XX", "XX"]).field);
+        target.set_heatmap_tracking(0, true);
+        target.set_trail(0, Some(3));
+        target.set_layers_enabled(0, true);
+        for _ in 0..3 {
+            testing_env!(get_context(false));
+            target.step(0);
+        }
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10u128.pow(22))
+            .build());
+        target.paint_layer(0, vec![(0, 0)], true);
+        testing_env!(get_context(false));
+        target.finalize_board(0);
+        let seq = target.get_board(0).unwrap().change_seq;
+
+        testing_env!(get_context(false));
+        target.import_snapshot(snapshots);
+        let restored = target.get_board(0).unwrap();
+        let page = target.get_changes_since(0, seq);
+        assert_eq!((page.resync_required, page.changes.len()), (false, 1));
+        assert_eq!(page.changes[0].seq, seq + 1);
+        assert!(restored.layer_owners.is_empty());
+        assert!(target.get_layer(0, accounts(2).into()).is_none());
+        // The snapshot doesn't track a heatmap or trail, and the stale counters are gone.
+        assert!(target.heatmaps.get(&0).is_none() && target.trails.get(&0).is_none());
+        // The museum entry was for the replaced state.
+        assert!(target.get_museum(0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_import_first_release_board() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        let remote = near_sdk::serde_json::json!({
+            "board": { "field": board_from_rows(&["XXX"]).field },
+            "current_block_height": 12,
+            "prev_block_height": 11,
+        });
+        let remote = near_sdk::serde_json::to_vec(&remote).unwrap();
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(remote));
+        let index = contract.on_board_imported(accounts(2).into(), 0, accounts(1).into(), U128(0)).unwrap();
+        let board = contract.get_board(index).unwrap();
+        assert_eq!((board.board.width(), board.board.height(), board.board.population()), (WIDTH, HEIGHT, 3));
+        let provenance = board.imported_from.unwrap();
+        assert_eq!((provenance.owner_id, provenance.generation), (AccountId::from(accounts(2)), 0));
+    }
+
+    #[test]
+    fn test_failed_shard_deployment_can_be_retried() {
+        let mut context = get_context(false);
+        context.input = b"\0asm".to_vec();
+        context.attached_deposit = 10;
+        testing_env!(context);
+        let mut contract = Contract::new();
+        contract.set_shard_code();
+        contract.deploy_shard("shard0".to_string(), 1);
+        testing_env_with_promise_results(get_context(false), PromiseResult::Failed);
+        contract.on_shard_deployed(0, U128(10));
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert_eq!(transfers(&receipts), 1);
+        assert!(!contract.get_shards()[0].active);
+
+        testing_env!(get_context(false));
+        contract.deploy_shard("shard0".to_string(), 2);
+        assert_eq!(contract.get_shards().len(), 1);
+        testing_env_with_promise_results(get_context(false), PromiseResult::Successful(vec![]));
+        contract.on_shard_deployed(0, U128(0));
+        let shard = &contract.get_shards()[0];
+        assert!(shard.active);
+        assert_eq!(shard.capacity, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "The shard is already deployed")]
+    fn test_deploy_active_shard_again() {
+        let mut contract = router_with_shard(1);
+        testing_env!(get_context(false));
+        contract.deploy_shard("shard0".to_string(), 1);
+    }
+
+    #[test]
+    fn test_create_board_routed_forwards_storage_deposit() {
+        let mut contract = router_with_shard(1);
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(2)).attached_deposit(10u128.pow(22)).build());
+        contract.create_board_routed(Board::new().field);
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains(&format!("\"deposit\":{}", 10u128.pow(22))));
+
+        // The deposit the shard sent back is refunded along with the fee.
+        testing_env_with_promise_results(get_context(false), PromiseResult::Failed);
+        contract.on_shard_board_created(0, accounts(2).into(), U128(0), U128(10u128.pow(22)));
+        let receipts = near_sdk::serde_json::to_string(&get_created_receipts()).unwrap();
+        assert!(receipts.contains(&format!("\"deposit\":{}", 10u128.pow(22))));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit")]
+    fn test_create_board_for_without_storage_deposit() {
+        testing_env!(get_context(false));
+        let mut contract = Contract::new();
+        contract.create_board_for(accounts(2).into(), Board::new().field);
+    }
+}
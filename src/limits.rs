@@ -0,0 +1,67 @@
+//! Explosion guard: boards can be capped by population or generation, and freeze themselves
+//! once a cap is reached so nobody keeps paying to step them.
+
+use crate::events::emit_board_event;
+use crate::*;
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct LimitReachedData {
+    index: BoardIndex,
+    generation: u64,
+    population: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Freezes the board at `index` after the step that brings it to `max_population` live
+    /// cells or `max_generation`, None for no cap. A board frozen by its caps is unfrozen when
+    /// they are raised past its current state. Owner only.
+    pub fn set_board_limits(
+        &mut self,
+        index: BoardIndex,
+        max_population: Option<u32>,
+        max_generation: Option<u64>,
+    ) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        board.max_population = max_population;
+        board.max_generation = max_generation;
+        if board.frozen_by_limit && !board.limit_reached() {
+            board.frozen = false;
+            board.frozen_by_limit = false;
+            // Blocks spent frozen don't count towards auto-step catch-up.
+            board.current_block_height = env::block_index();
+        }
+        self.internal_save(index, &board);
+        board
+    }
+}
+
+impl BoardWithBlock {
+    pub fn limit_reached(&self) -> bool {
+        self.max_population.is_some_and(|max| self.board.population() >= max)
+            || self.max_generation.is_some_and(|max| self.generation >= max)
+    }
+}
+
+impl Contract {
+    /// Freezes a freshly stepped board that reached one of its caps.
+    pub(crate) fn internal_check_limits(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        if board.frozen || !board.limit_reached() {
+            return;
+        }
+        board.frozen = true;
+        board.frozen_by_limit = true;
+        emit_board_event(
+            "board_limit_reached",
+            board,
+            LimitReachedData {
+                index,
+                generation: board.generation,
+                population: board.board.population(),
+            },
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! NEP-177 style metadata of boards, generated on every call so galleries show the current
+//! state without an off-chain metadata server.
+
+use crate::render::export_svg;
+use crate::*;
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_sdk::base64;
+use near_sdk::serde_json::json;
+
+/// Rule of boards without a rule oracle, in B/S notation.
+pub const CONWAY_RULE: &str = "B3/S23";
+
+#[near_bindgen]
+impl Contract {
+    /// Title, description, an SVG data URI of the current generation and marketplace-style
+    /// attributes (in `extra`) of the board at `index`.
+    pub fn get_board_metadata(&self, index: BoardIndex) -> TokenMetadata {
+        let board = self.internal_get_board(index);
+        let title = match &board.handle {
+            Some(handle) => format!("{} (board #{})", handle, index),
+            None => format!("Board #{}", index),
+        };
+        let rule = match &board.rule_oracle {
+            Some(oracle) => format!("Oracle {}", oracle),
+            None => CONWAY_RULE.to_string(),
+        };
+        let svg = export_svg(&board.board);
+        let attributes = json!([
+            { "trait_type": "rule", "value": rule },
+            { "trait_type": "width", "value": board.board.width },
+            { "trait_type": "height", "value": board.board.height },
+            { "trait_type": "generation", "value": board.generation },
+            { "trait_type": "population", "value": board.board.population() },
+            { "trait_type": "period", "value": board.period },
+            { "trait_type": "finalized", "value": board.finalized_at.is_some() },
+        ]);
+        TokenMetadata {
+            title: Some(title),
+            description: Some(format!(
+                "A {}x{} Game of Life board by {}, at generation {}",
+                board.board.width, board.board.height, board.owner_id, board.generation
+            )),
+            media: Some(format!("data:image/svg+xml;base64,{}", base64::encode(&svg))),
+            media_hash: Some(Base64VecU8(env::sha256(svg.as_bytes()))),
+            copies: Some(1),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: Some(json!({ "attributes": attributes }).to_string()),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Upgrades the state of the first release, which stored bare 16x16 boards and nothing else.
+//! Every later field of `Contract` and `BoardWithBlock` starts out at its default.
+
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct BoardV0 {
+    pub field: Base64VecU8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct BoardWithBlockV0 {
+    pub board: BoardV0,
+    pub current_block_height: BlockHeight,
+    pub prev_block_height: BlockHeight,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct ContractV0 {
+    pub boards: Vector<BoardWithBlockV0>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Converts first-release state in place, called by the contract itself right after
+    /// deploying the new code. Boards of that release had no owner, so they go to `owner_id`,
+    /// who also becomes the contract owner. All boards are converted in this one call: a
+    /// deployment with more boards than that call's gas covers has to start from fresh state.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate(owner_id: AccountId) -> Self {
+        let old: ContractV0 = env::state_read().expect("No state to migrate");
+        let mut contract = Self::new();
+        contract.owner_id = owner_id;
+        // The boards keep their storage prefix, so each one is read before its slot is
+        // overwritten by the converted board.
+        for index in 0..old.boards.len() {
+            let old_board = old.boards.get(index).expect("Missing board");
+            let mut board = BoardWithBlock::new(Board::from(old_board.board.field), contract.owner_id.clone(), None);
+            board.current_block_height = old_board.current_block_height;
+            board.prev_block_height = old_board.prev_block_height;
+            contract.internal_add_board(&board);
+        }
+        contract
+    }
+}
@@ -0,0 +1,168 @@
+use crate::events::{emit_board_event, emit_event};
+use crate::fees::charge_storage;
+use crate::*;
+
+/// Longest accepted flag reason, in bytes.
+pub const MAX_FLAG_REASON_LEN: usize = 280;
+
+/// Most boards returned by one enumeration call.
+pub const MAX_PAGE_LEN: u64 = 100;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Flag {
+    pub account_id: AccountId,
+    pub reason: String,
+    pub block_height: BlockHeight,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct ModerationData<'a> {
+    moderator_id: &'a AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<BoardIndex>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_id: Option<&'a AccountId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Lets `account_id` hide and freeze boards. Contract owner only.
+    pub fn add_moderator(&mut self, account_id: AccountId) {
+        self.assert_contract_owner();
+        assert!(self.moderators.insert(&account_id), "Already a moderator");
+        self.internal_emit_moderation("moderator_added", &account_id);
+    }
+
+    pub fn remove_moderator(&mut self, account_id: AccountId) {
+        self.assert_contract_owner();
+        assert!(self.moderators.remove(&account_id), "Not a moderator");
+        self.internal_emit_moderation("moderator_removed", &account_id);
+    }
+
+    pub fn get_moderators(&self) -> Vec<AccountId> {
+        self.moderators.to_vec()
+    }
+
+    /// Reports the board at `index` for moderation. Once per account and board. The attached
+    /// deposit must cover the storage the flag takes; any surplus is refunded.
+    #[payable]
+    pub fn flag_board(&mut self, index: BoardIndex, reason: String) -> Flag {
+        self.internal_get_board(index);
+        assert!(reason.len() <= MAX_FLAG_REASON_LEN, "Reason is too long");
+        let account_id = env::predecessor_account_id();
+        let storage_before = env::storage_usage();
+        let mut flags = self.flags.get(&index).unwrap_or_default();
+        assert!(
+            !flags.iter().any(|flag| flag.account_id == account_id),
+            "Already flagged"
+        );
+        let flag = Flag {
+            account_id,
+            reason,
+            block_height: env::block_index(),
+        };
+        flags.push(flag.clone());
+        self.flags.insert(&index, &flags);
+        charge_storage(storage_before);
+        flag
+    }
+
+    pub fn get_flags(&self, index: BoardIndex) -> Vec<Flag> {
+        self.flags.get(&index).unwrap_or_default()
+    }
+
+    /// Excludes the board at `index` from `get_boards`. The board itself is kept and its owner
+    /// can still use it. Contract owner or moderators only.
+    pub fn hide_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        self.internal_set_hidden(index, true)
+    }
+
+    /// Lists the board at `index` in `get_boards` again. Contract owner or moderators only.
+    pub fn unhide_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        self.internal_set_hidden(index, false)
+    }
+
+    /// Stops the board at `index` from being stepped or edited until it is unfrozen.
+    /// Contract owner or moderators only.
+    pub fn freeze_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        self.assert_moderator();
+        let mut board = self.internal_get_board(index);
+        // An auto-stepping board catches up to the freeze, not past it.
+        self.internal_catch_up(index, &mut board);
+        board.frozen = true;
+        // Raising the board's limits doesn't lift a moderator's freeze.
+        board.frozen_by_limit = false;
+        self.internal_save(index, &board);
+        self.internal_emit_board_moderation("board_frozen", index, &board);
+        board
+    }
+
+    /// Blocks spent frozen don't count towards auto-step catch-up. Finalized boards can't be
+    /// unfrozen.
+    pub fn unfreeze_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        self.assert_moderator();
+        let mut board = self.internal_get_board(index);
+        assert_not_finalized(&board);
+        board.frozen = false;
+        board.frozen_by_limit = false;
+        board.current_block_height = env::block_index();
+        self.internal_save(index, &board);
+        self.internal_emit_board_moderation("board_unfrozen", index, &board);
+        board
+    }
+
+    /// Up to `limit` boards that are not hidden, starting at `from_index`, with their indices.
+    pub fn get_boards(&self, from_index: BoardIndex, limit: u64) -> Vec<(BoardIndex, BoardWithBlock)> {
+        (from_index..self.boards.len())
+            .filter_map(|index| self.boards.get(index).map(|board| (index, board)))
+            .filter(|(_, board)| !board.hidden)
+            .take(limit.min(MAX_PAGE_LEN) as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    /// The contract owner counts as a moderator.
+    pub(crate) fn assert_moderator(&self) {
+        let account_id = env::predecessor_account_id();
+        assert!(
+            account_id == self.owner_id || self.moderators.contains(&account_id),
+            "Only moderators can do this"
+        );
+    }
+
+    fn internal_set_hidden(&mut self, index: BoardIndex, hidden: bool) -> BoardWithBlock {
+        self.assert_moderator();
+        let mut board = self.internal_get_board(index);
+        board.hidden = hidden;
+        self.internal_save(index, &board);
+        let event = if hidden { "board_hidden" } else { "board_unhidden" };
+        self.internal_emit_board_moderation(event, index, &board);
+        board
+    }
+
+    fn internal_emit_moderation(&self, event: &str, account_id: &AccountId) {
+        emit_event(
+            event,
+            ModerationData {
+                moderator_id: &env::predecessor_account_id(),
+                index: None,
+                account_id: Some(account_id),
+            },
+        );
+    }
+
+    fn internal_emit_board_moderation(&self, event: &str, index: BoardIndex, board: &BoardWithBlock) {
+        emit_board_event(
+            event,
+            board,
+            ModerationData {
+                moderator_id: &env::predecessor_account_id(),
+                index: Some(index),
+                account_id: None,
+            },
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Finalized boards: permanently locked artworks, listed in the museum in the order they were
+//! finalized.
+
+use crate::events::emit_board_event;
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct FinalizedData<'a> {
+    index: BoardIndex,
+    owner_id: &'a AccountId,
+    generation: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Locks the board at `index` for good: unlike a freeze this can't be undone, by anyone.
+    /// Owner only.
+    pub fn finalize_board(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        // An auto-stepping board catches up to the finalization, not past it.
+        self.internal_catch_up(index, &mut board);
+        board.frozen = true;
+        board.finalized_at = Some(env::block_index());
+        self.internal_save(index, &board);
+        self.museum.push(&(index, env::block_index()));
+        emit_board_event(
+            "board_finalized",
+            &board,
+            FinalizedData {
+                index,
+                owner_id: &board.owner_id,
+                generation: board.generation,
+            },
+        );
+        board
+    }
+
+    /// Up to `limit` finalized boards that are not hidden, starting at position `from_index`
+    /// of the museum, with their indices. Entries of boards a snapshot restore replaced with
+    /// another state are left out.
+    pub fn get_museum(&self, from_index: u64, limit: u64) -> Vec<(BoardIndex, BoardWithBlock)> {
+        (from_index..self.museum.len())
+            .filter_map(|position| self.museum.get(position))
+            .map(|(index, finalized_at)| (index, finalized_at, self.internal_get_board(index)))
+            .filter(|(_, finalized_at, board)| !board.hidden && board.finalized_at == Some(*finalized_at))
+            .map(|(index, _, board)| (index, board))
+            .take(limit.min(MAX_PAGE_LEN) as usize)
+            .collect()
+    }
+
+    /// Number of museum entries, including the ones `get_museum` leaves out.
+    pub fn get_museum_size(&self) -> u64 {
+        self.museum.len()
+    }
+}
@@ -0,0 +1,157 @@
+//! Library of well-known patterns, each stored in one phase and orientation.
+
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PatternKind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+}
+
+pub struct Pattern {
+    pub name: &'static str,
+    pub kind: PatternKind,
+    /// Rows of `X` (alive) and `.` (dead).
+    pub rows: &'static [&'static str],
+}
+
+/// Steps simulated to collect the phases of a pattern.
+const MAX_PATTERN_PERIOD: usize = 16;
+
+pub const KNOWN_PATTERNS: &[Pattern] = &[
+    Pattern {
+        name: "glider",
+        kind: PatternKind::Spaceship,
+        rows: &[".X.", "..X", "XXX"],
+    },
+    Pattern {
+        name: "lightweight spaceship",
+        kind: PatternKind::Spaceship,
+        rows: &[".X..X", "X....", "X...X", "XXXX."],
+    },
+    Pattern {
+        name: "middleweight spaceship",
+        kind: PatternKind::Spaceship,
+        rows: &["...X..", ".X...X", "X.....", "X....X", "XXXXX."],
+    },
+    Pattern {
+        name: "heavyweight spaceship",
+        kind: PatternKind::Spaceship,
+        rows: &["...XX..", ".X....X", "X......", "X.....X", "XXXXXX."],
+    },
+    Pattern {
+        name: "block",
+        kind: PatternKind::StillLife,
+        rows: &["XX", "XX"],
+    },
+    Pattern {
+        name: "beehive",
+        kind: PatternKind::StillLife,
+        rows: &[".XX.", "X..X", ".XX."],
+    },
+    Pattern {
+        name: "loaf",
+        kind: PatternKind::StillLife,
+        rows: &[".XX.", "X..X", ".X.X", "..X."],
+    },
+    Pattern {
+        name: "boat",
+        kind: PatternKind::StillLife,
+        rows: &["XX.", "X.X", ".X."],
+    },
+    Pattern {
+        name: "ship",
+        kind: PatternKind::StillLife,
+        rows: &["XX.", "X.X", ".XX"],
+    },
+    Pattern {
+        name: "tub",
+        kind: PatternKind::StillLife,
+        rows: &[".X.", "X.X", ".X."],
+    },
+    Pattern {
+        name: "pond",
+        kind: PatternKind::StillLife,
+        rows: &[".XX.", "X..X", "X..X", ".XX."],
+    },
+    Pattern {
+        name: "blinker",
+        kind: PatternKind::Oscillator,
+        rows: &["XXX"],
+    },
+    Pattern {
+        name: "toad",
+        kind: PatternKind::Oscillator,
+        rows: &[".XXX", "XXX."],
+    },
+    Pattern {
+        name: "beacon",
+        kind: PatternKind::Oscillator,
+        rows: &["XX..", "XX..", "..XX", "..XX"],
+    },
+];
+
+impl Pattern {
+    pub fn to_board(&self) -> Board {
+        let mut board = Board::with_dimensions(self.rows[0].len(), self.rows.len());
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                if cell == 'X' {
+                    board.set_bit(x, y, true);
+                }
+            }
+        }
+        board
+    }
+
+    /// Whether `shape`, trimmed to its live cells, is this pattern in any orientation.
+    pub fn matches(&self, shape: &Board) -> bool {
+        same_shape(&self.to_board(), shape)
+    }
+
+    /// Every phase of the pattern trimmed to its live cells, starting with the stored one.
+    pub fn phases(&self) -> Vec<Board> {
+        let pattern = self.to_board();
+        // Padding keeps the pattern clear of the edges while it evolves.
+        let mut state = Board::with_dimensions(pattern.width() + 4, pattern.height() + 4);
+        for (x, y) in pattern.live_cells() {
+            state.set_bit(x + 2, y + 2, true);
+        }
+        let mut phases = vec![pattern];
+        for _ in 1..MAX_PATTERN_PERIOD {
+            state = state.next_generation();
+            match state.trimmed() {
+                Some((shape, _)) if shape != phases[0] => phases.push(shape),
+                _ => break,
+            }
+        }
+        phases
+    }
+}
+
+/// Whether two trimmed shapes are the same up to rotation and reflection.
+pub fn same_shape(pattern: &Board, shape: &Board) -> bool {
+    let fits = (pattern.width == shape.width && pattern.height == shape.height)
+        || (pattern.width == shape.height && pattern.height == shape.width);
+    fits && pattern.population() == shape.population()
+        && Symmetry::ALL.iter().any(|&symmetry| pattern.transformed(symmetry) == *shape)
+}
+
+/// The smallest of all orientations of all `phases` of a pattern, the same whichever phase
+/// and orientation it is found in.
+pub fn canonical_shape(phases: &[Board]) -> Board {
+    phases
+        .iter()
+        .flat_map(|phase| Symmetry::ALL.iter().map(move |&symmetry| phase.transformed(symmetry)))
+        .min_by(|a, b| (a.width, a.height, &a.field.0).cmp(&(b.width, b.height, &b.field.0)))
+        .expect("Patterns have a phase")
+}
+
+/// The known pattern of the given kind that `shape` is in any orientation.
+pub fn identify(shape: &Board, kind: PatternKind) -> Option<&'static Pattern> {
+    KNOWN_PATTERNS
+        .iter()
+        .find(|pattern| pattern.kind == kind && pattern.matches(shape))
+}
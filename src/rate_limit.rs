@@ -0,0 +1,56 @@
+use crate::*;
+
+/// At most `max_calls` mutating calls per account in every window of `window_blocks` blocks.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RateLimit {
+    pub max_calls: u32,
+    pub window_blocks: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets or (with None) lifts the limit on mutating calls per account. Contract owner only.
+    pub fn set_rate_limit(&mut self, rate_limit: Option<RateLimit>) {
+        self.assert_contract_owner();
+        if let Some(limit) = rate_limit {
+            assert!(limit.max_calls > 0 && limit.window_blocks > 0, "Rate limit must be positive");
+        }
+        self.rate_limit = rate_limit;
+    }
+
+    pub fn get_rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit
+    }
+}
+
+impl Contract {
+    /// Counts a mutating call of the predecessor against the rate limit, if one is set.
+    pub(crate) fn internal_check_rate_limit(&mut self) {
+        self.internal_check_rate_limit_for(&env::predecessor_account_id());
+    }
+
+    /// Counts a mutating call made by `account_id`, directly or through a router.
+    pub(crate) fn internal_check_rate_limit_for(&mut self, account_id: &AccountId) {
+        assert!(self.internal_try_rate_limit(account_id), "Rate limit exceeded, try again in a later block");
+    }
+
+    /// Like `internal_check_rate_limit_for`, but returns false instead of failing once the
+    /// limit is reached. Batches count every item as a call.
+    pub(crate) fn internal_try_rate_limit(&mut self, account_id: &AccountId) -> bool {
+        let limit = match self.rate_limit {
+            Some(limit) => limit,
+            None => return true,
+        };
+        let window = env::block_index() / limit.window_blocks;
+        let calls = match self.call_counts.get(account_id) {
+            Some((last_window, calls)) if last_window == window => calls,
+            _ => 0,
+        };
+        if calls >= limit.max_calls {
+            return false;
+        }
+        self.call_counts.insert(account_id, &(window, calls + 1));
+        true
+    }
+}
@@ -0,0 +1,181 @@
+use crate::*;
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_NEWBORN: &str = "\x1b[92m";
+const ANSI_SURVIVOR: &str = "\x1b[97m";
+const ANSI_DIED: &str = "\x1b[31m";
+const ANSI_DEAD: &str = "\x1b[90m";
+
+const MAX_IMAGE_SCALE: u32 = 16;
+/// Keeps exported images (and the gas to build them) small.
+const MAX_IMAGE_PIXELS: u64 = 1 << 20;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RenderMode {
+    /// Same rows as the debug logs: `X` alive, `.` dead.
+    Ascii,
+    /// Terminal colors: bright green newborn cells, white survivors, red cells that just died.
+    Ansi,
+    /// Fading trail: `X` alive, digits for how many more steps a dead cell's trail lasts, `.`
+    /// for the rest. Boards without a trail render as `Ascii`.
+    Trail,
+}
+
+/// Colors `board` by comparing it with the generation before it. Without a previous
+/// generation every live cell counts as a survivor.
+pub fn render_ansi(board: &Board, prev: Option<&Board>) -> Vec<String> {
+    let prev = prev.filter(|prev| prev.width == board.width && prev.height == board.height);
+    (0..board.height())
+        .map(|y| {
+            let mut line = String::new();
+            let mut color = "";
+            for x in 0..board.width() {
+                let alive = board.is_bit_set(x, y);
+                let was_alive = prev.map_or(alive, |prev| prev.is_bit_set(x, y));
+                let (cell_color, cell) = match (was_alive, alive) {
+                    (false, true) => (ANSI_NEWBORN, 'X'),
+                    (true, true) => (ANSI_SURVIVOR, 'X'),
+                    (true, false) => (ANSI_DIED, 'x'),
+                    (false, false) => (ANSI_DEAD, '.'),
+                };
+                if cell_color != color {
+                    line.push_str(cell_color);
+                    color = cell_color;
+                }
+                line.push(cell);
+            }
+            line.push_str(ANSI_RESET);
+            line
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ImageFormat {
+    /// Uncompressed 1-bit Windows bitmap.
+    Bmp,
+    /// Binary portable bitmap (P4).
+    Pbm,
+}
+
+/// Pixel row `y` of `board` scaled up `scale` times, packed MSB first with live cells as 1.
+fn packed_row(board: &Board, y: usize, scale: usize) -> Vec<u8> {
+    let width = board.width() * scale;
+    let mut row = vec![0u8; width.div_ceil(8)];
+    for x in 0..width {
+        if board.is_bit_set(x / scale, y / scale) {
+            row[x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    row
+}
+
+pub fn export_pbm(board: &Board, scale: usize) -> Vec<u8> {
+    let height = board.height() * scale;
+    let mut image = format!("P4\n{} {}\n", board.width() * scale, height).into_bytes();
+    for y in 0..height {
+        image.extend(packed_row(board, y, scale));
+    }
+    image
+}
+
+pub fn export_bmp(board: &Board, scale: usize) -> Vec<u8> {
+    const HEADERS_LEN: u32 = 14 + 40 + 8;
+    let width = (board.width() * scale) as u32;
+    let height = (board.height() * scale) as u32;
+    // Rows are padded to a multiple of 4 bytes.
+    let row_len = width.div_ceil(32) * 4;
+    let pixels_len = row_len * height;
+
+    let mut image = Vec::with_capacity((HEADERS_LEN + pixels_len) as usize);
+    // File header.
+    image.extend(b"BM");
+    image.extend(&(HEADERS_LEN + pixels_len).to_le_bytes());
+    image.extend(&0u32.to_le_bytes());
+    image.extend(&HEADERS_LEN.to_le_bytes());
+    // BITMAPINFOHEADER: positive height means rows are stored bottom-up.
+    image.extend(&40u32.to_le_bytes());
+    image.extend(&width.to_le_bytes());
+    image.extend(&height.to_le_bytes());
+    image.extend(&1u16.to_le_bytes());
+    image.extend(&1u16.to_le_bytes());
+    image.extend(&0u32.to_le_bytes());
+    image.extend(&pixels_len.to_le_bytes());
+    image.extend(&2835u32.to_le_bytes());
+    image.extend(&2835u32.to_le_bytes());
+    image.extend(&2u32.to_le_bytes());
+    image.extend(&0u32.to_le_bytes());
+    // Palette: 0 is white for dead cells, 1 is black for live ones.
+    image.extend(&[0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for y in (0..height as usize).rev() {
+        let mut row = packed_row(board, y, scale);
+        row.resize(row_len as usize, 0);
+        image.extend(row);
+    }
+    image
+}
+
+/// Black-on-white SVG with one unit square per cell, runs of live cells merged.
+pub fn export_svg(board: &Board) -> String {
+    let (width, height) = (board.width(), board.height());
+    let mut path = String::new();
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            if !board.is_bit_set(x, y) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && board.is_bit_set(x, y) {
+                x += 1;
+            }
+            path.push_str(&format!("M{} {}h{}v1h-{}z", start, y, x - start, x - start));
+        }
+    }
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#,
+            r##"<rect width="{w}" height="{h}" fill="#fff"/><path fill="#000" d="{d}"/></svg>"##
+        ),
+        w = width,
+        h = height,
+        d = path
+    )
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Rows of the board at `index` rendered in the given mode.
+    pub fn render_board(&self, index: BoardIndex, mode: RenderMode) -> Vec<String> {
+        let board = self.internal_get_board(index);
+        match mode {
+            RenderMode::Ascii => board.board.to_string(),
+            RenderMode::Ansi => render_ansi(&board.board, board.prev_board.as_ref()),
+            RenderMode::Trail if board.trail_length.is_some() => {
+                trail::render_trail(&board.board, &self.internal_trail_counters(index, &board))
+            }
+            RenderMode::Trail => board.board.to_string(),
+        }
+    }
+
+    /// Cells of the board at `index` as rows of booleans, `true` for live cells.
+    pub fn get_board_matrix(&self, index: BoardIndex) -> Vec<Vec<bool>> {
+        self.internal_get_board(index).board.to_matrix()
+    }
+
+    /// The board at `index` as a black-on-white raster image, each cell `scale` pixels wide.
+    pub fn export_image(&self, index: BoardIndex, format: ImageFormat, scale: u32) -> Base64VecU8 {
+        assert!((1..=MAX_IMAGE_SCALE).contains(&scale), "Scale must be between 1 and {}", MAX_IMAGE_SCALE);
+        let board = self.internal_get_board(index).board;
+        let pixels = board.width() as u64 * board.height() as u64 * (scale as u64).pow(2);
+        assert!(pixels <= MAX_IMAGE_PIXELS, "Image is too large");
+        let image = match format {
+            ImageFormat::Bmp => export_bmp(&board, scale as usize),
+            ImageFormat::Pbm => export_pbm(&board, scale as usize),
+        };
+        image.into()
+    }
+}
@@ -0,0 +1,55 @@
+//! Replays of battles and tournaments: every move is stored as a frame so spectators can
+//! replay a match after it ends. Battle frames are the placements, from which the run itself
+//! follows; tournament frames hold both fields at the start of every matchup.
+
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MatchId {
+    Battle(BattleId),
+    Tournament(TournamentId),
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ReplayFrame {
+    Placement {
+        player_id: AccountId,
+        x: u32,
+        y: u32,
+        block_height: BlockHeight,
+    },
+    Matchup {
+        round: u32,
+        matchup: Matchup,
+        field_a: Board,
+        /// None for a bye.
+        field_b: Option<Board>,
+    },
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Up to `limit` frames of match `match_id` starting at frame `from_index`, in order.
+    pub fn get_match_replay(&self, match_id: MatchId, from_index: u32, limit: u32) -> Vec<ReplayFrame> {
+        let len = self.replay_lengths.get(&match_id).unwrap_or(0);
+        let end = len.min(from_index.saturating_add(limit.min(MAX_PAGE_LEN as u32)));
+        (from_index..end)
+            .filter_map(|frame| self.replay_frames.get(&(match_id, frame)))
+            .collect()
+    }
+
+    pub fn get_match_replay_len(&self, match_id: MatchId) -> u32 {
+        self.replay_lengths.get(&match_id).unwrap_or(0)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_record_frame(&mut self, match_id: MatchId, frame: &ReplayFrame) {
+        let len = self.replay_lengths.get(&match_id).unwrap_or(0);
+        self.replay_frames.insert(&(match_id, len), frame);
+        self.replay_lengths.insert(&match_id, &(len + 1));
+    }
+}
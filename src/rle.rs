@@ -0,0 +1,109 @@
+//! Storage encoding of boards. On chain a field is kept either packed (one bit per cell) or
+//! as run lengths of alternating dead/live cells, whichever is shorter. JSON always uses the
+//! packed Base64 field.
+
+use crate::{field_len, Board};
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use std::io::{Error, ErrorKind, Read, Write};
+
+const PACKED: u8 = 0;
+const RUN_LENGTH: u8 = 1;
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Run lengths of the first `cells` bits of `field`, starting with a (possibly empty) dead run.
+pub fn encode_runs(field: &[u8], cells: usize) -> Vec<u8> {
+    let bit = |i: usize| (field[i / 8] >> (i & 7)) & 1 != 0;
+    let mut out = vec![];
+    let mut current = false;
+    let mut run = 0u64;
+    for i in 0..cells {
+        if bit(i) != current {
+            write_varint(&mut out, run);
+            current = !current;
+            run = 0;
+        }
+        run += 1;
+    }
+    write_varint(&mut out, run);
+    out
+}
+
+/// Inverse of `encode_runs`, returning the packed field.
+pub fn decode_runs(mut data: &[u8], cells: usize) -> Option<Vec<u8>> {
+    let mut field = vec![0u8; cells.div_ceil(8)];
+    let mut position = 0usize;
+    let mut alive = false;
+    while !data.is_empty() {
+        let run = read_varint(&mut data)? as usize;
+        let end = position.checked_add(run).filter(|&end| end <= cells)?;
+        if alive {
+            for i in position..end {
+                field[i / 8] |= 1 << (i & 7);
+            }
+        }
+        position = end;
+        alive = !alive;
+    }
+    if position == cells {
+        Some(field)
+    } else {
+        None
+    }
+}
+
+impl BorshSerialize for Board {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.width.serialize(writer)?;
+        self.height.serialize(writer)?;
+        let runs = encode_runs(&self.field.0, self.width() * self.height());
+        if runs.len() < self.field.0.len() {
+            RUN_LENGTH.serialize(writer)?;
+            runs.serialize(writer)
+        } else {
+            PACKED.serialize(writer)?;
+            self.field.0.serialize(writer)
+        }
+    }
+}
+
+impl BorshDeserialize for Board {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        let width = u32::deserialize(buf)?;
+        let height = u32::deserialize(buf)?;
+        let mut encoding = [0u8];
+        buf.read_exact(&mut encoding)?;
+        let data = Vec::<u8>::deserialize(buf)?;
+        let cells = width as usize * height as usize;
+        let field = match encoding[0] {
+            PACKED if data.len() == field_len(width as usize, height as usize) => data,
+            RUN_LENGTH => decode_runs(&data, cells)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid run-length field"))?,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Invalid board field")),
+        };
+        Ok(Board {
+            width,
+            height,
+            field: field.into(),
+        })
+    }
+}
@@ -0,0 +1,160 @@
+//! Rule oracles: contracts computing a board's generations in place of Conway's rules, one row
+//! per call. An oracle implements
+//!
+//! `evaluate_row(width: u32, above: Base64VecU8, row: Base64VecU8, below: Base64VecU8) -> Base64VecU8`
+//!
+//! where every row holds one byte per cell, 1 for alive and 0 for dead, and the rows beyond
+//! the top and bottom edges are all dead. The result is the row's next generation.
+
+use crate::fees::hold_fee;
+use crate::*;
+use near_sdk::{ext_contract, Gas, Promise, PromiseResult};
+
+/// Rows a board with an oracle may have; every row is a separate call.
+pub const MAX_ORACLE_ROWS: usize = 32;
+
+const GAS_FOR_EVALUATE_ROW: Gas = 5_000_000_000_000;
+const GAS_FOR_ORACLE_CALLBACK: Gas = 50_000_000_000_000;
+
+/// What `step_with_oracle` took from the account stepping the board, kept until the rows
+/// are in.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HeldStep {
+    pub account_id: AccountId,
+    pub fee: U128,
+    pub points: u64,
+}
+
+#[ext_contract(ext_rule_oracle)]
+pub trait RuleOracle {
+    fn evaluate_row(&self, width: u32, above: Base64VecU8, row: Base64VecU8, below: Base64VecU8) -> Base64VecU8;
+}
+
+#[ext_contract(ext_oracle_callbacks)]
+pub trait OracleCallbacks {
+    fn on_oracle_rows(&mut self, index: BoardIndex, change_seq: u64, held: HeldStep) -> Option<BoardWithBlock>;
+}
+
+impl Board {
+    /// Row `y` with one byte per cell; rows outside the board are dead.
+    fn row_cells(&self, y: i64) -> Vec<u8> {
+        if y < 0 || y >= self.height() as i64 {
+            return vec![0; self.width()];
+        }
+        (0..self.width()).map(|x| self.is_bit_set(x, y as usize) as u8).collect()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Lets `oracle` compute the generations of the board at `index`, None for Conway's rules
+    /// again. Such boards are only stepped by `step_with_oracle`. Owner only.
+    pub fn set_rule_oracle(&mut self, index: BoardIndex, oracle: Option<AccountId>) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        if let Some(oracle) = &oracle {
+            assert!(env::is_valid_account_id(oracle.as_bytes()), "Invalid oracle");
+            assert!(
+                board.board.height() <= MAX_ORACLE_ROWS,
+                "Boards with an oracle can have at most {} rows",
+                MAX_ORACLE_ROWS
+            );
+        }
+        board.rule_oracle = oracle;
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Asks the board's oracle for every row of the next generation and steps the board once
+    /// all rows are in. Pending layers are merged first. Costs the step fee and action points
+    /// like `step`; both are held until the rows are in and given back if the step fails.
+    #[payable]
+    pub fn step_with_oracle(&mut self, index: BoardIndex) -> Promise {
+        self.internal_check_rate_limit();
+        let account_id = env::predecessor_account_id();
+        let mut board = self.internal_get_board(index);
+        assert_not_frozen(&board);
+        let oracle = board.rule_oracle.clone().expect("The board has no rule oracle");
+        assert!(
+            board.board.height() <= MAX_ORACLE_ROWS,
+            "Boards with an oracle can have at most {} rows",
+            MAX_ORACLE_ROWS
+        );
+        let points = self.internal_action_point_cost(&account_id, &board, 1, 0);
+        self.internal_spend_action_points(&board, 1, 0);
+        let fee = hold_fee(&account_id, self.internal_step_fee(&account_id, &board));
+        self.internal_merge_layers(index, &mut board);
+        self.internal_save(index, &board);
+
+        let field = &board.board;
+        let rows = (0..field.height() as i64).map(|y| {
+            ext_rule_oracle::evaluate_row(
+                field.width,
+                Base64VecU8(field.row_cells(y - 1)),
+                Base64VecU8(field.row_cells(y)),
+                Base64VecU8(field.row_cells(y + 1)),
+                &oracle,
+                0,
+                GAS_FOR_EVALUATE_ROW,
+            )
+        });
+        rows.reduce(|all, row| all.and(row))
+            .expect("Boards have at least one row")
+            .then(ext_oracle_callbacks::on_oracle_rows(
+                index,
+                board.change_seq,
+                HeldStep { account_id, fee: U128(fee), points },
+                &env::current_account_id(),
+                0,
+                GAS_FOR_ORACLE_CALLBACK,
+            ))
+    }
+
+    /// Assembles the oracle's rows into the next generation and collects the held fee. If
+    /// any row is missing or malformed, or the board changed or froze in the meantime, the
+    /// board isn't stepped, the fee and action points go back, and this returns None.
+    #[private]
+    pub fn on_oracle_rows(&mut self, index: BoardIndex, change_seq: u64, held: HeldStep) -> Option<BoardWithBlock> {
+        let mut board = self.internal_get_board(index);
+        let next = if board.frozen || board.change_seq != change_seq {
+            None
+        } else {
+            oracle_generation(board.board.width(), board.board.height())
+        };
+        self.internal_settle_held_fee(&held.account_id, held.fee.0, next.is_some());
+        let next = match next {
+            Some(next) => next,
+            None => {
+                self.internal_refund_action_points(&held.account_id, held.points);
+                return None;
+            }
+        };
+        board.last_step = None;
+        self.internal_advance(index, &mut board, next);
+        self.internal_save(index, &board);
+        Some(board)
+    }
+}
+
+/// The generation the oracle's rows make up, None if any row is missing or malformed.
+fn oracle_generation(width: usize, height: usize) -> Option<Board> {
+    if env::promise_results_count() as usize != height {
+        return None;
+    }
+    let mut next = Board::with_dimensions(width, height);
+    for y in 0..height {
+        let row = match env::promise_result(y as u64) {
+            PromiseResult::Successful(bytes) => near_sdk::serde_json::from_slice::<Base64VecU8>(&bytes).ok()?,
+            _ => return None,
+        };
+        if row.0.len() != width {
+            return None;
+        }
+        for (x, &cell) in row.0.iter().enumerate() {
+            next.set_bit(x, y, cell != 0);
+        }
+    }
+    Some(next)
+}
@@ -0,0 +1,162 @@
+//! Tutorial puzzles: a scenario provides a starting board and a goal, the player adds a few
+//! cells and steps the board, and the contract records who reached each goal.
+
+use crate::events::emit_board_event;
+use crate::*;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ScenarioGoal {
+    /// Still alive after the scenario's last generation.
+    Survive,
+    /// A live cell inside the area at any generation up to the last one.
+    Reach { x: u32, y: u32, width: u32, height: u32 },
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Scenario {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Rows of `X` (alive) and `.` (dead), all of the board's width.
+    pub rows: &'static [&'static str],
+    /// Most cells the player may add to the starting board.
+    pub max_cells: u32,
+    /// The board freezes after this generation.
+    pub max_generations: u64,
+    pub goal: ScenarioGoal,
+}
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "blinker_survival",
+        description: "Add one cell so the pattern survives 10 generations",
+        rows: &["........", "........", "........", "...XX...", "........", "........", "........", "........"],
+        max_cells: 1,
+        max_generations: 10,
+        goal: ScenarioGoal::Survive,
+    },
+    Scenario {
+        name: "glider_corner",
+        description: "Build a glider that reaches the bottom-right corner within 48 generations",
+        rows: &[
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+            "............",
+        ],
+        max_cells: 5,
+        max_generations: 48,
+        goal: ScenarioGoal::Reach { x: 9, y: 9, width: 3, height: 3 },
+    },
+];
+
+impl Scenario {
+    fn start(&self) -> Board {
+        let mut board = Board::with_dimensions(self.rows[0].len(), self.rows.len());
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                board.set_bit(x, y, cell == 'X');
+            }
+        }
+        board
+    }
+
+    fn goal_reached(&self, board: &BoardWithBlock) -> bool {
+        if board.generation > self.max_generations {
+            return false;
+        }
+        match self.goal {
+            ScenarioGoal::Survive => board.generation == self.max_generations && board.board.population() > 0,
+            ScenarioGoal::Reach { x, y, width, height } => {
+                (y..y + height).any(|cy| (x..x + width).any(|cx| board.board.is_bit_set(cx as usize, cy as usize)))
+            }
+        }
+    }
+}
+
+fn get_scenario(name: &str) -> &'static Scenario {
+    SCENARIOS.iter().find(|scenario| scenario.name == name).expect("No scenario")
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct CompletedData<'a> {
+    account_id: &'a AccountId,
+    scenario: &'a str,
+    index: BoardIndex,
+    generation: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_scenarios(&self) -> Vec<Scenario> {
+        SCENARIOS.to_vec()
+    }
+
+    /// Creates a board owned by the caller with scenario `name`'s starting cells plus `cells`.
+    /// Stepping it to the scenario's goal completes the scenario.
+    #[payable]
+    pub fn start_scenario(&mut self, name: String, cells: Vec<(u32, u32)>) -> BoardIndex {
+        let scenario = get_scenario(&name);
+        assert!(cells.len() as u32 <= scenario.max_cells, "At most {} cells can be added", scenario.max_cells);
+        let mut field = scenario.start();
+        for (x, y) in cells {
+            assert!(x < field.width && y < field.height, "Cell is outside the board");
+            field.set_bit(x as usize, y as usize, true);
+        }
+        let mut board = BoardWithBlock::new(field, env::predecessor_account_id(), None);
+        board.scenario = Some(name);
+        board.max_generation = Some(scenario.max_generations);
+        self.internal_charge_creation_fee(1);
+        self.internal_add_board(&board)
+    }
+
+    /// Names of the scenarios `account_id` completed, in completion order.
+    pub fn get_completed_scenarios(&self, account_id: AccountId) -> Vec<String> {
+        self.scenario_completions.get(&account_id).unwrap_or_default()
+    }
+}
+
+impl Contract {
+    /// Completes the scenario of a freshly stepped board for its owner if the goal is reached.
+    pub(crate) fn internal_check_scenario(&mut self, index: BoardIndex, board: &BoardWithBlock) {
+        let name = match &board.scenario {
+            Some(name) => name,
+            None => return,
+        };
+        let scenario = get_scenario(name);
+        let mut completed = self.scenario_completions.get(&board.owner_id).unwrap_or_default();
+        if completed.contains(name) || !scenario.goal_reached(board) {
+            return;
+        }
+        // Owners can edit their boards, so only a field evolved from the start counts.
+        let mut replay = board.genesis.clone();
+        for _ in 0..board.generation {
+            replay = replay.next_generation();
+        }
+        if replay != board.board {
+            return;
+        }
+        completed.push(name.clone());
+        self.scenario_completions.insert(&board.owner_id, &completed);
+        emit_board_event(
+            "scenario_completed",
+            board,
+            CompletedData {
+                account_id: &board.owner_id,
+                scenario: name,
+                index,
+                generation: board.generation,
+            },
+        );
+    }
+}
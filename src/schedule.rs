@@ -0,0 +1,99 @@
+use crate::batch::{BatchResult, GasBudget};
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+use near_sdk::{Balance, Promise};
+
+pub type ScheduleId = u64;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ScheduledStep {
+    pub id: ScheduleId,
+    pub index: BoardIndex,
+    pub at_block: BlockHeight,
+    pub scheduler_id: AccountId,
+    /// Paid to whoever executes the step, refunded if the step fails.
+    pub tip: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Steps the board at `index` once as soon as block `at_block` is reached and someone calls
+    /// `execute_due_steps`. The attached deposit pays for the storage the schedule takes and
+    /// the rest is the tip for the caller. Owner only.
+    #[payable]
+    pub fn schedule_step(&mut self, index: BoardIndex, at_block: BlockHeight) -> ScheduledStep {
+        let board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert!(at_block > env::block_index(), "The block has already passed");
+        let storage_before = env::storage_usage();
+        let mut schedule = ScheduledStep {
+            id: self.next_schedule_id,
+            index,
+            at_block,
+            scheduler_id: env::predecessor_account_id(),
+            tip: U128(0),
+        };
+        self.next_schedule_id += 1;
+        self.schedules.insert(&(at_block, schedule.id), &schedule);
+
+        let cost = (env::storage_usage() - storage_before) as Balance * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= cost, "Requires attached deposit of at least {}", cost);
+        schedule.tip = U128(deposit - cost);
+        self.schedules.insert(&(at_block, schedule.id), &schedule);
+        schedule
+    }
+
+    /// Pending schedules due at `from_block` or later, earliest first.
+    pub fn get_scheduled_steps(&self, from_block: BlockHeight, limit: u64) -> Vec<ScheduledStep> {
+        self.schedules
+            .iter_from((from_block, 0))
+            .map(|(_, schedule)| schedule)
+            .take(limit.min(MAX_PAGE_LEN) as usize)
+            .collect()
+    }
+
+    /// Performs up to `limit` schedules whose block has arrived, earliest first, and pays the
+    /// caller their tips. Steps of missing or frozen boards, or of boards with a rule oracle,
+    /// fail and their tips are refunded. Schedules left when gas runs low are reported as
+    /// skipped and stay due for the next call. Anyone can call it, as one call against their
+    /// rate limit.
+    pub fn execute_due_steps(&mut self, limit: u64) -> BatchResult {
+        self.internal_check_rate_limit();
+        let now = env::block_index();
+        let mut result = BatchResult::default();
+        let mut budget = GasBudget::new();
+        let mut earned: Balance = 0;
+        while (result.processed.len() + result.failed.len()) < limit as usize {
+            let key = match self.schedules.min() {
+                Some(key) if key.0 <= now => key,
+                _ => break,
+            };
+            if !budget.has_room() {
+                result.skipped.push(key.1);
+                break;
+            }
+            let schedule = self.schedules.remove(&key).expect("Missing schedule");
+            match self.boards.get(schedule.index) {
+                Some(mut board) if step_blocker(&board).is_none() => {
+                    self.internal_catch_up(schedule.index, &mut board);
+                    self.internal_step(schedule.index, &mut board);
+                    self.internal_save(schedule.index, &board);
+                    earned += schedule.tip.0;
+                    result.processed.push(schedule.id);
+                }
+                _ => {
+                    if schedule.tip.0 > 0 {
+                        Promise::new(schedule.scheduler_id).transfer(schedule.tip.0);
+                    }
+                    result.failed.push(schedule.id);
+                }
+            }
+        }
+        if earned > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(earned);
+        }
+        result
+    }
+}
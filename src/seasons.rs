@@ -0,0 +1,101 @@
+//! Contract-level seasons: achievements earn points on the leaderboard of the season they
+//! were awarded in, so every new season starts with empty standings.
+
+use crate::*;
+
+/// Accounts kept on each season's leaderboard.
+pub const MAX_LEADERBOARD_LEN: usize = 100;
+
+pub type SeasonId = u32;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Season {
+    pub id: SeasonId,
+    pub name: String,
+    /// First block of the season.
+    pub start_block: BlockHeight,
+    /// First block after the season.
+    pub end_block: BlockHeight,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LeaderboardEntry {
+    pub account_id: AccountId,
+    pub points: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Schedules a season over blocks `start_block..end_block`. Seasons are added in order and
+    /// may not overlap. Contract owner only.
+    pub fn add_season(&mut self, name: String, start_block: BlockHeight, end_block: BlockHeight) -> Season {
+        self.assert_contract_owner();
+        assert!(start_block < end_block, "A season must end after it starts");
+        if let Some(last) = self.seasons.get(self.seasons.len().saturating_sub(1)) {
+            assert!(start_block >= last.end_block, "Seasons may not overlap");
+        }
+        let season = Season {
+            id: self.seasons.len() as SeasonId,
+            name,
+            start_block,
+            end_block,
+        };
+        self.seasons.push(&season);
+        season
+    }
+
+    pub fn get_seasons(&self) -> Vec<Season> {
+        self.seasons.to_vec()
+    }
+
+    /// The season running at the current block, if any.
+    pub fn get_current_season(&self) -> Option<Season> {
+        let now = env::block_index();
+        (0..self.seasons.len())
+            .rev()
+            .filter_map(|id| self.seasons.get(id))
+            .take_while(|season| season.end_block > now)
+            .find(|season| season.start_block <= now)
+    }
+
+    /// Highest scoring accounts of `season`, best first.
+    pub fn get_leaderboard(&self, season: SeasonId) -> Vec<LeaderboardEntry> {
+        self.leaderboards.get(&season).unwrap_or_default()
+    }
+
+    /// Achievements `account_id` was awarded during `season`.
+    pub fn get_season_achievements(&self, account_id: AccountId, season: SeasonId) -> Vec<Achievement> {
+        self.get_achievements(account_id)
+            .into_iter()
+            .filter(|achievement| achievement.season == Some(season))
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_contract_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only the contract owner can do this"
+        );
+    }
+
+    /// Adds `points` to the score of `account_id` in `season` and re-ranks its leaderboard.
+    pub(crate) fn internal_add_points(&mut self, season: SeasonId, account_id: &AccountId, points: u64) {
+        let mut leaderboard = self.get_leaderboard(season);
+        match leaderboard.iter_mut().find(|entry| entry.account_id == *account_id) {
+            Some(entry) => entry.points += points,
+            None => leaderboard.push(LeaderboardEntry {
+                account_id: account_id.clone(),
+                points,
+            }),
+        }
+        // Stable, so earlier scorers stay ahead on ties.
+        leaderboard.sort_by_key(|entry| std::cmp::Reverse(entry.points));
+        leaderboard.truncate(MAX_LEADERBOARD_LEN);
+        self.leaderboards.insert(&season, &leaderboard);
+    }
+}
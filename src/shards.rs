@@ -0,0 +1,304 @@
+//! Factory and router for shards: child accounts running this contract, each holding part of
+//! a large deployment's boards. The router owns every shard and creates and steps boards on
+//! them on behalf of users, passing on who the user is. It records every board created
+//! through it, so the whole deployment can be listed from the router.
+
+use crate::fees::hold_fee;
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+use near_sdk::{ext_contract, Balance, Gas, Promise, PromiseResult};
+
+/// Raw storage key of the wasm deployed to new shards.
+const SHARD_CODE_KEY: &[u8] = b"shard_code";
+
+const GAS_FOR_SHARD_INIT: Gas = 20_000_000_000_000;
+const GAS_FOR_SHARD_CALL: Gas = 50_000_000_000_000;
+const GAS_FOR_CALLBACK: Gas = 10_000_000_000_000;
+
+pub type ShardId = u32;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Shard {
+    pub account_id: AccountId,
+    /// Boards the router stops creating on the shard after.
+    pub capacity: u64,
+    /// Boards created through the router, counting creations still in flight.
+    pub board_count: u64,
+    /// Set once the shard's deployment succeeded.
+    pub active: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoutedBoard {
+    pub shard_id: ShardId,
+    pub account_id: AccountId,
+    pub index: BoardIndex,
+    pub owner_id: AccountId,
+}
+
+/// Where a board of the deployment is: on this contract, or on one of its shards.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BoardLocation {
+    /// Contract holding the board.
+    pub account_id: AccountId,
+    pub shard_id: Option<ShardId>,
+    pub index: BoardIndex,
+    pub owner_id: AccountId,
+}
+
+#[ext_contract(ext_shard)]
+pub trait ShardContract {
+    fn create_board_for(&mut self, owner_id: AccountId, field: Base64VecU8) -> BoardIndex;
+    fn step_for(&mut self, account_id: AccountId, index: BoardIndex) -> BoardWithBlock;
+    fn get_board(&self, index: BoardIndex) -> Option<BoardWithBlock>;
+}
+
+#[ext_contract(ext_self)]
+pub trait RouterCallbacks {
+    fn on_shard_deployed(&mut self, shard_id: ShardId, deposit: U128);
+    fn on_shard_board_created(
+        &mut self,
+        shard_id: ShardId,
+        owner_id: AccountId,
+        fee: U128,
+        storage_deposit: U128,
+    ) -> Option<RoutedBoard>;
+    fn on_routed_step(&mut self, account_id: AccountId, deposit: U128) -> Option<BoardWithBlock>;
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Stores the raw wasm passed as the call's input as the code of future shards.
+    /// Contract owner only.
+    pub fn set_shard_code(&mut self) {
+        self.assert_contract_owner();
+        let code = env::input().expect("Missing shard code");
+        assert!(!code.is_empty(), "Missing shard code");
+        env::storage_write(SHARD_CODE_KEY, &code);
+    }
+
+    /// Creates `<name>.<this account>`, funds it with the attached deposit and deploys the
+    /// shard code to it. A shard whose deployment failed keeps its id and can be deployed again
+    /// under the same name; the deposit of a failed deployment is refunded. Contract owner only.
+    #[payable]
+    pub fn deploy_shard(&mut self, name: String, capacity: u64) -> Promise {
+        self.assert_contract_owner();
+        assert!(capacity > 0, "Capacity must be positive");
+        let code = env::storage_read(SHARD_CODE_KEY).expect("No shard code, see set_shard_code");
+        let account_id = format!("{}.{}", name, env::current_account_id());
+        assert!(env::is_valid_account_id(account_id.as_bytes()), "Invalid shard name");
+        let shard = Shard {
+            account_id: account_id.clone(),
+            capacity,
+            board_count: 0,
+            active: false,
+        };
+        let existing = self.shards.iter().position(|shard| shard.account_id == account_id);
+        let shard_id = match existing {
+            Some(position) => {
+                assert!(!self.shards.get(position as u64).unwrap().active, "The shard is already deployed");
+                self.shards.replace(position as u64, &shard);
+                position as ShardId
+            }
+            None => {
+                self.shards.push(&shard);
+                self.shards.len() as ShardId - 1
+            }
+        };
+        let deposit = env::attached_deposit();
+        Promise::new(account_id)
+            .create_account()
+            .transfer(deposit)
+            .deploy_contract(code)
+            .function_call(b"new".to_vec(), b"{}".to_vec(), 0, GAS_FOR_SHARD_INIT)
+            .then(ext_self::on_shard_deployed(
+                shard_id,
+                U128(deposit),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_CALLBACK,
+            ))
+    }
+
+    /// Activates the shard, or refunds `deposit` to the contract owner if the deployment
+    /// failed. A failed retry leaves a shard deployed meanwhile active.
+    #[private]
+    pub fn on_shard_deployed(&mut self, shard_id: ShardId, deposit: U128) {
+        let mut shard = self.shards.get(shard_id as u64).expect("No shard");
+        if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            shard.active = true;
+            self.shards.replace(shard_id as u64, &shard);
+        } else if deposit.0 > 0 {
+            Promise::new(self.owner_id.clone()).transfer(deposit.0);
+        }
+    }
+
+    pub fn get_shards(&self) -> Vec<Shard> {
+        self.shards.to_vec()
+    }
+
+    /// Boards held here and on all shards.
+    pub fn get_total_boards(&self) -> u64 {
+        self.boards.len() + self.routed_boards.len()
+    }
+
+    /// Up to `limit` boards of the whole deployment starting at position `from_index`: the
+    /// boards held here in index order, then the boards created on shards in creation order.
+    pub fn get_board_locations(&self, from_index: u64, limit: u64) -> Vec<BoardLocation> {
+        let local = self.boards.len();
+        let end = self.get_total_boards().min(from_index.saturating_add(limit.min(MAX_PAGE_LEN)));
+        (from_index..end)
+            .map(|position| {
+                if position < local {
+                    BoardLocation {
+                        account_id: env::current_account_id(),
+                        shard_id: None,
+                        index: position,
+                        owner_id: self.internal_get_board(position).owner_id,
+                    }
+                } else {
+                    let routed = self.routed_boards.get(position - local).expect("Missing routed board");
+                    BoardLocation {
+                        account_id: routed.account_id,
+                        shard_id: Some(routed.shard_id),
+                        index: routed.index,
+                        owner_id: routed.owner_id,
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Creates a board owned by the caller on the first active shard with room left. The
+    /// board's slot on the shard is reserved until the shard answers, so concurrent calls
+    /// can't overfill it. The attached deposit covers the creation fee, which is held until
+    /// the shard created the board and refunded otherwise, and the rest goes to the shard to
+    /// pay for the board's storage, which the shard refunds any surplus of.
+    #[payable]
+    pub fn create_board_routed(&mut self, field: Base64VecU8) -> Promise {
+        let shard_id = self
+            .shards
+            .iter()
+            .position(|shard| shard.active && shard.board_count < shard.capacity)
+            .expect("No shard has room left") as ShardId;
+        let mut shard = self.shards.get(shard_id as u64).expect("No shard");
+        shard.board_count += 1;
+        self.shards.replace(shard_id as u64, &shard);
+        let fee = self.internal_creation_fee(1);
+        let deposit = env::attached_deposit();
+        assert!(deposit >= fee, "Requires attached deposit of at least {}", fee);
+        let storage_deposit = deposit - fee;
+        ext_shard::create_board_for(
+            env::predecessor_account_id(),
+            field,
+            &shard.account_id,
+            storage_deposit,
+            GAS_FOR_SHARD_CALL,
+        )
+        .then(ext_self::on_shard_board_created(
+            shard_id,
+            env::predecessor_account_id(),
+            U128(fee),
+            U128(storage_deposit),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_CALLBACK,
+        ))
+    }
+
+    /// Records the board the shard created, collecting `fee`. If the shard failed, `fee` and
+    /// the `storage_deposit` that came back from it are refunded to `owner_id`.
+    #[private]
+    pub fn on_shard_board_created(
+        &mut self,
+        shard_id: ShardId,
+        owner_id: AccountId,
+        fee: U128,
+        storage_deposit: U128,
+    ) -> Option<RoutedBoard> {
+        let index = match env::promise_result(0) {
+            PromiseResult::Successful(value) => near_sdk::serde_json::from_slice::<BoardIndex>(&value).ok(),
+            _ => None,
+        };
+        if index.is_some() {
+            self.internal_collect_fee(fee.0);
+        } else if fee.0 + storage_deposit.0 > 0 {
+            Promise::new(owner_id.clone()).transfer(fee.0 + storage_deposit.0);
+        }
+        let mut shard = self.shards.get(shard_id as u64).expect("No shard");
+        let index = match index {
+            Some(index) => index,
+            None => {
+                // Releases the slot reserved by `create_board_routed`.
+                shard.board_count -= 1;
+                self.shards.replace(shard_id as u64, &shard);
+                return None;
+            }
+        };
+        let routed = RoutedBoard {
+            shard_id,
+            account_id: shard.account_id,
+            index,
+            owner_id,
+        };
+        self.routed_boards.push(&routed);
+        Some(routed)
+    }
+
+    /// Steps board `index` of shard `shard_id` for the caller, who is held to the shard's rate
+    /// limit and action points and pays its step fee out of the attached deposit. The shard
+    /// refunds any surplus; the whole deposit is refunded if the step fails.
+    #[payable]
+    pub fn step_routed(&mut self, shard_id: ShardId, index: BoardIndex) -> Promise {
+        let shard = self.shards.get(shard_id as u64).expect("No shard");
+        let account_id = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        ext_shard::step_for(account_id.clone(), index, &shard.account_id, deposit, GAS_FOR_SHARD_CALL).then(
+            ext_self::on_routed_step(account_id, U128(deposit), &env::current_account_id(), 0, GAS_FOR_CALLBACK),
+        )
+    }
+
+    #[private]
+    pub fn on_routed_step(&mut self, account_id: AccountId, deposit: U128) -> Option<BoardWithBlock> {
+        match env::promise_result(0) {
+            PromiseResult::Successful(value) => near_sdk::serde_json::from_slice(&value).ok(),
+            _ => {
+                if deposit.0 > 0 {
+                    Promise::new(account_id).transfer(deposit.0);
+                }
+                None
+            }
+        }
+    }
+
+    /// Fetches board `index` of shard `shard_id`. A call rather than a view, as views can't
+    /// reach other contracts; frontends can also query the shard directly.
+    pub fn get_board_routed(&mut self, shard_id: ShardId, index: BoardIndex) -> Promise {
+        let shard = self.shards.get(shard_id as u64).expect("No shard");
+        ext_shard::get_board(index, &shard.account_id, 0, GAS_FOR_SHARD_CALL)
+    }
+
+    /// Creates a board owned by `owner_id`. Called by the router that deployed this shard,
+    /// which is its contract owner. The attached deposit must cover the board's storage; any
+    /// surplus is refunded to `owner_id`.
+    #[payable]
+    pub fn create_board_for(&mut self, owner_id: AccountId, field: Base64VecU8) -> BoardIndex {
+        self.assert_contract_owner();
+        let storage_before = env::storage_usage();
+        let board = BoardWithBlock::new(Board::from(field), owner_id.clone(), None);
+        let index = self.internal_add_board(&board);
+        hold_fee(&owner_id, (env::storage_usage() - storage_before) as Balance * env::storage_byte_cost());
+        index
+    }
+
+    /// Steps the board at `index` as `account_id`, who called `step_routed` on the router that
+    /// deployed this shard. Only the router, which is the shard's contract owner, can call it.
+    #[payable]
+    pub fn step_for(&mut self, account_id: AccountId, index: BoardIndex) -> BoardWithBlock {
+        self.assert_contract_owner();
+        self.internal_step_for(&account_id, index)
+    }
+}
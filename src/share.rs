@@ -0,0 +1,115 @@
+//! Share payloads: a board's dimensions, rule, generation and cells in one URL-safe string, so
+//! boards can be passed around in links and recreated on any deployment.
+//!
+//! Version 1 is base64url without padding of: the version byte, varint width, height and
+//! generation, the rule (0 for B3/S23, or 1 followed by the oracle's varint length and
+//! account id) and the cells, either 0 followed by the packed field or 1 followed by the
+//! run lengths of `rle::encode_runs`, whichever is shorter.
+
+use crate::rle::{decode_runs, encode_runs, read_varint, write_varint};
+use crate::*;
+use near_sdk::base64;
+
+pub const SHARE_PAYLOAD_VERSION: u8 = 1;
+
+const RULE_CONWAY: u8 = 0;
+const RULE_ORACLE: u8 = 1;
+const FIELD_PACKED: u8 = 0;
+const FIELD_RUN_LENGTH: u8 = 1;
+
+/// A board as decoded from a share payload.
+#[derive(Debug, PartialEq)]
+pub struct SharedBoard {
+    pub board: Board,
+    pub generation: u64,
+    pub rule_oracle: Option<AccountId>,
+}
+
+pub fn encode_share_payload(board: &BoardWithBlock) -> String {
+    let field = &board.board;
+    let mut out = vec![SHARE_PAYLOAD_VERSION];
+    write_varint(&mut out, field.width as u64);
+    write_varint(&mut out, field.height as u64);
+    write_varint(&mut out, board.generation);
+    match &board.rule_oracle {
+        None => out.push(RULE_CONWAY),
+        Some(oracle) => {
+            out.push(RULE_ORACLE);
+            write_varint(&mut out, oracle.len() as u64);
+            out.extend(oracle.as_bytes());
+        }
+    }
+    let runs = encode_runs(&field.field.0, field.width() * field.height());
+    if runs.len() < field.field.0.len() {
+        out.push(FIELD_RUN_LENGTH);
+        out.extend(runs);
+    } else {
+        out.push(FIELD_PACKED);
+        out.extend(&field.field.0);
+    }
+    base64::encode_config(out, base64::URL_SAFE_NO_PAD)
+}
+
+/// None for a malformed payload or one of another version.
+pub fn decode_share_payload(payload: &str) -> Option<SharedBoard> {
+    let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let (&version, mut data) = bytes.split_first()?;
+    if version != SHARE_PAYLOAD_VERSION {
+        return None;
+    }
+    let width = read_varint(&mut data)? as usize;
+    let height = read_varint(&mut data)? as usize;
+    if width == 0 || height == 0 || width > MAX_WIDTH || height > MAX_HEIGHT {
+        return None;
+    }
+    let generation = read_varint(&mut data)?;
+    let (&rule, mut data) = data.split_first()?;
+    let rule_oracle = match rule {
+        RULE_CONWAY => None,
+        RULE_ORACLE => {
+            let len = read_varint(&mut data)? as usize;
+            if len > data.len() {
+                return None;
+            }
+            let (oracle, rest) = data.split_at(len);
+            data = rest;
+            Some(String::from_utf8(oracle.to_vec()).ok()?)
+        }
+        _ => return None,
+    };
+    let (&encoding, data) = data.split_first()?;
+    let field = match encoding {
+        FIELD_PACKED if data.len() == field_len(width, height) => data.to_vec(),
+        FIELD_RUN_LENGTH => decode_runs(data, width * height)?,
+        _ => return None,
+    };
+    Some(SharedBoard {
+        board: Board::from_field(width, height, field.into()),
+        generation,
+        rule_oracle,
+    })
+}
+
+#[near_bindgen]
+impl Contract {
+    /// The board at `index` as a share payload, see `create_board_from_payload`.
+    pub fn get_share_payload(&self, index: BoardIndex) -> String {
+        encode_share_payload(&self.internal_get_board(index))
+    }
+
+    /// Creates a board owned by the caller from a share payload. It starts at generation 0,
+    /// like imported boards, and remembers the generation it was shared at. Requires the
+    /// creation fee; any surplus is refunded.
+    #[payable]
+    pub fn create_board_from_payload(&mut self, payload: String) -> BoardIndex {
+        self.internal_charge_creation_fee(1);
+        let shared = decode_share_payload(&payload).expect("Invalid share payload");
+        let mut board = BoardWithBlock::new(shared.board, env::predecessor_account_id(), None);
+        board.shared_generation = Some(shared.generation);
+        let index = self.internal_add_board(&board);
+        if shared.rule_oracle.is_some() {
+            self.set_rule_oracle(index, shared.rule_oracle);
+        }
+        index
+    }
+}
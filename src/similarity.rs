@@ -0,0 +1,103 @@
+//! Locality-sensitive fingerprints of boards: how densely each quadrant is populated, plus
+//! hashes of a grid of tiles. Boards that look alike have close fingerprints, so galleries
+//! can surface them and contests can reject near-duplicates.
+
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+
+/// Tiles per row and column of the hashed grid.
+pub const FINGERPRINT_GRID: usize = 4;
+/// Distance a tile that differs adds, on top of the quadrant density differences.
+pub const TILE_MISMATCH_DISTANCE: u32 = 32;
+/// Most recent boards `find_similar_boards` compares against, to bound its gas.
+pub const MAX_SIMILARITY_SCAN: u64 = 1000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Fingerprint {
+    /// Live share of the top left, top right, bottom left and bottom right quadrants, 0 to 255.
+    pub quadrant_densities: [u8; 4],
+    /// Truncated hashes of the `FINGERPRINT_GRID` x `FINGERPRINT_GRID` tiles, row by row.
+    pub tile_hashes: [u32; FINGERPRINT_GRID * FINGERPRINT_GRID],
+}
+
+/// Live share of the cells in `x0..x1` x `y0..y1`, scaled to 0 to 255.
+fn density(board: &Board, (x0, x1): (usize, usize), (y0, y1): (usize, usize)) -> u8 {
+    let cells = (x1 - x0) * (y1 - y0);
+    if cells == 0 {
+        return 0;
+    }
+    let live = (y0..y1)
+        .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+        .filter(|&(x, y)| board.is_bit_set(x, y))
+        .count();
+    (live * 255 / cells) as u8
+}
+
+impl Fingerprint {
+    pub fn new(board: &Board) -> Self {
+        let (width, height) = (board.width(), board.height());
+        let halves = |len: usize| [(0, len / 2), (len / 2, len)];
+        let mut quadrant_densities = [0; 4];
+        for (row, rows) in halves(height).into_iter().enumerate() {
+            for (column, columns) in halves(width).into_iter().enumerate() {
+                quadrant_densities[row * 2 + column] = density(board, columns, rows);
+            }
+        }
+        let mut tile_hashes = [0; FINGERPRINT_GRID * FINGERPRINT_GRID];
+        for row in 0..FINGERPRINT_GRID {
+            let (y0, y1) = (height * row / FINGERPRINT_GRID, height * (row + 1) / FINGERPRINT_GRID);
+            for column in 0..FINGERPRINT_GRID {
+                let (x0, x1) = (
+                    width * column / FINGERPRINT_GRID,
+                    width * (column + 1) / FINGERPRINT_GRID,
+                );
+                if x1 > x0 && y1 > y0 {
+                    tile_hashes[row * FINGERPRINT_GRID + column] =
+                        board.crop(x0, y0, x1 - x0, y1 - y0).short_hash() as u32;
+                }
+            }
+        }
+        Self {
+            quadrant_densities,
+            tile_hashes,
+        }
+    }
+
+    /// 0 for boards with the same fingerprint, larger the more they differ.
+    pub fn distance(&self, other: &Fingerprint) -> u32 {
+        let densities: u32 = self
+            .quadrant_densities
+            .iter()
+            .zip(&other.quadrant_densities)
+            .map(|(a, b)| a.abs_diff(*b) as u32)
+            .sum();
+        let tiles = self
+            .tile_hashes
+            .iter()
+            .zip(&other.tile_hashes)
+            .filter(|(a, b)| a != b)
+            .count() as u32;
+        densities + tiles * TILE_MISMATCH_DISTANCE
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Up to `limit` boards that are not hidden and look most like the board at `index`, with
+    /// their fingerprint distance, closest first. Compares against the `MAX_SIMILARITY_SCAN`
+    /// most recent boards.
+    pub fn find_similar_boards(&self, index: BoardIndex, limit: u64) -> Vec<(BoardIndex, u32)> {
+        let fingerprint = self.board_headers.get(index).expect("No board").fingerprint;
+        let total = self.board_headers.len();
+        let mut similar: Vec<(BoardIndex, u32)> = (total.saturating_sub(MAX_SIMILARITY_SCAN)..total)
+            .filter(|&other| other != index)
+            .filter_map(|other| self.board_headers.get(other))
+            .filter(|header| !header.hidden)
+            .map(|header| (header.index, fingerprint.distance(&header.fingerprint)))
+            .collect();
+        similar.sort_by_key(|&(other, distance)| (distance, other));
+        similar.truncate(limit.min(MAX_PAGE_LEN) as usize);
+        similar
+    }
+}
@@ -0,0 +1,76 @@
+//! Test-only scenario runner: drives a contract on the mocked blockchain through many blocks
+//! of creating and stepping boards, measuring the gas of every call and checking that board
+//! state stays consistent.
+
+use crate::*;
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{testing_env, Gas, MockedBlockchain};
+
+/// Most gas one step of a board up to 32x32 may take. The mocked chain only charges host
+/// calls (storage, logs, hashing), not wasm execution, so this guards against storage and
+/// logging regressions rather than slower engines; the sandbox suite in `sandbox/` measures
+/// the deployed wasm.
+pub const STEP_GAS_CEILING: Gas = 5_000_000_000_000;
+
+pub struct ScenarioRunner {
+    pub contract: Contract,
+    pub block: BlockHeight,
+    /// Most gas a single step took so far.
+    pub max_step_gas: Gas,
+}
+
+impl ScenarioRunner {
+    pub fn new() -> Self {
+        testing_env!(VMContextBuilder::new().build());
+        Self {
+            contract: Contract::new(),
+            block: 0,
+            max_step_gas: 0,
+        }
+    }
+
+    /// Starts a new block; the mocked chain also resets its logs and gas counter here.
+    pub fn next_block(&mut self) {
+        self.block += 1;
+        testing_env!(VMContextBuilder::new().block_index(self.block).build());
+    }
+
+    pub fn create(&mut self, board: Board) -> BoardIndex {
+        let board = BoardWithBlock::new(board, env::predecessor_account_id(), None);
+        self.contract.internal_add_board(&board)
+    }
+
+    /// Steps every board `rounds` times, each step in a block of its own, checking all boards
+    /// after every round.
+    pub fn run(&mut self, rounds: u64) {
+        for _ in 0..rounds {
+            for index in 0..self.contract.boards.len() {
+                self.next_block();
+                let before = self.contract.internal_get_board(index);
+                let start = env::used_gas();
+                let after = self.contract.step(index);
+                let gas = env::used_gas() - start;
+                assert!(gas <= STEP_GAS_CEILING, "stepping board {} took {} gas", index, gas);
+                self.max_step_gas = self.max_step_gas.max(gas);
+                assert_eq!(after.generation, before.generation + 1, "board {} skipped a generation", index);
+                assert!(after.current_block_height >= before.current_block_height);
+            }
+            self.check_all();
+        }
+    }
+
+    pub fn check_all(&self) {
+        for index in 0..self.contract.boards.len() {
+            let board = self.contract.internal_get_board(index);
+            let field = &board.board;
+            assert_eq!(
+                field.field.0.len(),
+                field_len(field.width(), field.height()),
+                "board {} has a field of the wrong length",
+                index
+            );
+            assert!(board.prev_block_height <= board.current_block_height);
+            assert!(board.recent_hashes.len() as u64 <= board.max_period + 1);
+        }
+    }
+}
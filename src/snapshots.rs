@@ -0,0 +1,128 @@
+//! Backup and restore of board records through the contract API. Only the boards themselves
+//! are included. The handle, tile and museum entries are rebuilt from the board, heatmaps and
+//! trails start over from the restored field, and pending layers and history are dropped. A
+//! replaced board's change log goes on with one change to the restored field.
+
+use crate::moderation::MAX_PAGE_LEN;
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct BoardSnapshot {
+    pub index: BoardIndex,
+    pub board: BoardWithBlock,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Borsh-packed snapshots of up to `limit` boards starting at `from_index`. A call so it
+    /// can be limited to the contract owner; the same state is readable from storage anyway.
+    pub fn export_all_boards(&mut self, from_index: BoardIndex, limit: u64) -> Vec<Base64VecU8> {
+        self.assert_contract_owner();
+        let end = self.boards.len().min(from_index.saturating_add(limit.min(MAX_PAGE_LEN)));
+        (from_index..end)
+            .map(|index| {
+                let snapshot = BoardSnapshot {
+                    index,
+                    board: self.internal_get_board(index),
+                };
+                Base64VecU8(snapshot.try_to_vec().expect("Snapshot serializes"))
+            })
+            .collect()
+    }
+
+    /// Restores snapshots made by `export_all_boards`, replacing boards with the same index.
+    /// New boards have to be restored in index order. A handle or tile already used by another
+    /// board here is dropped from the restored board, and museum entries of a replaced board
+    /// stop matching unless the restored board was finalized at the same block. Contract owner
+    /// only.
+    pub fn import_snapshot(&mut self, snapshots: Vec<Base64VecU8>) -> u64 {
+        self.assert_contract_owner();
+        for snapshot in &snapshots {
+            let BoardSnapshot { index, mut board } =
+                BoardSnapshot::try_from_slice(&snapshot.0).expect("Invalid snapshot");
+            // The snapshot's checkpoints aren't stored here; history restarts from the
+            // restored field.
+            board.checkpoints.clear();
+            // Neither are pending layers.
+            board.layer_owners.clear();
+            // Comments and follows live in maps of their own, so a replaced board keeps the
+            // ones made here and a new board starts without any.
+            let mut old = self.boards.get(index);
+            board.comment_count = old.as_ref().map_or(0, |old| old.comment_count);
+            board.followers = old.as_ref().map_or(0, |old| old.followers);
+            let old_finalized_at = old.as_ref().and_then(|old| old.finalized_at);
+            if let Some(old) = old.as_mut() {
+                self.internal_release_board_entries(index, old);
+                self.internal_truncate_history(index, old, 0);
+            }
+            self.internal_restore_board_entries(index, &mut board);
+            if let Some(finalized_at) = board.finalized_at.filter(|&at| Some(at) != old_finalized_at) {
+                self.museum.push(&(index, finalized_at));
+            }
+            if let Some(old) = old {
+                // The change log goes on from the replaced board, so clients following it see
+                // the restore as one more change.
+                board.change_seq = old.change_seq;
+                self.internal_record_edit(index, &mut board);
+                self.internal_record_change(index, &mut board, &old.board);
+                self.internal_save(index, &board);
+            } else {
+                assert_eq!(index, self.boards.len(), "Snapshots must be restored in index order");
+                self.internal_add_board(&board);
+            }
+            self.internal_restart_tracking(index, &board);
+        }
+        snapshots.len() as u64
+    }
+}
+
+impl Contract {
+    /// Frees the handle, tile and pending layers of a board about to be replaced.
+    fn internal_release_board_entries(&mut self, index: BoardIndex, old: &BoardWithBlock) {
+        for account_id in &old.layer_owners {
+            self.layers.remove(&(index, account_id.clone()));
+        }
+        if let Some(handle) = &old.handle {
+            if self.handles.get(handle) == Some(index) {
+                self.handles.remove(handle);
+            }
+        }
+        if let Some(tile) = &old.tile {
+            if self.tiles.get(tile) == Some(index) {
+                self.tiles.remove(tile);
+            }
+        }
+    }
+
+    /// Starts the heatmap and trail of a restored board over from its field.
+    fn internal_restart_tracking(&mut self, index: BoardIndex, board: &BoardWithBlock) {
+        self.heatmaps.remove(&index);
+        if board.track_heatmap {
+            self.internal_update_heatmap(index, &board.board);
+        }
+        self.trails.remove(&index);
+        if board.trail_length.is_some() {
+            self.internal_update_trail(index, board);
+        }
+    }
+
+    /// Claims the restored board's handle and tile, or drops them if they are taken.
+    fn internal_restore_board_entries(&mut self, index: BoardIndex, board: &mut BoardWithBlock) {
+        if let Some(handle) = &board.handle {
+            match self.handles.get(handle) {
+                Some(other) if other != index => board.handle = None,
+                _ => {
+                    self.handles.insert(handle, &index);
+                }
+            }
+        }
+        if let Some(tile) = &board.tile {
+            match self.tiles.get(tile) {
+                Some(other) if other != index => board.tile = None,
+                _ => {
+                    self.tiles.insert(tile, &index);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Named recipes that generate a starting field on-chain.
+
+use crate::*;
+
+/// Share of live cells in a soup when the parameters don't say, in basis points.
+pub const DEFAULT_SOUP_DENSITY_BPS: u32 = 3750;
+pub const DEFAULT_RING_BLINKERS: u32 = 8;
+/// Blinkers closer than this (in either axis) would interact.
+const MIN_BLINKER_DISTANCE: i64 = 5;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TemplateKind {
+    /// Random cells mirrored across both axes.
+    SymmetricSoup,
+    /// Blinkers evenly spaced on a circle around the center.
+    BlinkerRing,
+    /// Text centered on the board.
+    TextBanner,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Template {
+    pub name: String,
+    pub kind: TemplateKind,
+    pub width: u32,
+    pub height: u32,
+    pub description: String,
+}
+
+/// Parameters of `create_from_template`; each template kind reads the ones it needs.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(crate = "near_sdk::serde", default)]
+pub struct TemplateParams {
+    /// Mixed into the block's random seed by soups.
+    pub seed: Option<u64>,
+    pub density_bps: Option<u32>,
+    /// Number of blinkers in a ring.
+    pub count: Option<u32>,
+    pub text: Option<String>,
+}
+
+impl Template {
+    pub fn generate(&self, params: &TemplateParams) -> Board {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut board = Board::with_dimensions(width, height);
+        match self.kind {
+            TemplateKind::SymmetricSoup => {
+                let density = params.density_bps.unwrap_or(DEFAULT_SOUP_DENSITY_BPS);
+                assert!(density <= 10_000, "Density can't exceed 10000 basis points");
+                let mut seed = env::random_seed();
+                seed.extend(&params.seed.unwrap_or(0).to_le_bytes());
+                let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+                let random = random_bytes(&seed, half_width * half_height * 2);
+                for y in 0..half_height {
+                    for x in 0..half_width {
+                        let i = (y * half_width + x) * 2;
+                        let roll = u16::from_le_bytes([random[i], random[i + 1]]) as u32 % 10_000;
+                        if roll < density {
+                            for (mx, my) in [(x, y), (width - 1 - x, y), (x, height - 1 - y), (width - 1 - x, height - 1 - y)] {
+                                board.set_bit(mx, my, true);
+                            }
+                        }
+                    }
+                }
+            }
+            TemplateKind::BlinkerRing => {
+                let count = params.count.unwrap_or(DEFAULT_RING_BLINKERS);
+                assert!(count > 0, "A ring needs at least one blinker");
+                let center_x = (width as f64 - 1.0) / 2.0;
+                let center_y = (height as f64 - 1.0) / 2.0;
+                let radius = (width.min(height) as f64 - 1.0) / 2.0 - 2.0;
+                assert!(radius >= 0.0, "The board is too small for a ring");
+                let centers: Vec<(i64, i64)> = (0..count)
+                    .map(|k| {
+                        let angle = 2.0 * std::f64::consts::PI * k as f64 / count as f64;
+                        (
+                            (center_x + radius * angle.cos()).round() as i64,
+                            (center_y + radius * angle.sin()).round() as i64,
+                        )
+                    })
+                    .collect();
+                for (i, a) in centers.iter().enumerate() {
+                    for b in &centers[i + 1..] {
+                        let distance = (a.0 - b.0).abs().max((a.1 - b.1).abs());
+                        assert!(distance >= MIN_BLINKER_DISTANCE, "Too many blinkers for the board size");
+                    }
+                }
+                for (x, y) in centers {
+                    for dx in -1..=1 {
+                        board.set_bit((x + dx) as usize, y as usize, true);
+                    }
+                }
+            }
+            TemplateKind::TextBanner => {
+                let text = params.text.as_deref().expect("Missing text");
+                let (text_width, text_height) = font::text_size(text);
+                board.draw_text(
+                    text,
+                    width.saturating_sub(text_width) / 2,
+                    height.saturating_sub(text_height) / 2,
+                );
+            }
+        }
+        board
+    }
+}
+
+/// `len` pseudo-random bytes expanded from `seed` with sha256.
+pub(crate) fn random_bytes(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len + 32);
+    let mut counter: u32 = 0;
+    while bytes.len() < len {
+        let mut block = seed.to_vec();
+        block.extend(&counter.to_le_bytes());
+        bytes.extend(env::sha256(&block));
+        counter += 1;
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Makes `name` available to `create_from_template`. Contract owner only.
+    pub fn register_template(
+        &mut self,
+        name: String,
+        kind: TemplateKind,
+        width: u32,
+        height: u32,
+        description: String,
+    ) -> Template {
+        self.assert_contract_owner();
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert!(
+            width as usize <= MAX_WIDTH && height as usize <= MAX_HEIGHT,
+            "Board dimensions are too large"
+        );
+        let template = Template {
+            name: name.clone(),
+            kind,
+            width,
+            height,
+            description,
+        };
+        self.templates.insert(&name, &template);
+        template
+    }
+
+    pub fn get_templates(&self) -> Vec<Template> {
+        self.templates.values().collect()
+    }
+
+    /// Creates a board owned by the caller with a field generated by template `name`. Requires
+    /// the creation fee.
+    #[payable]
+    pub fn create_from_template(&mut self, name: String, params: TemplateParams) -> BoardIndex {
+        self.internal_charge_creation_fee(1);
+        let template = self.templates.get(&name).expect("No template");
+        let board = template.generate(&params);
+        let board_with_blocks = BoardWithBlock::new(board, env::predecessor_account_id(), None);
+        self.internal_add_board(&board_with_blocks)
+    }
+}
@@ -0,0 +1,454 @@
+//! Single-elimination tournaments between board snapshots.
+
+use crate::batch::{BatchResult, GasBudget};
+use crate::events::emit_event;
+use crate::fees::charge_storage;
+use crate::templates::random_bytes;
+use crate::*;
+use near_sdk::{Balance, Promise};
+
+/// Most generations a matchup may be evolved for.
+pub const MAX_MATCH_GENERATIONS: u64 = 128;
+
+/// Blocks without anything happening after which entrants can call a tournament off, about a
+/// day.
+pub const TOURNAMENT_STALL_TIMEOUT: BlockHeight = 86_400;
+
+/// Blocks after the committed seed block in which the seed can be resolved from the block's
+/// randomness. Later the bracket is seeded from the commitment alone, so waiting for a
+/// favourable block gets nobody anything.
+pub const SEEDING_WINDOW: BlockHeight = 10;
+
+pub type TournamentId = u64;
+
+/// How the winner of a matchup is decided. Ties go to the earlier entry, or to a coin flip
+/// from the seed in seeded tournaments.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TournamentMetric {
+    /// Most live cells after the agreed number of generations.
+    FinalPopulation,
+    /// Most generations before the board dies out, up to the agreed number.
+    SurvivalLength,
+}
+
+impl TournamentMetric {
+    fn score(&self, contender: &Contender) -> u64 {
+        match self {
+            TournamentMetric::FinalPopulation => contender.state.population() as u64,
+            // A board that died on its last step did not survive it.
+            TournamentMetric::SurvivalLength if contender.state.population() == 0 => {
+                contender.survived.saturating_sub(1)
+            }
+            TournamentMetric::SurvivalLength => contender.survived,
+        }
+    }
+}
+
+/// An entry's board partway through a matchup.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Contender {
+    pub state: Board,
+    /// Generations stepped before the board died out.
+    pub survived: u64,
+}
+
+impl Contender {
+    fn new(field: &Board) -> Self {
+        Self { state: field.clone(), survived: 0 }
+    }
+
+    fn advance(&mut self) {
+        if self.state.population() > 0 {
+            self.state = self.state.next_generation();
+            self.survived += 1;
+        }
+    }
+}
+
+/// The matchup `advance_tournament` ran out of gas in, to be continued by the next call.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MatchupProgress {
+    pub generation: u64,
+    pub a: Contender,
+    pub b: Option<Contender>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TournamentEntry {
+    pub board: BoardIndex,
+    pub owner_id: AccountId,
+    /// Snapshot taken on entry, evolved by every won matchup.
+    pub field: Board,
+}
+
+/// Entries are indices into `Tournament::entries`; `b` is None for a bye.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Matchup {
+    pub a: u32,
+    pub b: Option<u32>,
+    pub score_a: u64,
+    pub score_b: u64,
+    pub winner: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Tournament {
+    pub organizer_id: AccountId,
+    pub entry_fee: U128,
+    pub max_entries: u32,
+    /// Generations every matchup is evolved for.
+    pub generations: u64,
+    pub metric: TournamentMetric,
+    pub entries: Vec<TournamentEntry>,
+    /// Number of rounds started; entries close once the first one is.
+    pub round: u32,
+    /// Entries still in the running, in bracket order.
+    pub remaining: Vec<u32>,
+    /// Matchups of the latest round played so far.
+    pub matchups: Vec<Matchup>,
+    /// Next matchup of the round in progress, None between rounds.
+    pub next_matchup: Option<u32>,
+    /// State of the next matchup if it was started but not finished.
+    pub matchup_progress: Option<MatchupProgress>,
+    pub champion: Option<u32>,
+    /// Entry fees collected, paid to the champion's owner.
+    pub prize_pool: U128,
+    /// Block after which the seed can be resolved, set by `commit_seeding`; the seed comes
+    /// from the block's randomness for `SEEDING_WINDOW` blocks.
+    pub seed_block: Option<BlockHeight>,
+    /// Random seed the bracket was shuffled with and ties are broken by.
+    pub seed: Option<Base64VecU8>,
+    /// Set by `cancel_tournament`; entry fees were refunded and nothing more can happen.
+    pub cancelled: bool,
+    /// Block of the latest entry, seeding or advance.
+    pub last_activity_block: BlockHeight,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Opens a tournament organized by the caller. The attached deposit must cover the storage
+    /// the tournament takes; any surplus is refunded.
+    #[payable]
+    pub fn create_tournament(
+        &mut self,
+        entry_fee: U128,
+        max_entries: u32,
+        generations: u64,
+        metric: TournamentMetric,
+    ) -> TournamentId {
+        assert!(max_entries >= 2, "A tournament needs at least 2 entries");
+        assert!(
+            generations > 0 && generations <= MAX_MATCH_GENERATIONS,
+            "Generations must be between 1 and {}",
+            MAX_MATCH_GENERATIONS
+        );
+        let storage_before = env::storage_usage();
+        let tournament = Tournament {
+            organizer_id: env::predecessor_account_id(),
+            entry_fee,
+            max_entries,
+            generations,
+            metric,
+            entries: vec![],
+            round: 0,
+            remaining: vec![],
+            matchups: vec![],
+            next_matchup: None,
+            matchup_progress: None,
+            champion: None,
+            prize_pool: U128(0),
+            seed_block: None,
+            seed: None,
+            cancelled: false,
+            last_activity_block: env::block_index(),
+        };
+        self.tournaments.push(&tournament);
+        charge_storage(storage_before);
+        self.tournaments.len() - 1
+    }
+
+    pub fn get_tournament(&self, id: TournamentId) -> Option<Tournament> {
+        self.tournaments.get(id)
+    }
+
+    /// Enters a snapshot of the board at `index` into tournament `id`. Board owner only, once
+    /// per board, before the first round. Requires the entry fee plus the storage the entry's
+    /// snapshot takes; any surplus is refunded. Cancelling refunds only the entry fee.
+    #[payable]
+    pub fn enter_tournament(&mut self, id: TournamentId, index: BoardIndex) -> u32 {
+        let mut tournament = self.internal_get_tournament(id);
+        let board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert!(
+            tournament.round == 0 && tournament.seed_block.is_none() && !tournament.cancelled,
+            "Entries are closed"
+        );
+        assert!(
+            (tournament.entries.len() as u32) < tournament.max_entries,
+            "The tournament is full"
+        );
+        assert!(
+            !tournament.entries.iter().any(|entry| entry.board == index),
+            "The board is already entered"
+        );
+        let storage_before = env::storage_usage();
+        let entry = tournament.entries.len() as u32;
+        tournament.entries.push(TournamentEntry {
+            board: index,
+            owner_id: board.owner_id,
+            field: board.board,
+        });
+        tournament.remaining.push(entry);
+        tournament.last_activity_block = env::block_index();
+        let fee: Balance = tournament.entry_fee.0;
+        tournament.prize_pool = U128(tournament.prize_pool.0 + fee);
+        self.tournaments.replace(id, &tournament);
+
+        let cost = (env::storage_usage() - storage_before) as Balance * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= fee + cost, "Requires attached deposit of at least {}", fee + cost);
+        if deposit > fee + cost {
+            Promise::new(env::predecessor_account_id()).transfer(deposit - fee - cost);
+        }
+        entry
+    }
+
+    /// Plays matchups of tournament `id`, continuing the round in progress or starting the
+    /// next one: remaining entries are paired in bracket order and an odd one out gets a bye.
+    /// Stops when gas runs low, in the middle of a matchup if need be, after at least one
+    /// generation; the result numbers matchups within the round. The last entry
+    /// standing is the champion and receives the prize pool. Organizer only. The attached
+    /// deposit must cover the storage of the matchups' replay frames; any surplus is refunded.
+    #[payable]
+    pub fn advance_tournament(&mut self, id: TournamentId) -> BatchResult {
+        let storage_before = env::storage_usage();
+        let mut tournament = self.internal_get_tournament(id);
+        assert_eq!(
+            env::predecessor_account_id(),
+            tournament.organizer_id,
+            "Only the organizer can do this"
+        );
+        assert!(tournament.champion.is_none() && !tournament.cancelled, "The tournament is over");
+        assert!(tournament.remaining.len() >= 2, "A tournament needs at least 2 entries");
+        assert!(
+            tournament.seed_block.is_none() || tournament.seed.is_some(),
+            "The seed is not resolved yet"
+        );
+
+        if tournament.next_matchup.is_none() {
+            tournament.round += 1;
+            tournament.matchups.clear();
+        }
+        let first = tournament.matchups.len();
+        let pairs: Vec<Vec<u32>> = tournament.remaining.chunks(2).map(|pair| pair.to_vec()).collect();
+        let mut result = BatchResult::default();
+        let mut budget = GasBudget::new();
+        for (number, pair) in pairs.iter().enumerate().skip(first) {
+            if number > first && !budget.has_room() {
+                result.skipped = (number as u64..pairs.len() as u64).collect();
+                result.next_cursor = Some(number as u64);
+                break;
+            }
+            let field_a = tournament.entries[pair[0] as usize].field.clone();
+            let field_b = pair.get(1).map(|&b| tournament.entries[b as usize].field.clone());
+            let matchup = match tournament.play(pair, &mut budget) {
+                Some(matchup) => matchup,
+                None => {
+                    result.skipped = (number as u64..pairs.len() as u64).collect();
+                    result.next_cursor = Some(number as u64);
+                    break;
+                }
+            };
+            let frame = ReplayFrame::Matchup {
+                round: tournament.round,
+                matchup: matchup.clone(),
+                field_a,
+                field_b,
+            };
+            self.internal_record_frame(MatchId::Tournament(id), &frame);
+            tournament.matchups.push(matchup);
+            result.processed.push(number as u64);
+        }
+        tournament.next_matchup = result.next_cursor.map(|number| number as u32);
+        if tournament.next_matchup.is_none() {
+            self.internal_finish_round(id, &mut tournament);
+        }
+        tournament.last_activity_block = env::block_index();
+        self.tournaments.replace(id, &tournament);
+        charge_storage(storage_before);
+        result
+    }
+
+    /// Closes entries of tournament `id` and commits to seeding it from a block after
+    /// `seed_block`, which must be in the future so nobody knows the seed yet. Organizer only.
+    pub fn commit_seeding(&mut self, id: TournamentId, seed_block: BlockHeight) {
+        let mut tournament = self.internal_get_tournament(id);
+        assert_eq!(
+            env::predecessor_account_id(),
+            tournament.organizer_id,
+            "Only the organizer can do this"
+        );
+        assert!(tournament.round == 0, "The tournament has started");
+        assert!(!tournament.cancelled, "The tournament is over");
+        assert!(tournament.seed_block.is_none(), "The seeding is already committed");
+        assert!(seed_block > env::block_index(), "The block has already passed");
+        tournament.seed_block = Some(seed_block);
+        tournament.last_activity_block = env::block_index();
+        self.tournaments.replace(id, &tournament);
+    }
+
+    /// Takes the random seed of the current block, which has to come after the committed one
+    /// and at most `SEEDING_WINDOW` blocks later, and shuffles the bracket with it. Once the
+    /// window is over the seed is derived from the tournament and the committed block instead,
+    /// so the block doesn't depend on when someone calls. Anyone can call it, so the organizer
+    /// can't hold the seeding back.
+    pub fn resolve_seeding(&mut self, id: TournamentId) -> Vec<u32> {
+        let mut tournament = self.internal_get_tournament(id);
+        let seed_block = tournament.seed_block.expect("The seeding is not committed");
+        assert!(tournament.seed.is_none(), "The seed is already resolved");
+        assert!(!tournament.cancelled, "The tournament is over");
+        assert!(env::block_index() > seed_block, "The seed block has not passed yet");
+        let seed = if env::block_index() <= seed_block + SEEDING_WINDOW {
+            env::random_seed()
+        } else {
+            env::sha256(&(id, seed_block).try_to_vec().unwrap())
+        };
+        // Fisher-Yates, four random bytes per swap.
+        let random = random_bytes(&seed, tournament.remaining.len() * 4);
+        for i in (1..tournament.remaining.len()).rev() {
+            let roll = u32::from_le_bytes(random[i * 4..i * 4 + 4].try_into().unwrap());
+            tournament.remaining.swap(i, roll as usize % (i + 1));
+        }
+        tournament.seed = Some(Base64VecU8(seed));
+        tournament.last_activity_block = env::block_index();
+        self.tournaments.replace(id, &tournament);
+        tournament.remaining
+    }
+
+    /// Calls off tournament `id` before it has a champion, for example when fewer than 2
+    /// boards entered, and refunds every entry fee to the owner who paid it. The organizer can
+    /// cancel at any time; entrants once nothing has happened for `TOURNAMENT_STALL_TIMEOUT`
+    /// blocks.
+    pub fn cancel_tournament(&mut self, id: TournamentId) {
+        let mut tournament = self.internal_get_tournament(id);
+        assert!(tournament.champion.is_none() && !tournament.cancelled, "The tournament is over");
+        let account_id = env::predecessor_account_id();
+        if account_id != tournament.organizer_id {
+            assert!(
+                tournament.entries.iter().any(|entry| entry.owner_id == account_id),
+                "Only the organizer or an entrant can do this"
+            );
+            assert!(
+                env::block_index() > tournament.last_activity_block + TOURNAMENT_STALL_TIMEOUT,
+                "The tournament is still active"
+            );
+        }
+        let fee: Balance = tournament.entry_fee.0;
+        if fee > 0 {
+            for entry in &tournament.entries {
+                Promise::new(entry.owner_id.clone()).transfer(fee);
+            }
+        }
+        tournament.prize_pool = U128(0);
+        tournament.cancelled = true;
+        self.tournaments.replace(id, &tournament);
+    }
+}
+
+impl Tournament {
+    /// Evolves a pair of entries, or a single one with a bye, and keeps the winner's state.
+    /// Returns None, keeping the progress, if gas runs low before the last generation.
+    fn play(&mut self, pair: &[u32], budget: &mut GasBudget) -> Option<Matchup> {
+        let a = pair[0];
+        let mut progress = self.matchup_progress.take().unwrap_or_else(|| MatchupProgress {
+            generation: 0,
+            a: Contender::new(&self.entries[a as usize].field),
+            b: pair.get(1).map(|&b| Contender::new(&self.entries[b as usize].field)),
+        });
+        while progress.generation < self.generations {
+            progress.a.advance();
+            if let Some(b) = progress.b.as_mut() {
+                b.advance();
+            }
+            progress.generation += 1;
+            if progress.generation < self.generations && !budget.has_room() {
+                self.matchup_progress = Some(progress);
+                return None;
+            }
+        }
+        let score_a = self.metric.score(&progress.a);
+        let field_a = progress.a.state;
+        Some(match (pair.get(1), progress.b) {
+            (Some(&b), Some(contender_b)) => {
+                let score_b = self.metric.score(&contender_b);
+                let field_b = contender_b.state;
+                let b_wins = match &self.seed {
+                    Some(seed) if score_a == score_b => {
+                        let mut flip = seed.0.clone();
+                        flip.extend(&self.round.to_le_bytes());
+                        flip.extend(&a.to_le_bytes());
+                        flip.extend(&b.to_le_bytes());
+                        env::sha256(&flip)[0] & 1 == 1
+                    }
+                    _ => score_b > score_a,
+                };
+                let (winner, field) = if b_wins { (b, field_b) } else { (a, field_a) };
+                self.entries[winner as usize].field = field;
+                Matchup { a, b: Some(b), score_a, score_b, winner }
+            }
+            _ => {
+                self.entries[a as usize].field = field_a;
+                Matchup { a, b: None, score_a, score_b: 0, winner: a }
+            }
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct RoundData<'a> {
+    tournament: TournamentId,
+    round: u32,
+    matchups: &'a [Matchup],
+}
+
+impl Contract {
+    /// Advances the winners of a fully played round and crowns the champion once one is left.
+    fn internal_finish_round(&mut self, id: TournamentId, tournament: &mut Tournament) {
+        tournament.remaining = tournament.matchups.iter().map(|matchup| matchup.winner).collect();
+        emit_event(
+            "tournament_round",
+            RoundData {
+                tournament: id,
+                round: tournament.round,
+                matchups: &tournament.matchups,
+            },
+        );
+        if let [champion] = tournament.remaining[..] {
+            tournament.champion = Some(champion);
+            let prize = tournament.prize_pool.0;
+            tournament.prize_pool = U128(0);
+            let entry = tournament.entries[champion as usize].clone();
+            if prize > 0 {
+                Promise::new(entry.owner_id.clone()).transfer(prize);
+            }
+            let achievement = Achievement {
+                kind: AchievementKind::TournamentChampion { tournament: id },
+                board: entry.board,
+                block_height: env::block_index(),
+                season: None,
+            };
+            self.internal_award(&entry.owner_id, achievement);
+        }
+    }
+
+    pub(crate) fn internal_get_tournament(&self, id: TournamentId) -> Tournament {
+        self.tournaments.get(id).expect("No tournament")
+    }
+}
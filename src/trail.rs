@@ -0,0 +1,99 @@
+//! Fading trails: for every cell, how recently it was alive. Live cells are at the board's
+//! trail length and every step a dead cell's counter drops by one, down to 0.
+
+use crate::fees::charge_storage;
+use crate::*;
+
+/// Longest trail, so every counter renders as one digit.
+pub const MAX_TRAIL_LENGTH: u8 = 9;
+
+/// Trail counters of every cell, row by row.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Trail {
+    pub width: u32,
+    pub height: u32,
+    /// Counter of live cells.
+    pub length: u8,
+    pub counters: Vec<u8>,
+}
+
+/// Rows of `board` with live cells as `X`, fading cells as their counter and the rest as `.`.
+pub fn render_trail(board: &Board, counters: &[u8]) -> Vec<String> {
+    (0..board.height())
+        .map(|y| {
+            (0..board.width())
+                .map(|x| match counters[y * board.width() + x] {
+                    _ if board.is_bit_set(x, y) => 'X',
+                    0 => '.',
+                    counter => char::from(b'0' + counter),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts recording a trail of `length` steps for the board at `index`, from the current
+    /// generation, or stops with `None`. Owner only. The attached deposit must cover the
+    /// storage of the counters; any surplus is refunded.
+    #[payable]
+    pub fn set_trail(&mut self, index: BoardIndex, length: Option<u8>) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        if let Some(length) = length {
+            assert!(
+                (2..=MAX_TRAIL_LENGTH).contains(&length),
+                "Trail length must be between 2 and {}",
+                MAX_TRAIL_LENGTH
+            );
+        }
+        let storage_before = env::storage_usage();
+        board.trail_length = length;
+        self.trails.remove(&index);
+        if board.trail_length.is_some() {
+            self.internal_update_trail(index, &board);
+        }
+        self.internal_save(index, &board);
+        charge_storage(storage_before);
+        board
+    }
+
+    /// None unless the board at `index` records a trail.
+    pub fn get_trail(&self, index: BoardIndex) -> Option<Trail> {
+        let board = self.internal_get_board(index);
+        let length = board.trail_length?;
+        Some(Trail {
+            width: board.board.width,
+            height: board.board.height,
+            length,
+            counters: self.internal_trail_counters(index, &board),
+        })
+    }
+}
+
+impl Contract {
+    /// Counters of a board that was just resized are reset on its next step.
+    pub(crate) fn internal_trail_counters(&self, index: BoardIndex, board: &BoardWithBlock) -> Vec<u8> {
+        let cells = board.board.width() * board.board.height();
+        self.trails
+            .get(&index)
+            .filter(|counters| counters.len() == cells)
+            .unwrap_or_else(|| vec![0; cells])
+    }
+
+    /// Fades every dead cell by one step and marks live cells as freshly visited.
+    pub(crate) fn internal_update_trail(&mut self, index: BoardIndex, board: &BoardWithBlock) {
+        let length = board.trail_length.expect("The board records a trail");
+        let mut counters = self.internal_trail_counters(index, board);
+        for y in 0..board.board.height() {
+            for x in 0..board.board.width() {
+                let counter = &mut counters[y * board.board.width() + x];
+                *counter = if board.board.is_bit_set(x, y) { length } else { counter.saturating_sub(1) };
+            }
+        }
+        self.trails.insert(&index, &counters);
+    }
+}
@@ -0,0 +1,51 @@
+//! Protocol fees are booked into an explicit treasury balance and only leave the contract
+//! through `withdraw_treasury`, apart from the balance needed for storage.
+
+use crate::*;
+use near_sdk::{Balance, Promise};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Treasury {
+    /// Fees collected and not withdrawn yet.
+    pub balance: U128,
+    pub total_collected: U128,
+    pub total_withdrawn: U128,
+}
+
+impl Default for Treasury {
+    fn default() -> Self {
+        Self {
+            balance: U128(0),
+            total_collected: U128(0),
+            total_withdrawn: U128(0),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_treasury(&self) -> Treasury {
+        self.treasury.clone()
+    }
+
+    /// Sends `amount` of collected fees to `to`. Contract owner only.
+    pub fn withdraw_treasury(&mut self, amount: U128, to: AccountId) -> Treasury {
+        self.assert_contract_owner();
+        assert!(env::is_valid_account_id(to.as_bytes()), "Invalid receiver");
+        let amount: Balance = amount.0;
+        assert!(amount > 0, "Amount must be positive");
+        assert!(amount <= self.treasury.balance.0, "The treasury holds only {}", self.treasury.balance.0);
+        self.treasury.balance = U128(self.treasury.balance.0 - amount);
+        self.treasury.total_withdrawn = U128(self.treasury.total_withdrawn.0 + amount);
+        Promise::new(to).transfer(amount);
+        self.treasury.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_collect_fee(&mut self, fee: Balance) {
+        self.treasury.balance = U128(self.treasury.balance.0 + fee);
+        self.treasury.total_collected = U128(self.treasury.total_collected.0 + fee);
+    }
+}
@@ -0,0 +1,169 @@
+//! Soul-bound trophy tokens minted for every achievement. They follow the NEP-171 core,
+//! metadata and enumeration views, but cannot be transferred.
+
+use crate::*;
+use near_contract_standards::non_fungible_token::metadata::{NFTContractMetadata, TokenMetadata, NFT_METADATA_SPEC};
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_sdk::serde_json::json;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Trophy {
+    pub owner_id: AccountId,
+    pub achievement: Achievement,
+    /// `Board::hash` of the board when the achievement was awarded.
+    pub board_hash: Base64VecU8,
+    /// Nanoseconds since the Unix epoch.
+    pub issued_at: u64,
+}
+
+impl Trophy {
+    fn to_token(&self, token_id: u64) -> Token {
+        let title = match &self.achievement.kind {
+            AchievementKind::SpaceshipDiscovered { period, dx, dy } => {
+                format!("Discovered a {} spaceship", analysis::format_speed(*period, *dx, *dy))
+            }
+            AchievementKind::TournamentChampion { tournament } => format!("Champion of tournament #{}", tournament),
+        };
+        let metadata = TokenMetadata {
+            title: Some(title),
+            description: Some(format!("Awarded for board #{}", self.achievement.board)),
+            media: None,
+            media_hash: None,
+            copies: Some(1),
+            issued_at: Some(iso_date(self.issued_at)),
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: Some(json!({ "achievement": self.achievement, "board_hash": self.board_hash }).to_string()),
+            reference: None,
+            reference_hash: None,
+        };
+        Token {
+            token_id: token_id.to_string(),
+            owner_id: self.owner_id.clone(),
+            metadata: Some(metadata),
+            approved_account_ids: None,
+        }
+    }
+}
+
+/// `YYYY-MM-DD` of a timestamp in nanoseconds since the Unix epoch.
+fn iso_date(timestamp: u64) -> String {
+    // Civil-from-days, proleptic Gregorian calendar.
+    let days = (timestamp / 1_000_000_000 / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn parse_token_id(token_id: &TokenId) -> Option<u64> {
+    token_id.parse().ok()
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Game of Life trophies".to_string(),
+            symbol: "LIFE".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    pub fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        let id = parse_token_id(&token_id)?;
+        self.trophies.get(id).map(|trophy| trophy.to_token(id))
+    }
+
+    pub fn nft_total_supply(&self) -> U128 {
+        U128(self.trophies.len() as u128)
+    }
+
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        let from = from_index.map_or(0, |i| i.0 as u64);
+        let limit = limit.unwrap_or(u64::MAX);
+        (from..self.trophies.len())
+            .take(limit as usize)
+            .map(|id| self.trophies.get(id).expect("Missing trophy").to_token(id))
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.trophies_per_owner.get(&account_id).map_or(0, |ids| ids.len()) as u128)
+    }
+
+    pub fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        let from = from_index.map_or(0, |i| i.0 as usize);
+        let limit = limit.unwrap_or(u64::MAX) as usize;
+        self.trophies_per_owner
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from)
+            .take(limit)
+            .map(|id| self.trophies.get(id).expect("Missing trophy").to_token(id))
+            .collect()
+    }
+
+    #[payable]
+    #[allow(unused_variables)]
+    pub fn nft_transfer(&mut self, receiver_id: AccountId, token_id: TokenId, approval_id: Option<u64>, memo: Option<String>) {
+        env::panic(b"Trophies are soul-bound");
+    }
+
+    #[payable]
+    #[allow(unused_variables)]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> bool {
+        env::panic(b"Trophies are soul-bound");
+    }
+}
+
+impl Contract {
+    /// Mints the trophy recording `achievement` to `account_id`.
+    pub(crate) fn internal_mint_trophy(&mut self, account_id: &AccountId, achievement: &Achievement) -> TokenId {
+        let board_hash = self
+            .boards
+            .get(achievement.board)
+            .map(|board| board.board.hash())
+            .unwrap_or_default();
+        let trophy = Trophy {
+            owner_id: account_id.clone(),
+            achievement: achievement.clone(),
+            board_hash: Base64VecU8(board_hash),
+            issued_at: env::block_timestamp(),
+        };
+        let id = self.trophies.len();
+        self.trophies.push(&trophy);
+        let mut owned = self.trophies_per_owner.get(account_id).unwrap_or_default();
+        owned.push(id);
+        self.trophies_per_owner.insert(account_id, &owned);
+
+        let token_id = id.to_string();
+        let event = json!({
+            "standard": "nep171",
+            "version": "1.0.0",
+            "event": "nft_mint",
+            "data": [{ "owner_id": account_id, "token_ids": [token_id] }],
+        });
+        env::log(format!("EVENT_JSON:{}", event).as_bytes());
+        token_id
+    }
+}
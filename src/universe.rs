@@ -0,0 +1,243 @@
+//! The shared universe: boards of the default size can claim a tile coordinate, and the
+//! viewport view stitches neighbouring tiles into one picture.
+//!
+//! Tiles that opt into edge exchange step as parts of one world: cells along their borders
+//! see the cells of neighbouring tiles that opted in too. Every tile is stepped in its own
+//! transaction, so neighbours drift apart. A tile reads a neighbour one generation ahead
+//! from before the neighbour's last step, and any other neighbour as it is now, so frozen or
+//! lagging neighbours never block it. Saving a linked tile sends a `sync_tile` promise to
+//! every linked neighbour that is behind it, which steps the neighbour one generation at a
+//! time, on behalf of and paid by whoever stepped the tile, until it reaches its furthest
+//! neighbour, so linked tiles converge again over the following blocks.
+
+use crate::fees::charge_storage;
+use crate::*;
+use near_sdk::serde_json::json;
+use near_sdk::{Gas, Promise};
+
+/// Gas of every `sync_tile` promise.
+pub const GAS_FOR_SYNC_TILE: Gas = 50_000_000_000_000;
+/// Gas kept back for the rest of the call when sending `sync_tile` promises.
+const SYNC_GAS_RESERVE: Gas = 20_000_000_000_000;
+
+/// Most cells a viewport can cover in either direction.
+pub const MAX_VIEWPORT_SIZE: u32 = 128;
+
+pub type TileCoord = (i32, i32);
+
+#[near_bindgen]
+impl Contract {
+    /// Places the board at `index` on tile (`x`, `y`) of the universe. Only boards of the
+    /// default size can be tiles, and every tile holds one board. Owner only. The attached
+    /// deposit must cover the storage of the tile; any surplus is refunded.
+    #[payable]
+    pub fn claim_tile(&mut self, index: BoardIndex, x: i32, y: i32) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_frozen(&board);
+        assert_not_finalized(&board);
+        assert!(
+            board.board.width() == WIDTH && board.board.height() == HEIGHT,
+            "Tiles must be {}x{}",
+            WIDTH,
+            HEIGHT
+        );
+        assert!(board.tile.is_none(), "The board is already a tile");
+        assert!(self.tiles.get(&(x, y)).is_none(), "The tile is taken");
+        let storage_before = env::storage_usage();
+        self.tiles.insert(&(x, y), &index);
+        board.tile = Some((x, y));
+        self.internal_save(index, &board);
+        charge_storage(storage_before);
+        board
+    }
+
+    /// Takes the board at `index` out of the universe. Owner only.
+    pub fn release_tile(&mut self, index: BoardIndex) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        let tile = board.tile.take().expect("The board is not a tile");
+        self.tiles.remove(&tile);
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Lets cells cross between the tile at `index` and neighbouring tiles that enabled it too.
+    /// Owner only.
+    pub fn set_edge_exchange(&mut self, index: BoardIndex, enabled: bool) -> BoardWithBlock {
+        let mut board = self.internal_get_board(index);
+        assert_owner(&board);
+        assert_not_finalized(&board);
+        assert!(board.tile.is_some(), "The board is not a tile");
+        board.edge_exchange = enabled;
+        self.internal_save(index, &board);
+        board
+    }
+
+    /// Steps the linked tile at `index` once on behalf of `account_id`, whose step of a
+    /// neighbour got ahead of it, with the rate limit, action points and step fee of `step`.
+    /// Sends itself again while the tile is still behind its furthest linked neighbour. Sent by
+    /// the contract itself.
+    #[private]
+    pub fn sync_tile(&mut self, index: BoardIndex, account_id: AccountId) -> BoardWithBlock {
+        let board = self.internal_get_board(index);
+        if !board.edge_exchange || step_blocker(&board).is_some() || !self.internal_is_behind(index, &board) {
+            return board;
+        }
+        let board = self.internal_step_for(&account_id, index);
+        self.internal_sync_neighbours(index, &board, &account_id);
+        if self.internal_is_behind(index, &board) {
+            send_sync(index, &account_id);
+        }
+        board
+    }
+
+    pub fn get_tile(&self, x: i32, y: i32) -> Option<BoardIndex> {
+        self.tiles.get(&(x, y))
+    }
+
+    /// Rows of the `width` x `height` cells of the universe starting at cell (`x`, `y`), `X`
+    /// alive and `.` dead. Tile (0, 0) covers cells (0, 0) to (15, 15); cells of unclaimed
+    /// tiles are dead.
+    pub fn get_universe_viewport(&self, x: i64, y: i64, width: u32, height: u32) -> Vec<String> {
+        assert!(
+            width > 0 && height > 0 && width <= MAX_VIEWPORT_SIZE && height <= MAX_VIEWPORT_SIZE,
+            "Viewports are between 1x1 and {0}x{0} cells",
+            MAX_VIEWPORT_SIZE
+        );
+        let mut tiles: std::collections::HashMap<TileCoord, Option<Board>> = std::collections::HashMap::new();
+        (y..y + height as i64)
+            .map(|cy| {
+                (x..x + width as i64)
+                    .map(|cx| {
+                        let tile = (cx.div_euclid(WIDTH as i64) as i32, cy.div_euclid(HEIGHT as i64) as i32);
+                        let board = tiles.entry(tile).or_insert_with(|| {
+                            self.tiles.get(&tile).map(|index| self.internal_get_board(index).board)
+                        });
+                        let alive = board.as_ref().is_some_and(|board| {
+                            board.is_bit_set(cx.rem_euclid(WIDTH as i64) as usize, cy.rem_euclid(HEIGHT as i64) as usize)
+                        });
+                        if alive { 'X' } else { '.' }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Next generation of the board at `index` computed by `engine`, with the edges of linked
+    /// neighbours taken into account for tiles exchanging edges.
+    pub(crate) fn internal_next_generation(
+        &self,
+        index: BoardIndex,
+        board: &BoardWithBlock,
+        engine: Engine,
+    ) -> Board {
+        if board.tile.is_none() || !board.edge_exchange {
+            return engine.step(&board.board);
+        }
+        let (width, height) = (board.board.width() as i64, board.board.height() as i64);
+        let neighbours: Vec<((i64, i64), Board)> = self
+            .internal_linked_neighbours(index, board)
+            .into_iter()
+            .map(|(offset, _, neighbour)| {
+                // A neighbour one step ahead still remembers the matching generation; any
+                // other, including frozen and lagging ones, lends its latest state.
+                let state = match neighbour.prev_board {
+                    Some(prev) if neighbour.generation == board.generation + 1 => prev,
+                    _ => neighbour.board,
+                };
+                (offset, state)
+            })
+            .collect();
+
+        // The board with a one-cell border borrowed from the neighbours.
+        let mut padded = Board::with_dimensions(width as usize + 2, height as usize + 2);
+        for py in 0..height + 2 {
+            for px in 0..width + 2 {
+                let (cx, cy) = (px - 1, py - 1);
+                let offset = (
+                    if cx < 0 { -1 } else if cx >= width { 1 } else { 0 },
+                    if cy < 0 { -1 } else if cy >= height { 1 } else { 0 },
+                );
+                let alive = if offset == (0, 0) {
+                    board.board.is_bit_set(cx as usize, cy as usize)
+                } else {
+                    neighbours.iter().any(|(at, state)| {
+                        *at == offset
+                            && state.is_bit_set((cx - offset.0 * width) as usize, (cy - offset.1 * height) as usize)
+                    })
+                };
+                if alive {
+                    padded.set_bit(px as usize, py as usize, true);
+                }
+            }
+        }
+        engine.step(&padded).crop(1, 1, width as usize, height as usize)
+    }
+
+    /// Neighbours of the tile at `index` that exchange edges with it, with their offsets and
+    /// indices. Empty
+    /// unless the board is a tile exchanging edges itself.
+    pub(crate) fn internal_linked_neighbours(
+        &self,
+        index: BoardIndex,
+        board: &BoardWithBlock,
+    ) -> Vec<((i64, i64), BoardIndex, BoardWithBlock)> {
+        let (tile_x, tile_y) = match board.tile {
+            Some(tile) if board.edge_exchange => tile,
+            _ => return vec![],
+        };
+        [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)]
+            .into_iter()
+            .filter_map(|(dx, dy)| {
+                let neighbour_index = self.tiles.get(&(tile_x + dx as i32, tile_y + dy as i32))?;
+                if neighbour_index == index {
+                    return None;
+                }
+                let neighbour = self.internal_get_board(neighbour_index);
+                neighbour.edge_exchange.then_some(((dx, dy), neighbour_index, neighbour))
+            })
+            .collect()
+    }
+
+    /// Whether a linked neighbour of the tile at `index` is ahead of it.
+    fn internal_is_behind(&self, index: BoardIndex, board: &BoardWithBlock) -> bool {
+        self.internal_linked_neighbours(index, board)
+            .into_iter()
+            .any(|(_, _, neighbour)| neighbour.generation > board.generation)
+    }
+
+    /// Sends `sync_tile` on behalf of `account_id` to every linked neighbour of the tile at
+    /// `index` that can step and is behind it, as long as the call has gas to spare. The
+    /// promises carry no deposit, so neighbours that would cost `account_id` a step fee are
+    /// left to catch up when they are stepped themselves.
+    pub(crate) fn internal_sync_neighbours(&self, index: BoardIndex, board: &BoardWithBlock, account_id: &AccountId) {
+        for (_, neighbour_index, neighbour) in self.internal_linked_neighbours(index, board) {
+            if neighbour.generation >= board.generation
+                || step_blocker(&neighbour).is_some()
+                || self.internal_step_fee(account_id, &neighbour) > 0
+            {
+                continue;
+            }
+            if !send_sync(neighbour_index, account_id) {
+                break;
+            }
+        }
+    }
+}
+
+/// Sends `sync_tile` for the tile at `index`, false if the call has no gas to spare for it.
+fn send_sync(index: BoardIndex, account_id: &AccountId) -> bool {
+    if env::prepaid_gas().saturating_sub(env::used_gas()) < GAS_FOR_SYNC_TILE + SYNC_GAS_RESERVE {
+        return false;
+    }
+    Promise::new(env::current_account_id()).function_call(
+        b"sync_tile".to_vec(),
+        json!({ "index": index, "account_id": account_id }).to_string().into_bytes(),
+        0,
+        GAS_FOR_SYNC_TILE,
+    );
+    true
+}